@@ -0,0 +1,143 @@
+use crate::analysis::predicates::get_all_predicates;
+use crate::analysis::predicates::reserved::IsGloballyRoutablePredicate;
+use plugin::contracts::{AbsorbField, Predicate};
+use polars::prelude::*;
+use std::net::Ipv6Addr;
+
+/// Name the globally-routable check is reported under; it is not a filter predicate.
+pub const GLOBALLY_ROUTABLE: &str = "globally_routable";
+
+/// Reserved blocks that do not overlap, so no address can match two of them, and none
+/// of which is globally routable.
+const DISJOINT_RESERVED: &[&str] = &[
+    "loopback",
+    "unspecified",
+    "link_local",
+    "unique_local",
+    "multicast",
+    "documentation",
+    "documentation_2",
+];
+
+/// Lists the predicates each address matches and flags combinations that should be
+/// impossible, which point at a predicate bug or corrupt input.
+pub struct PredicateAnomalyAnalysis {
+    predicates: Vec<(&'static str, fn(Ipv6Addr) -> bool)>,
+    flagged_only: bool,
+    addresses: Vec<String>,
+    matches: Vec<String>,
+    reasons: Vec<Option<String>>,
+}
+
+impl PredicateAnomalyAnalysis {
+    pub fn new(flagged_only: bool) -> Self {
+        let mut predicates: Vec<(&'static str, fn(Ipv6Addr) -> bool)> = get_all_predicates()
+            .into_iter()
+            .map(|(name, _, predicate_fn)| (name, predicate_fn))
+            .collect();
+        predicates.push((GLOBALLY_ROUTABLE, |addr| {
+            IsGloballyRoutablePredicate.predicate(addr)
+        }));
+
+        Self {
+            predicates,
+            flagged_only,
+            addresses: Vec::new(),
+            matches: Vec::new(),
+            reasons: Vec::new(),
+        }
+    }
+
+    /// Names of the predicates `addr` matches, in registry order.
+    pub fn matching(&self, addr: Ipv6Addr) -> Vec<&'static str> {
+        self.predicates
+            .iter()
+            .filter(|(_, predicate_fn)| predicate_fn(addr))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// Why `matched` is an impossible combination, or `None` if it is consistent.
+    pub fn anomaly(matched: &[&str]) -> Option<String> {
+        let reserved: Vec<&str> = matched
+            .iter()
+            .copied()
+            .filter(|name| DISJOINT_RESERVED.contains(name))
+            .collect();
+
+        if matched.contains(&GLOBALLY_ROUTABLE) && !reserved.is_empty() {
+            Some(format!("{} and {}", GLOBALLY_ROUTABLE, reserved.join(", ")))
+        } else if reserved.len() > 1 {
+            Some(format!("disjoint ranges {}", reserved.join(", ")))
+        } else {
+            None
+        }
+    }
+}
+
+impl AbsorbField<Ipv6Addr> for PredicateAnomalyAnalysis {
+    fn absorb(&mut self, addr: Ipv6Addr) {
+        let matched = self.matching(addr);
+        let reason = Self::anomaly(&matched);
+        if self.flagged_only && reason.is_none() {
+            return;
+        }
+        self.addresses.push(addr.to_string());
+        self.matches.push(matched.join(","));
+        self.reasons.push(reason);
+    }
+
+    fn finalize(&mut self) -> DataFrame {
+        let anomalous: Vec<bool> = self.reasons.iter().map(Option::is_some).collect();
+
+        DataFrame::new(vec![
+            Column::new("address".into(), std::mem::take(&mut self.addresses)),
+            Column::new("predicates".into(), std::mem::take(&mut self.matches)),
+            Column::new("anomalous".into(), anomalous),
+            Column::new("reason".into(), std::mem::take(&mut self.reasons)),
+        ])
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_documentation_address_is_not_globally_routable() {
+        let analyzer = PredicateAnomalyAnalysis::new(false);
+        let matched = analyzer.matching("2001:db8::1".parse().unwrap());
+        assert!(matched.contains(&"documentation"), "{:?}", matched);
+        assert!(!matched.contains(&GLOBALLY_ROUTABLE), "{:?}", matched);
+        assert_eq!(PredicateAnomalyAnalysis::anomaly(&matched), None);
+    }
+
+    #[test]
+    fn test_flags_impossible_combinations() {
+        let reason = PredicateAnomalyAnalysis::anomaly(&["documentation", GLOBALLY_ROUTABLE]);
+        assert_eq!(
+            reason.as_deref(),
+            Some("globally_routable and documentation")
+        );
+        assert!(PredicateAnomalyAnalysis::anomaly(&["loopback", "link_local"]).is_some());
+        assert!(PredicateAnomalyAnalysis::anomaly(&["multicast", "solicited_node"]).is_none());
+    }
+
+    #[test]
+    fn test_flagged_only_keeps_just_anomalies() {
+        let mut analyzer = PredicateAnomalyAnalysis::new(true);
+        for addr in ["2001:db8::1", "fe80::1", "2600:1f18::8a2e:370:7334:9f1b"] {
+            analyzer.absorb(addr.parse().unwrap());
+        }
+        assert_eq!(analyzer.finalize().height(), 0);
+
+        let mut analyzer = PredicateAnomalyAnalysis::new(false);
+        analyzer.absorb("2600:1f18::8a2e:370:7334:9f1b".parse().unwrap());
+        let out = analyzer.finalize();
+        assert_eq!(
+            out.column("predicates").unwrap().str().unwrap().get(0),
+            Some(GLOBALLY_ROUTABLE)
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use super::{
+    CountAnalysis, DispersionAnalysis, MultiAnalyzer, ShannonEntropyAnalysis, SubnetAnalysis,
+};
+use plugin::contracts::AbsorbField;
+use polars::prelude::*;
+use std::net::Ipv6Addr;
+
+/// Runs counts, dispersion, entropy and subnets side by side, so one pass over the
+/// addresses feeds all four. The report has one `analysis`, `metric`, `value` row per
+/// statistic, with values rendered as strings since the analyses' types differ.
+pub struct CombinedAnalysis {
+    analyzers: MultiAnalyzer,
+}
+
+impl CombinedAnalysis {
+    pub fn new(max_subnets: usize, prefix_length: u8) -> Self {
+        // Registration order matches the order `finalize` reads the results in
+        let mut analyzers = MultiAnalyzer::new();
+        analyzers.add(CountAnalysis::new(None));
+        analyzers.add(DispersionAnalysis::new());
+        analyzers.add(ShannonEntropyAnalysis::new_with_options(0, 128));
+        analyzers.add(SubnetAnalysis::new_with_options(max_subnets, prefix_length));
+        Self { analyzers }
+    }
+}
+
+impl AbsorbField<Ipv6Addr> for CombinedAnalysis {
+    fn absorb(&mut self, addr: Ipv6Addr) {
+        self.analyzers.absorb(addr);
+    }
+
+    fn finalize(&mut self) -> DataFrame {
+        let [counts, dispersion, entropy, subnets]: [DataFrame; 4] = self
+            .analyzers
+            .finalize_all()
+            .try_into()
+            .expect("four analyzers are registered");
+
+        let mut report = Report::default();
+        report.keyed("counts", &counts, "predicate");
+        report.columns(
+            "dispersion",
+            &dispersion,
+            &[
+                "min_distance",
+                "max_distance",
+                "avg_distance",
+                "total_pairs",
+            ],
+        );
+        report.columns("entropy", &entropy, &["entropy", "total_bits"]);
+        report.keyed("subnets", &subnets, "subnet");
+
+        DataFrame::new(vec![
+            Column::new("analysis".into(), &report.analyses),
+            Column::new("metric".into(), &report.metrics),
+            Column::new("value".into(), &report.values),
+        ])
+        .unwrap()
+    }
+}
+
+#[derive(Default)]
+struct Report {
+    analyses: Vec<&'static str>,
+    metrics: Vec<String>,
+    values: Vec<String>,
+}
+
+impl Report {
+    fn push(&mut self, analysis: &'static str, metric: String, value: String) {
+        self.analyses.push(analysis);
+        self.metrics.push(metric);
+        self.values.push(value);
+    }
+
+    /// One row per listed column of a single-row result.
+    fn columns(&mut self, analysis: &'static str, df: &DataFrame, names: &[&str]) {
+        for name in names {
+            self.push(analysis, name.to_string(), cell(df, name, 0));
+        }
+    }
+
+    /// One row per result row, named by `key` and valued by its `count`.
+    fn keyed(&mut self, analysis: &'static str, df: &DataFrame, key: &str) {
+        for i in 0..df.height() {
+            self.push(analysis, cell(df, key, i), cell(df, "count", i));
+        }
+    }
+}
+
+fn cell(df: &DataFrame, column: &str, row: usize) -> String {
+    match df.column(column).and_then(|c| c.get(row)) {
+        Ok(AnyValue::String(s)) => s.to_string(),
+        Ok(AnyValue::Null) | Err(_) => String::new(),
+        Ok(value) => value.to_string(),
+    }
+}
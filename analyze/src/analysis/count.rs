@@ -6,40 +6,42 @@ use std::net::Ipv6Addr;
 
 pub struct CountAnalysis {
     predicate_name: Option<String>,
+    predicates: Vec<(&'static str, fn(Ipv6Addr) -> bool)>,
     predicate_counts: HashMap<&'static str, u64>,
     total_addresses: u64,
 }
 
 impl CountAnalysis {
+    /// Counts matches for every predicate, or only for `predicate_name` when given.
     pub fn new(predicate_name: Option<String>) -> Self {
-        let all_predicates = get_all_predicates();
-        let mut predicate_counts = HashMap::new();
-        for (name, _) in &all_predicates {
-            predicate_counts.insert(*name, 0);
-        }
+        let predicates: Vec<_> = get_all_predicates()
+            .into_iter()
+            .filter(|(name, _, _)| {
+                predicate_name
+                    .as_deref()
+                    .is_none_or(|wanted| wanted == *name)
+            })
+            .map(|(name, _, predicate_fn)| (name, predicate_fn))
+            .collect();
+        let predicate_counts = predicates.iter().map(|(name, _)| (*name, 0)).collect();
         Self {
             predicate_name,
+            predicates,
             predicate_counts,
             total_addresses: 0,
         }
     }
+
+    /// Names of the predicates evaluated for each absorbed address.
+    pub fn evaluated_predicates(&self) -> Vec<&'static str> {
+        self.predicates.iter().map(|(name, _)| *name).collect()
+    }
 }
 
 impl AbsorbField<Ipv6Addr> for CountAnalysis {
-    type Config = ();
-
     fn absorb(&mut self, addr: Ipv6Addr) {
         self.total_addresses += 1;
-        let all_predicates = get_all_predicates();
-        let predicates_to_check = if let Some(ref name) = self.predicate_name {
-            all_predicates
-                .into_iter()
-                .filter(|(pred_name, _)| pred_name == name)
-                .collect::<Vec<_>>()
-        } else {
-            all_predicates
-        };
-        for (name, predicate_fn) in predicates_to_check {
+        for (name, predicate_fn) in &self.predicates {
             if predicate_fn(addr) {
                 let count = self.predicate_counts.get_mut(name).unwrap();
                 *count += 1;
@@ -52,7 +54,8 @@ impl AbsorbField<Ipv6Addr> for CountAnalysis {
         let mut counts = Vec::new();
         let mut percentages = Vec::new();
         for (name, count) in &self.predicate_counts {
-            if *count > 0 {
+            // A single requested predicate is reported even when nothing matched
+            if *count > 0 || self.predicate_name.is_some() {
                 predicate_names.push(name.to_string());
                 counts.push(*count);
                 let percentage = if self.total_addresses > 0 {
@@ -94,3 +97,25 @@ impl CountResults {
         Self { dataframe }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_predicate_only_evaluates_that_predicate() {
+        let mut analyzer = CountAnalysis::new(Some("link_local".to_string()));
+        assert_eq!(analyzer.evaluated_predicates(), ["link_local"]);
+
+        for addr in ["fe80::1", "ff02::1", "::1"] {
+            analyzer.absorb(addr.parse().unwrap());
+        }
+        let out = analyzer.finalize();
+        assert_eq!(out.height(), 1);
+        assert_eq!(
+            out.column("predicate").unwrap().str().unwrap().get(0),
+            Some("link_local")
+        );
+        assert_eq!(out.column("count").unwrap().u64().unwrap().get(0), Some(1));
+    }
+}
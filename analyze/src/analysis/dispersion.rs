@@ -1,3 +1,4 @@
+use indicatif::ProgressStyle;
 use itertools::Itertools;
 use plugin::contracts::{AbsorbField, MyField};
 use polars::prelude::*;
@@ -59,9 +60,6 @@ impl std::fmt::Display for DispersionResults {
     }
 }
 
-#[derive(Default)]
-pub struct DispersionConfig;
-
 pub struct DispersionAnalysis {
     addresses: Vec<Ipv6Addr>,
 }
@@ -75,8 +73,6 @@ impl DispersionAnalysis {
 }
 
 impl AbsorbField<Ipv6Addr> for DispersionAnalysis {
-    type Config = DispersionConfig;
-
     fn absorb(&mut self, addr: Ipv6Addr) {
         self.addresses.push(addr);
     }
@@ -87,6 +83,17 @@ impl AbsorbField<Ipv6Addr> for DispersionAnalysis {
         let mut total_distance = 0u64;
         let mut pair_count = 0u64;
 
+        let n = self.addresses.len() as u64;
+        let total_pairs = n * n.saturating_sub(1) / 2;
+        let pb = crate::progress::new_progress_bar(total_pairs);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] {msg} [{bar:20.cyan/grey}] {pos}/{len} ({eta})")
+                .expect("Failed to create progress bar template")
+                .progress_chars("█░"),
+        );
+        pb.set_message("Computing pairwise distances...");
+
         for (a, b) in self.addresses.iter().combinations(2).map(|v| (v[0], v[1])) {
             let a_u128 = u128::from_be_bytes(a.octets());
             let b_u128 = u128::from_be_bytes(b.octets());
@@ -95,8 +102,15 @@ impl AbsorbField<Ipv6Addr> for DispersionAnalysis {
             max_distance = max_distance.max(dist);
             total_distance = total_distance.wrapping_add(dist as u64);
             pair_count += 1;
+
+            if pair_count % 10_000 == 0 {
+                pb.set_position(pair_count);
+            }
         }
 
+        pb.set_position(pair_count);
+        pb.finish_with_message("Dispersion complete!");
+
         let avg_distance = if pair_count > 0 {
             total_distance as f64 / pair_count as f64
         } else {
@@ -114,3 +128,22 @@ impl AbsorbField<Ipv6Addr> for DispersionAnalysis {
         .unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dispersion_results_with_progress() {
+        let mut analyzer = DispersionAnalysis::new();
+        for i in 0..200u128 {
+            analyzer.absorb(Ipv6Addr::from(i));
+        }
+        let results = DispersionResults::from_dataframe(&analyzer.finalize());
+
+        assert_eq!(results.total_pairs, 200 * 199 / 2);
+        assert_eq!(results.min_distance, 1);
+        // 0b0111_1111 vs 0b1000_0000 differ in every one of the low 8 bits
+        assert_eq!(results.max_distance, 8);
+    }
+}
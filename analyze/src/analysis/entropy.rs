@@ -4,11 +4,6 @@ use std::collections::HashMap;
 use std::fmt;
 use std::net::Ipv6Addr;
 
-pub struct ShannonEntropyConfig {
-    pub start_bit: u8,
-    pub end_bit: u8,
-}
-
 pub struct ShannonEntropyAnalysis {
     start_bit: u8,
     end_bit: u8,
@@ -28,8 +23,6 @@ impl ShannonEntropyAnalysis {
 }
 
 impl AbsorbField<Ipv6Addr> for ShannonEntropyAnalysis {
-    type Config = ShannonEntropyConfig;
-
     fn absorb(&mut self, addr: Ipv6Addr) {
         let bytes = addr.octets();
         for i in self.start_bit..self.end_bit {
@@ -62,6 +55,58 @@ impl AbsorbField<Ipv6Addr> for ShannonEntropyAnalysis {
     }
 }
 
+/// Shannon entropy, in bits per symbol, of a histogram of symbol counts.
+pub fn shannon_entropy(counts: &[u64]) -> f64 {
+    let total: u64 = counts.iter().sum();
+    counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Byte-level entropy: a 256-bucket distribution over the bytes `start_byte..end_byte`
+/// of every address, reported in bits per byte (0.0 to 8.0).
+pub struct ByteEntropyAnalysis {
+    start_byte: usize,
+    end_byte: usize,
+    byte_counts: [u64; 256],
+}
+
+impl ByteEntropyAnalysis {
+    pub fn new_with_options(start_byte: u8, end_byte: u8) -> Self {
+        Self {
+            start_byte: start_byte as usize,
+            end_byte: (end_byte as usize).min(16),
+            byte_counts: [0; 256],
+        }
+    }
+}
+
+impl AbsorbField<Ipv6Addr> for ByteEntropyAnalysis {
+    fn absorb(&mut self, addr: Ipv6Addr) {
+        let bytes = addr.octets();
+        for &b in bytes.get(self.start_byte..self.end_byte).unwrap_or(&[]) {
+            self.byte_counts[b as usize] += 1;
+        }
+    }
+
+    fn finalize(&mut self) -> DataFrame {
+        let total_bytes: u64 = self.byte_counts.iter().sum();
+        let distinct_bytes = self.byte_counts.iter().filter(|&&c| c > 0).count() as u64;
+
+        DataFrame::new(vec![
+            Column::new("entropy".into(), &[shannon_entropy(&self.byte_counts)]),
+            Column::new("total_bytes".into(), &[total_bytes]),
+            Column::new("distinct_bytes".into(), &[distinct_bytes]),
+        ])
+        .unwrap()
+    }
+}
+
 #[derive(Debug)]
 pub struct ShannonEntropyResults {
     pub entropy: f64,
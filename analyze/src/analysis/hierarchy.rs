@@ -0,0 +1,148 @@
+use plugin::contracts::AbsorbField;
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::net::Ipv6Addr;
+
+/// Counts addresses per subnet at several prefix lengths in a single pass.
+pub struct HierarchyAnalysis {
+    /// Per prefix length, shortest first: network bits -> address count
+    levels: Vec<(u8, HashMap<u128, u64>)>,
+    max_subnets: usize,
+}
+
+impl HierarchyAnalysis {
+    /// Fails if a prefix length is longer than 128 bits.
+    pub fn new(prefix_lengths: &[u8], max_subnets: usize) -> Result<Self, String> {
+        if let Some(len) = prefix_lengths.iter().find(|len| **len > 128) {
+            return Err(format!(
+                "Prefix length must be between 0 and 128, got {}",
+                len
+            ));
+        }
+        let mut prefix_lengths = prefix_lengths.to_vec();
+        prefix_lengths.sort_unstable();
+        prefix_lengths.dedup();
+
+        Ok(Self {
+            levels: prefix_lengths
+                .into_iter()
+                .map(|len| (len, HashMap::new()))
+                .collect(),
+            max_subnets,
+        })
+    }
+
+    fn mask(prefix_length: u8) -> u128 {
+        u128::MAX
+            .checked_shl(128 - prefix_length as u32)
+            .unwrap_or(0)
+    }
+}
+
+impl AbsorbField<Ipv6Addr> for HierarchyAnalysis {
+    fn absorb(&mut self, addr: Ipv6Addr) {
+        let addr = u128::from(addr);
+        for (prefix_length, counts) in &mut self.levels {
+            *counts.entry(addr & Self::mask(*prefix_length)).or_insert(0) += 1;
+        }
+    }
+
+    /// One row per subnet, ordered by prefix length and then by descending count, with
+    /// at most `max_subnets` rows per prefix length.
+    fn finalize(&mut self) -> DataFrame {
+        let mut prefix_lengths = Vec::new();
+        let mut subnets = Vec::new();
+        let mut counts = Vec::new();
+
+        for (prefix_length, level) in &self.levels {
+            let mut level: Vec<_> = level.iter().map(|(net, count)| (*net, *count)).collect();
+            level.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            level.truncate(self.max_subnets);
+
+            for (network, count) in level {
+                prefix_lengths.push(*prefix_length as u32);
+                subnets.push(format!("{}/{}", Ipv6Addr::from(network), prefix_length));
+                counts.push(count);
+            }
+        }
+
+        DataFrame::new(vec![
+            Column::new("prefix_length".into(), &prefix_lengths),
+            Column::new("subnet".into(), &subnets),
+            Column::new("count".into(), &counts),
+        ])
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_are_consistent_across_levels() {
+        let mut analyzer = HierarchyAnalysis::new(&[64, 32, 48, 56], 100).unwrap();
+        for addr in [
+            "2001:db8:1:1::1",
+            "2001:db8:1:1::2",
+            "2001:db8:1:2::1",
+            "2001:db8:2:1::1",
+            "2001:db9::1",
+        ] {
+            analyzer.absorb(addr.parse().unwrap());
+        }
+        let out = analyzer.finalize();
+
+        let rows: HashMap<String, u64> = out
+            .column("subnet")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .zip(
+                out.column("count")
+                    .unwrap()
+                    .u64()
+                    .unwrap()
+                    .into_no_null_iter(),
+            )
+            .map(|(subnet, count)| (subnet.to_string(), count))
+            .collect();
+        assert_eq!(rows["2001:db8::/32"], 4);
+        assert_eq!(rows["2001:db8:1::/48"], 3);
+        assert_eq!(rows["2001:db8:1:1::/64"], 2);
+        assert!(rows["2001:db8::/32"] >= rows["2001:db8:1:1::/64"]);
+
+        let lengths: Vec<u32> = out
+            .column("prefix_length")
+            .unwrap()
+            .u32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert!(lengths.is_sorted());
+        // Every level accounts for all five addresses
+        for len in [32, 48, 56, 64] {
+            let total: u64 = lengths
+                .iter()
+                .zip(
+                    out.column("count")
+                        .unwrap()
+                        .u64()
+                        .unwrap()
+                        .into_no_null_iter(),
+                )
+                .filter(|(l, _)| **l == len)
+                .map(|(_, count)| count)
+                .sum();
+            assert_eq!(total, 5, "/{}", len);
+        }
+    }
+
+    #[test]
+    fn test_prefix_lengths_over_128_are_rejected() {
+        let err = HierarchyAnalysis::new(&[48, 129], 100).err().unwrap();
+        assert!(err.contains("129"), "{}", err);
+        assert!(HierarchyAnalysis::new(&[0, 128], 100).is_ok());
+    }
+}
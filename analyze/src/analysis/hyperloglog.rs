@@ -0,0 +1,107 @@
+use std::net::Ipv6Addr;
+
+/// Approximate distinct counter using HyperLogLog. Memory is fixed at `2^precision`
+/// one-byte registers regardless of how many addresses are inserted.
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Default precision: 16384 registers (16 KiB) for a relative error of about 0.8%.
+    pub const DEFAULT_PRECISION: u8 = 14;
+
+    pub fn new(precision: u8) -> Self {
+        assert!(
+            (4..=18).contains(&precision),
+            "HyperLogLog precision must be between 4 and 18"
+        );
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    pub fn insert(&mut self, addr: &Ipv6Addr) {
+        let hash = hash_address(addr);
+        let index = (hash >> (64 - self.precision)) as usize;
+        let remaining = hash << self.precision;
+        let rank = (remaining.leading_zeros() + 1).min(64 - self.precision as u32 + 1) as u8;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / sum;
+
+        // Linear counting is more accurate while many registers are still empty
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+
+    /// Relative standard error of [`estimate`](Self::estimate).
+    pub fn relative_error(&self) -> f64 {
+        1.04 / (self.registers.len() as f64).sqrt()
+    }
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_PRECISION)
+    }
+}
+
+/// Hashes the address with the splitmix64 finalizer so sequential addresses spread
+/// evenly over the registers.
+fn hash_address(addr: &Ipv6Addr) -> u64 {
+    fn mix(mut z: u64) -> u64 {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    let value = u128::from(*addr);
+    mix((value as u64) ^ mix(((value >> 64) as u64).wrapping_add(0x9e3779b97f4a7c15)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_within_error_bound() {
+        let mut hll = HyperLogLog::default();
+        let distinct = 100_000u128;
+        for i in 0..distinct {
+            let addr = Ipv6Addr::from(0x2001_0db8_0000_0000_0000_0000_0000_0000u128 + i * 7919);
+            // Duplicates must not move the estimate
+            hll.insert(&addr);
+            hll.insert(&addr);
+        }
+
+        let error = (hll.estimate() - distinct as f64).abs() / distinct as f64;
+        assert!(
+            error < 3.0 * hll.relative_error(),
+            "estimate {} off by {:.2}%",
+            hll.estimate(),
+            error * 100.0
+        );
+    }
+
+    #[test]
+    fn test_small_sets_are_near_exact() {
+        let mut hll = HyperLogLog::default();
+        for i in 0..100u128 {
+            hll.insert(&Ipv6Addr::from(i));
+        }
+        assert!((hll.estimate() - 100.0).abs() < 2.0);
+    }
+}
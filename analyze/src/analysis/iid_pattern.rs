@@ -0,0 +1,170 @@
+use crate::analysis::predicates::eui64::{Eui64Analysis, IsLowByteHostPredicate};
+use plugin::contracts::{AbsorbField, Predicate};
+use polars::prelude::*;
+use std::fmt;
+use std::net::Ipv6Addr;
+
+/// Interface identifier classes commonly reported in IPv6 measurement studies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IidPattern {
+    LowByte,
+    Eui64,
+    Wordy,
+    EmbeddedIpv4,
+    Random,
+}
+
+/// 16-bit hex words that show up in hand-assigned ("vanity") interface identifiers.
+const WORDY_SEGMENTS: &[u16] = &[
+    0xbabe, 0xbad, 0xbeef, 0xb00c, 0xc0de, 0xc0fe, 0xcafe, 0xdead, 0xdeaf, 0xface, 0xfade, 0xfeed,
+    0xf00d, 0xbead, 0xd00d, 0xace, 0xbee, 0xc0c0, 0x1337,
+];
+
+impl IidPattern {
+    pub const ALL: [IidPattern; 5] = [
+        IidPattern::LowByte,
+        IidPattern::Eui64,
+        IidPattern::Wordy,
+        IidPattern::EmbeddedIpv4,
+        IidPattern::Random,
+    ];
+
+    /// Classifies the lower 64 bits of `addr`. Checks run from most to least specific,
+    /// so e.g. `::1` counts as low-byte rather than embedded IPv4.
+    pub fn classify(addr: Ipv6Addr) -> Self {
+        let iid = &addr.segments()[4..];
+        if IsLowByteHostPredicate.predicate(addr) {
+            IidPattern::LowByte
+        } else if Eui64Analysis.predicate(addr) {
+            IidPattern::Eui64
+        } else if iid.iter().any(|segment| WORDY_SEGMENTS.contains(segment)) {
+            IidPattern::Wordy
+        } else if Self::is_embedded_ipv4(iid) {
+            IidPattern::EmbeddedIpv4
+        } else {
+            IidPattern::Random
+        }
+    }
+
+    /// Matches an IPv4 address in the low 32 bits (`::c000:201`) or spelled out one
+    /// octet per segment in decimal (`::192:0:2:1`).
+    fn is_embedded_ipv4(iid: &[u16]) -> bool {
+        let low_32_bits = iid[0] == 0 && iid[1] == 0;
+        let decimal_octets = iid.iter().all(|segment| {
+            let digits = format!("{:x}", segment);
+            digits.chars().all(|c| c.is_ascii_digit()) && digits.parse::<u16>().unwrap() <= 255
+        });
+        low_32_bits || decimal_octets
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            IidPattern::LowByte => "low_byte",
+            IidPattern::Eui64 => "eui64",
+            IidPattern::Wordy => "wordy",
+            IidPattern::EmbeddedIpv4 => "embedded_ipv4",
+            IidPattern::Random => "random",
+        }
+    }
+}
+
+pub struct IidPatternAnalysis {
+    counts: [u64; IidPattern::ALL.len()],
+    total_addresses: u64,
+}
+
+impl IidPatternAnalysis {
+    pub fn new() -> Self {
+        Self {
+            counts: [0; IidPattern::ALL.len()],
+            total_addresses: 0,
+        }
+    }
+}
+
+impl AbsorbField<Ipv6Addr> for IidPatternAnalysis {
+    fn absorb(&mut self, addr: Ipv6Addr) {
+        let pattern = IidPattern::classify(addr);
+        let index = IidPattern::ALL.iter().position(|p| *p == pattern).unwrap();
+        self.counts[index] += 1;
+        self.total_addresses += 1;
+    }
+
+    fn finalize(&mut self) -> DataFrame {
+        let patterns: Vec<&str> = IidPattern::ALL.iter().map(|p| p.name()).collect();
+        let percentages: Vec<f64> = self
+            .counts
+            .iter()
+            .map(|count| {
+                if self.total_addresses > 0 {
+                    (*count as f64 / self.total_addresses as f64) * 100.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        DataFrame::new(vec![
+            Column::new("pattern".into(), &patterns),
+            Column::new("count".into(), &self.counts),
+            Column::new("percentage".into(), &percentages),
+        ])
+        .unwrap()
+    }
+}
+
+#[derive(Debug)]
+pub struct IidPatternResults {
+    pub patterns: Vec<(String, u64)>,
+}
+
+impl IidPatternResults {
+    pub fn from_dataframe(df: &DataFrame) -> Self {
+        let patterns = df
+            .column("pattern")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .zip(df.column("count").unwrap().u64().unwrap().into_iter())
+            .map(|(pattern, count)| (pattern.unwrap().to_string(), count.unwrap()))
+            .collect();
+        Self { patterns }
+    }
+}
+
+impl fmt::Display for IidPatternResults {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Interface Identifier Patterns:")?;
+        for (pattern, count) in &self.patterns {
+            writeln!(f, "  {}: {}", pattern, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_address_per_category() {
+        let cases = [
+            ("2001:db8::1", IidPattern::LowByte),
+            ("2001:db8::211:22ff:fe33:4455", IidPattern::Eui64),
+            ("2001:db8::dead:beef", IidPattern::Wordy),
+            ("2001:db8::192:0:2:1", IidPattern::EmbeddedIpv4),
+            ("2001:db8::8a3f:51c2:9d0e:7b64", IidPattern::Random),
+        ];
+        let mut analyzer = IidPatternAnalysis::new();
+        for (addr, expected) in cases {
+            let addr: Ipv6Addr = addr.parse().unwrap();
+            assert_eq!(IidPattern::classify(addr), expected, "{}", addr);
+            analyzer.absorb(addr);
+        }
+
+        let results = IidPatternResults::from_dataframe(&analyzer.finalize());
+        assert_eq!(results.patterns.len(), 5);
+        assert!(results.patterns.iter().all(|(_, count)| *count == 1));
+    }
+}
@@ -1,14 +1,36 @@
+pub mod anomaly;
+pub mod combined;
 pub mod count;
 pub mod dispersion;
 pub mod entropy;
+pub mod hierarchy;
+pub mod hyperloglog;
+pub mod iid_pattern;
+pub mod multi;
 pub mod predicates;
+pub mod prefix;
+pub mod response_types;
+pub mod rtt;
 pub mod statistics;
 pub mod subnets;
+pub mod ttl;
 pub mod unique;
 
+pub use anomaly::PredicateAnomalyAnalysis;
+pub use combined::CombinedAnalysis;
 pub use count::{CountAnalysis, CountResults};
 pub use dispersion::{DispersionAnalysis, DispersionResults};
-pub use entropy::{ShannonEntropyAnalysis, ShannonEntropyResults};
+pub use entropy::{
+    ByteEntropyAnalysis, ShannonEntropyAnalysis, ShannonEntropyResults, shannon_entropy,
+};
+pub use hierarchy::HierarchyAnalysis;
+pub use hyperloglog::HyperLogLog;
+pub use iid_pattern::{IidPattern, IidPatternAnalysis, IidPatternResults};
+pub use multi::{BoxedAnalyzer, MultiAnalyzer};
+pub use prefix::{PrefixAnalysis, PrefixResults};
+pub use response_types::{ResponseTypeAnalysis, ResponseTypeResults};
+pub use rtt::rtt_summary;
 pub use statistics::{StatisticsAnalysis, StatisticsResults};
 pub use subnets::{SubnetAnalysis, SubnetResults};
+pub use ttl::TtlAnalysis;
 pub use unique::{UniqueAnalysis, UniqueResults};
@@ -0,0 +1,82 @@
+use plugin::contracts::AbsorbField;
+use polars::prelude::*;
+use std::net::Ipv6Addr;
+
+/// An analyzer behind a trait object, so different analyzers can share one `Vec`.
+pub type BoxedAnalyzer = Box<dyn AbsorbField<Ipv6Addr>>;
+
+/// Feeds each absorbed address to every registered analyzer, so several analyses
+/// share a single pass over the input.
+#[derive(Default)]
+pub struct MultiAnalyzer {
+    analyzers: Vec<BoxedAnalyzer>,
+}
+
+impl MultiAnalyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `analyzer`; its result is at this position in [`MultiAnalyzer::finalize_all`].
+    pub fn add<A: AbsorbField<Ipv6Addr> + 'static>(&mut self, analyzer: A) -> usize {
+        self.analyzers.push(Box::new(analyzer));
+        self.analyzers.len() - 1
+    }
+
+    pub fn absorb(&mut self, addr: Ipv6Addr) {
+        for analyzer in &mut self.analyzers {
+            analyzer.absorb(addr);
+        }
+    }
+
+    /// Each analyzer's result, in registration order.
+    pub fn finalize_all(&mut self) -> Vec<DataFrame> {
+        self.analyzers.iter_mut().map(|a| a.finalize()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{DispersionAnalysis, PrefixAnalysis};
+
+    #[test]
+    fn test_registered_analyzers_match_independent_runs() {
+        let addrs: Vec<Ipv6Addr> = ["2001:db8::1", "2001:db8::2", "2001:db8:1::1", "fe80::1"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+
+        let prefixes: Vec<ipnet::Ipv6Net> = vec![
+            "2001:db8::/32".parse().unwrap(),
+            "fe80::/10".parse().unwrap(),
+        ];
+
+        let mut multi = MultiAnalyzer::new();
+        let dispersion_at = multi.add(DispersionAnalysis::new());
+        let prefixes_at = multi.add(PrefixAnalysis::new(prefixes.clone()));
+        for addr in &addrs {
+            multi.absorb(*addr);
+        }
+        let results = multi.finalize_all();
+        assert_eq!(results.len(), 2);
+
+        let mut dispersion = DispersionAnalysis::new();
+        let mut prefix = PrefixAnalysis::new(prefixes);
+        for addr in &addrs {
+            dispersion.absorb(*addr);
+            prefix.absorb(*addr);
+        }
+        assert!(results[dispersion_at].equals(&dispersion.finalize()));
+        assert!(results[prefixes_at].equals(&prefix.finalize()));
+        assert_eq!(
+            results[prefixes_at]
+                .column("count")
+                .unwrap()
+                .u64()
+                .unwrap()
+                .get(0),
+            Some(3)
+        );
+    }
+}
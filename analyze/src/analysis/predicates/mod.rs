@@ -7,88 +7,156 @@ pub mod special;
 pub mod special_purpose;
 pub mod transition;
 
-use plugin::contracts::Predicate;
+use plugin::contracts::{PluginInfo, Predicate};
 use std::net::Ipv6Addr;
 
-pub fn get_all_predicates() -> Vec<(&'static str, fn(Ipv6Addr) -> bool)> {
-    vec![
-        ("loopback", |addr| {
-            reserved::LoopbackPredicate.predicate(addr)
-        }),
-        ("unspecified", |addr| {
-            reserved::UnspecifiedPredicate.predicate(addr)
-        }),
-        ("link_local", |addr| {
-            reserved::LinkLocalPredicate.predicate(addr)
-        }),
-        ("unique_local", |addr| {
-            reserved::UniqueLocalPredicate.predicate(addr)
-        }),
+/// A named address predicate together with the metadata shown by `list-predicates`.
+#[derive(Clone, Copy)]
+pub struct PredicateInfo {
+    pub name: &'static str,
+    pub category: &'static str,
+    pub description: &'static str,
+    pub predicate: fn(Ipv6Addr) -> bool,
+}
 
-        ("multicast", |addr| {
-            multicast::IsMulticastPredicate.predicate(addr)
-        }),
-        ("solicited_node", |addr| {
-            multicast::SolicitedNodeMulticastPredicate.predicate(addr)
-        }),
-        ("ipv4_mapped", |addr| {
-            transition::Ipv4MappedPredicate.predicate(addr)
-        }),
-        ("ipv4_to_ipv6", |addr| {
-            transition::Ipv4ToIpv6Predicate.predicate(addr)
-        }),
-        ("extended_ipv4", |addr| {
-            transition::ExtendedIpv4Ipv6Predicate.predicate(addr)
-        }),
-        ("ipv6_to_ipv4", |addr| {
-            transition::Ipv6ToIpv4Predicate.predicate(addr)
-        }),
-        ("documentation", |addr| {
-            documentation::DocumentationPredicate.predicate(addr)
-        }),
-        ("documentation_2", |addr| {
-            documentation::Documentation2Predicate.predicate(addr)
-        }),
-        ("benchmarking", |addr| {
-            documentation::BenchmarkingPredicate.predicate(addr)
-        }),
-        ("teredo", |addr| protocols::TeredoPredicate.predicate(addr)),
-        ("ietf_protocol", |addr| {
-            protocols::IetfProtocolPredicate.predicate(addr)
-        }),
-        ("port_control", |addr| {
-            protocols::PortControlProtocolPredicate.predicate(addr)
-        }),
-        ("turn", |addr| protocols::TurnPredicate.predicate(addr)),
-        ("dns_sd", |addr| protocols::DnsSdPredicate.predicate(addr)),
-        ("amt", |addr| protocols::AmtPredicate.predicate(addr)),
-        ("segment_routing", |addr| {
-            protocols::SegmentRoutingPredicate.predicate(addr)
-        }),
-        ("discard_only", |addr| {
-            special_purpose::DiscardOnlyPredicate.predicate(addr)
-        }),
-        ("dummy_prefix", |addr| {
-            special_purpose::DummyPrefixPredicate.predicate(addr)
-        }),
-        ("as112_v6", |addr| {
-            special_purpose::As112V6Predicate.predicate(addr)
-        }),
-        ("direct_as112", |addr| {
-            special_purpose::DirectAs112Predicate.predicate(addr)
-        }),
-        ("deprecated_orchid", |addr| {
-            special_purpose::DeprecatedOrchidPredicate.predicate(addr)
-        }),
-        ("orchid_v2", |addr| {
-            special_purpose::OrchidV2Predicate.predicate(addr)
-        }),
-        ("drone_remote_id", |addr| {
-            special_purpose::DroneRemoteIdPredicate.predicate(addr)
-        }),
-        ("eui64", |addr| eui64::Eui64Analysis.predicate(addr)),
-        ("low_byte_host", |addr| {
-            eui64::IsLowByteHostPredicate.predicate(addr)
-        }),
+macro_rules! predicate_info {
+    ($name:expr, $category:expr, $ty:path) => {
+        PredicateInfo {
+            name: $name,
+            category: $category,
+            description: <$ty as PluginInfo>::DESCRIPTION,
+            predicate: |addr| $ty.predicate(addr),
+        }
+    };
+}
+
+/// Every predicate usable with `--include`/`--exclude`, grouped by category.
+pub fn get_predicate_infos() -> Vec<PredicateInfo> {
+    vec![
+        predicate_info!("loopback", "reserved", reserved::LoopbackPredicate),
+        predicate_info!("unspecified", "reserved", reserved::UnspecifiedPredicate),
+        predicate_info!("link_local", "reserved", reserved::LinkLocalPredicate),
+        predicate_info!("unique_local", "reserved", reserved::UniqueLocalPredicate),
+        predicate_info!("multicast", "multicast", multicast::IsMulticastPredicate),
+        predicate_info!(
+            "solicited_node",
+            "multicast",
+            multicast::SolicitedNodeMulticastPredicate
+        ),
+        predicate_info!("ipv4_mapped", "transition", transition::Ipv4MappedPredicate),
+        predicate_info!(
+            "ipv4_to_ipv6",
+            "transition",
+            transition::Ipv4ToIpv6Predicate
+        ),
+        predicate_info!(
+            "extended_ipv4",
+            "transition",
+            transition::ExtendedIpv4Ipv6Predicate
+        ),
+        predicate_info!(
+            "ipv6_to_ipv4",
+            "transition",
+            transition::Ipv6ToIpv4Predicate
+        ),
+        predicate_info!(
+            "documentation",
+            "documentation",
+            documentation::DocumentationPredicate
+        ),
+        predicate_info!(
+            "documentation_2",
+            "documentation",
+            documentation::Documentation2Predicate
+        ),
+        predicate_info!(
+            "benchmarking",
+            "documentation",
+            documentation::BenchmarkingPredicate
+        ),
+        predicate_info!("teredo", "protocol", protocols::TeredoPredicate),
+        predicate_info!(
+            "ietf_protocol",
+            "protocol",
+            protocols::IetfProtocolPredicate
+        ),
+        predicate_info!(
+            "port_control",
+            "protocol",
+            protocols::PortControlProtocolPredicate
+        ),
+        predicate_info!("turn", "protocol", protocols::TurnPredicate),
+        predicate_info!("dns_sd", "protocol", protocols::DnsSdPredicate),
+        predicate_info!("amt", "protocol", protocols::AmtPredicate),
+        predicate_info!(
+            "segment_routing",
+            "protocol",
+            protocols::SegmentRoutingPredicate
+        ),
+        predicate_info!(
+            "discard_only",
+            "special_purpose",
+            special_purpose::DiscardOnlyPredicate
+        ),
+        predicate_info!(
+            "dummy_prefix",
+            "special_purpose",
+            special_purpose::DummyPrefixPredicate
+        ),
+        predicate_info!(
+            "as112_v6",
+            "special_purpose",
+            special_purpose::As112V6Predicate
+        ),
+        predicate_info!(
+            "direct_as112",
+            "special_purpose",
+            special_purpose::DirectAs112Predicate
+        ),
+        predicate_info!(
+            "deprecated_orchid",
+            "special_purpose",
+            special_purpose::DeprecatedOrchidPredicate
+        ),
+        predicate_info!(
+            "orchid_v2",
+            "special_purpose",
+            special_purpose::OrchidV2Predicate
+        ),
+        predicate_info!(
+            "drone_remote_id",
+            "special_purpose",
+            special_purpose::DroneRemoteIdPredicate
+        ),
+        predicate_info!("eui64", "interface_id", eui64::Eui64Analysis),
+        predicate_info!(
+            "low_byte_host",
+            "interface_id",
+            eui64::IsLowByteHostPredicate
+        ),
     ]
 }
+
+/// Every predicate as `(name, description, predicate)`.
+pub fn get_all_predicates() -> Vec<(&'static str, &'static str, fn(Ipv6Addr) -> bool)> {
+    get_predicate_infos()
+        .into_iter()
+        .map(|info| (info.name, info.description, info.predicate))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_predicate_has_a_description() {
+        for (name, description, _) in get_all_predicates() {
+            assert!(
+                !description.trim().is_empty(),
+                "{} has no description",
+                name
+            );
+        }
+    }
+}
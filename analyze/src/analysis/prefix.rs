@@ -0,0 +1,118 @@
+use ipnet::Ipv6Net;
+use plugin::contracts::AbsorbField;
+use polars::prelude::*;
+use std::fmt;
+use std::net::Ipv6Addr;
+
+/// Counts addresses contained in each of a set of user-supplied prefixes.
+/// Prefixes may overlap, in which case an address is counted under every prefix containing it.
+pub struct PrefixAnalysis {
+    prefixes: Vec<Ipv6Net>,
+    counts: Vec<u64>,
+    total_addresses: u64,
+}
+
+impl PrefixAnalysis {
+    pub fn new(prefixes: Vec<Ipv6Net>) -> Self {
+        let counts = vec![0; prefixes.len()];
+        Self {
+            prefixes,
+            counts,
+            total_addresses: 0,
+        }
+    }
+
+    /// Returns true if `addr` falls inside any of `prefixes`.
+    pub fn contains_any(prefixes: &[Ipv6Net], addr: &Ipv6Addr) -> bool {
+        prefixes.iter().any(|prefix| prefix.contains(addr))
+    }
+}
+
+impl AbsorbField<Ipv6Addr> for PrefixAnalysis {
+    fn absorb(&mut self, addr: Ipv6Addr) {
+        self.total_addresses += 1;
+        for (prefix, count) in self.prefixes.iter().zip(self.counts.iter_mut()) {
+            if prefix.contains(&addr) {
+                *count += 1;
+            }
+        }
+    }
+
+    fn finalize(&mut self) -> DataFrame {
+        let prefixes: Vec<String> = self.prefixes.iter().map(|p| p.to_string()).collect();
+        let percentages: Vec<f64> = self
+            .counts
+            .iter()
+            .map(|count| {
+                if self.total_addresses > 0 {
+                    (*count as f64 / self.total_addresses as f64) * 100.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        DataFrame::new(vec![
+            Column::new("prefix".into(), &prefixes),
+            Column::new("count".into(), &self.counts),
+            Column::new("percentage".into(), &percentages),
+        ])
+        .unwrap()
+    }
+}
+
+#[derive(Debug)]
+pub struct PrefixResults {
+    pub prefixes: Vec<(String, u64)>,
+}
+
+impl PrefixResults {
+    pub fn from_dataframe(df: &DataFrame) -> Self {
+        let prefixes = df
+            .column("prefix")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .zip(df.column("count").unwrap().u64().unwrap().into_iter())
+            .map(|(prefix, count)| (prefix.unwrap().to_string(), count.unwrap()))
+            .collect();
+        Self { prefixes }
+    }
+}
+
+impl fmt::Display for PrefixResults {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Prefix Membership Results:")?;
+        for (prefix, count) in &self.prefixes {
+            writeln!(f, "  {}: {}", prefix, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping_prefixes_count_address_under_both() {
+        let prefixes = vec![
+            "2001:db8::/32".parse().unwrap(),
+            "2001:db8:1::/48".parse().unwrap(),
+        ];
+        let mut analyzer = PrefixAnalysis::new(prefixes);
+        for addr in ["2001:db8:1::1", "2001:db8:2::1", "2001:db9::1"] {
+            analyzer.absorb(addr.parse().unwrap());
+        }
+
+        let results = PrefixResults::from_dataframe(&analyzer.finalize());
+        assert_eq!(
+            results.prefixes,
+            vec![
+                ("2001:db8::/32".to_string(), 2),
+                ("2001:db8:1::/48".to_string(), 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use plugin::contracts::AbsorbField;
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Tallies scan results by their ICMPv6 response `type`.
+pub struct ResponseTypeAnalysis {
+    type_counts: HashMap<u8, u64>,
+}
+
+impl ResponseTypeAnalysis {
+    pub fn new() -> Self {
+        Self {
+            type_counts: HashMap::new(),
+        }
+    }
+
+    pub fn type_name(icmp_type: u8) -> &'static str {
+        match icmp_type {
+            1 => "destination_unreachable",
+            2 => "packet_too_big",
+            3 => "time_exceeded",
+            4 => "parameter_problem",
+            128 => "echo_request",
+            129 => "echo_reply",
+            133 => "router_solicitation",
+            134 => "router_advertisement",
+            135 => "neighbor_solicitation",
+            136 => "neighbor_advertisement",
+            137 => "redirect",
+            _ => "unknown",
+        }
+    }
+}
+
+impl AbsorbField<u8> for ResponseTypeAnalysis {
+    fn absorb(&mut self, icmp_type: u8) {
+        *self.type_counts.entry(icmp_type).or_insert(0) += 1;
+    }
+
+    fn finalize(&mut self) -> DataFrame {
+        let mut types: Vec<_> = self.type_counts.iter().map(|(t, c)| (*t, *c)).collect();
+        types.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let type_values: Vec<u32> = types.iter().map(|(t, _)| *t as u32).collect();
+        let names: Vec<&str> = types.iter().map(|(t, _)| Self::type_name(*t)).collect();
+        let counts: Vec<u64> = types.iter().map(|(_, c)| *c).collect();
+
+        DataFrame::new(vec![
+            Column::new("type".into(), &type_values),
+            Column::new("name".into(), &names),
+            Column::new("count".into(), &counts),
+        ])
+        .unwrap()
+    }
+}
+
+#[derive(Debug)]
+pub struct ResponseTypeResults {
+    pub types: Vec<(u32, String, u64)>,
+}
+
+impl ResponseTypeResults {
+    pub fn from_dataframe(df: &DataFrame) -> Self {
+        let types = df
+            .column("type")
+            .unwrap()
+            .u32()
+            .unwrap()
+            .into_iter()
+            .zip(df.column("name").unwrap().str().unwrap().into_iter())
+            .zip(df.column("count").unwrap().u64().unwrap().into_iter())
+            .map(|((t, name), count)| (t.unwrap(), name.unwrap().to_string(), count.unwrap()))
+            .collect();
+        Self { types }
+    }
+}
+
+impl fmt::Display for ResponseTypeResults {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Response Type Analysis Results:")?;
+        for (icmp_type, name, count) in &self.types {
+            writeln!(f, "  {} ({}): {}", icmp_type, name, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_counts_mixed_response_types() {
+        let csv = "saddr,type,code\n\
+                   2001:db8::1,129,0\n\
+                   2001:db8::2,129,0\n\
+                   2001:db8::3,1,4\n\
+                   2001:db8::4,3,0\n\
+                   2001:db8::5,129,0\n\
+                   2001:db8::6,1,3\n";
+        let df = CsvReadOptions::default()
+            .with_has_header(true)
+            .into_reader_with_file_handle(Cursor::new(csv))
+            .finish()
+            .unwrap();
+
+        let series = df.column("type").unwrap().as_materialized_series();
+        let mut analyzer = ResponseTypeAnalysis::new();
+        let out = analyzer.absorb_series(series);
+
+        let results = ResponseTypeResults::from_dataframe(&out);
+        assert_eq!(
+            results.types,
+            vec![
+                (129, "echo_reply".to_string(), 3),
+                (1, "destination_unreachable".to_string(), 2),
+                (3, "time_exceeded".to_string(), 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use polars::prelude::*;
+
+/// Summarizes a column of round-trip times as count, min, max, mean, median, p95 and p99.
+/// Nulls (targets that never replied) are left out of every statistic.
+pub fn rtt_summary(rtts: &Column) -> PolarsResult<DataFrame> {
+    let rtt = col("rtt_ms");
+    let df = DataFrame::new(vec![
+        rtts.cast(&DataType::Float64)?.with_name("rtt_ms".into()),
+    ])?;
+
+    df.lazy()
+        .select([
+            rtt.clone().count().cast(DataType::UInt64).alias("count"),
+            rtt.clone().min().alias("min"),
+            rtt.clone().max().alias("max"),
+            rtt.clone().mean().alias("mean"),
+            rtt.clone().median().alias("median"),
+            rtt.clone()
+                .quantile(lit(0.95), QuantileMethod::Linear)
+                .alias("p95"),
+            rtt.quantile(lit(0.99), QuantileMethod::Linear).alias("p99"),
+        ])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(df: &DataFrame, name: &str) -> f64 {
+        df.column(name).unwrap().f64().unwrap().get(0).unwrap()
+    }
+
+    #[test]
+    fn test_summarizes_known_rtts_and_skips_nulls() {
+        let mut rtts: Vec<Option<u64>> = (1..=100).map(Some).collect();
+        rtts.push(None);
+        let out = rtt_summary(&Column::new("rtt_ms".into(), rtts)).unwrap();
+
+        assert_eq!(
+            out.get_column_names_str(),
+            ["count", "min", "max", "mean", "median", "p95", "p99"]
+        );
+        assert_eq!(
+            out.column("count").unwrap().u64().unwrap().get(0),
+            Some(100)
+        );
+        assert_eq!(stat(&out, "min"), 1.0);
+        assert_eq!(stat(&out, "max"), 100.0);
+        assert!((stat(&out, "mean") - 50.5).abs() < 1e-9);
+        assert!((stat(&out, "median") - 50.5).abs() < 1e-9);
+        assert!((stat(&out, "p95") - 95.05).abs() < 1e-9);
+        assert!((stat(&out, "p99") - 99.01).abs() < 1e-9);
+    }
+}
@@ -1,36 +1,57 @@
+use crate::analysis::hyperloglog::HyperLogLog;
 use hashbrown::HashMap;
 use plugin::contracts::{AbsorbField, MyField};
 use polars::prelude::*;
 use std::fmt;
 use std::net::Ipv6Addr;
 
-#[derive(Default)]
-pub struct StatisticsConfig;
+enum UniqueCounter {
+    Exact(HashMap<Ipv6Addr, usize>),
+    Approximate(HyperLogLog),
+}
 
 pub struct StatisticsAnalysis {
-    address_counts: HashMap<Ipv6Addr, usize>,
+    unique: UniqueCounter,
     total_count: usize,
 }
 
 impl StatisticsAnalysis {
+    /// Counts unique addresses exactly, storing every distinct address.
     pub fn new() -> Self {
         Self {
-            address_counts: HashMap::new(),
+            unique: UniqueCounter::Exact(HashMap::new()),
+            total_count: 0,
+        }
+    }
+
+    /// Estimates unique addresses with a HyperLogLog sketch in fixed memory.
+    pub fn new_approximate() -> Self {
+        Self {
+            unique: UniqueCounter::Approximate(HyperLogLog::default()),
             total_count: 0,
         }
     }
 }
 
 impl AbsorbField<Ipv6Addr> for StatisticsAnalysis {
-    type Config = StatisticsConfig;
-
     fn absorb(&mut self, addr: Ipv6Addr) {
-        *self.address_counts.entry(addr).or_insert(0) += 1;
+        match &mut self.unique {
+            UniqueCounter::Exact(address_counts) => {
+                *address_counts.entry(addr).or_insert(0) += 1;
+            }
+            UniqueCounter::Approximate(hll) => hll.insert(&addr),
+        }
         self.total_count += 1;
     }
 
     fn finalize(&mut self) -> DataFrame {
-        let unique_count = self.address_counts.len();
+        let (unique_count, unique_count_error) = match &self.unique {
+            UniqueCounter::Exact(address_counts) => (address_counts.len(), 0.0),
+            UniqueCounter::Approximate(hll) => (
+                (hll.estimate().round() as usize).min(self.total_count),
+                hll.relative_error(),
+            ),
+        };
         let duplicate_count = self.total_count - unique_count;
         let duplication_ratio = if self.total_count > 0 {
             duplicate_count as f64 / self.total_count as f64
@@ -41,6 +62,7 @@ impl AbsorbField<Ipv6Addr> for StatisticsAnalysis {
         DataFrame::new(vec![
             Column::new("total_count".into(), &[self.total_count as u64]),
             Column::new("unique_count".into(), &[unique_count as u64]),
+            Column::new("unique_count_error".into(), &[unique_count_error]),
             Column::new("duplicate_count".into(), &[duplicate_count as u64]),
             Column::new("duplication_ratio".into(), &[duplication_ratio]),
         ])
@@ -52,6 +74,8 @@ impl AbsorbField<Ipv6Addr> for StatisticsAnalysis {
 pub struct StatisticsResults {
     pub total_count: usize,
     pub unique_count: usize,
+    /// Relative standard error of `unique_count`; zero when counted exactly
+    pub unique_count_error: f64,
     pub duplicate_count: usize,
     pub duplication_ratio: f64,
 }
@@ -60,7 +84,16 @@ impl fmt::Display for StatisticsResults {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Statistics Analysis Results:")?;
         writeln!(f, "  Total addresses: {}", self.total_count)?;
-        writeln!(f, "  Unique addresses: {}", self.unique_count)?;
+        if self.unique_count_error > 0.0 {
+            writeln!(
+                f,
+                "  Unique addresses: ~{} (±{:.2}%)",
+                self.unique_count,
+                self.unique_count_error * 100.0
+            )?;
+        } else {
+            writeln!(f, "  Unique addresses: {}", self.unique_count)?;
+        }
         writeln!(f, "  Duplicate addresses: {}", self.duplicate_count)?;
         writeln!(
             f,
@@ -70,3 +103,52 @@ impl fmt::Display for StatisticsResults {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approximate_unique_count_close_to_exact() {
+        let mut exact = StatisticsAnalysis::new();
+        let mut approximate = StatisticsAnalysis::new_approximate();
+        for i in 0..50_000u128 {
+            let addr = Ipv6Addr::from((0xfe80u128 << 112) | (i % 20_000));
+            exact.absorb(addr);
+            approximate.absorb(addr);
+        }
+
+        let exact = exact.finalize();
+        let approximate = approximate.finalize();
+        let exact_unique = exact
+            .column("unique_count")
+            .unwrap()
+            .u64()
+            .unwrap()
+            .get(0)
+            .unwrap();
+        let estimate = approximate
+            .column("unique_count")
+            .unwrap()
+            .u64()
+            .unwrap()
+            .get(0)
+            .unwrap();
+        let error = approximate
+            .column("unique_count_error")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .get(0)
+            .unwrap();
+
+        assert_eq!(exact_unique, 20_000);
+        let observed = (estimate as f64 - exact_unique as f64).abs() / exact_unique as f64;
+        assert!(
+            observed < 3.0 * error,
+            "estimate {} vs exact {}",
+            estimate,
+            exact_unique
+        );
+    }
+}
@@ -1,29 +1,65 @@
+use ipnet::Ipv6Net;
 use plugin::contracts::{AbsorbField, MyField};
 use polars::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::net::Ipv6Addr;
 
-#[derive(Default)]
-pub struct SubnetConfig {
-    pub max_subnets: usize,
-    pub prefix_length: u8,
-}
-
 pub struct SubnetAnalysis {
     pub subnet_counts: HashMap<String, usize>,
     pub max_subnets: usize,
     pub prefix_length: u8,
+    /// Allocation networks grouped by prefix length, longest first. When set, addresses
+    /// are attributed to the most specific allocation containing them instead of a
+    /// fixed-length bucket.
+    allocations: Option<Vec<(u8, HashSet<u128>)>>,
 }
 
+/// Bucket for addresses that fall outside every allocation.
+pub const UNALLOCATED: &str = "unallocated";
+
 impl SubnetAnalysis {
     pub fn new_with_options(max_subnets: usize, prefix_length: u8) -> Self {
         Self {
             subnet_counts: HashMap::new(),
             max_subnets,
             prefix_length,
+            allocations: None,
         }
     }
+
+    pub fn new_with_allocations(max_subnets: usize, allocations: &[Ipv6Net]) -> Self {
+        let mut by_length: HashMap<u8, HashSet<u128>> = HashMap::new();
+        for net in allocations {
+            let net = net.trunc();
+            by_length
+                .entry(net.prefix_len())
+                .or_default()
+                .insert(u128::from(net.network()));
+        }
+        let mut by_length: Vec<_> = by_length.into_iter().collect();
+        by_length.sort_by(|a, b| b.0.cmp(&a.0));
+
+        Self {
+            subnet_counts: HashMap::new(),
+            max_subnets,
+            prefix_length: 0,
+            allocations: Some(by_length),
+        }
+    }
+
+    fn get_allocation(allocations: &[(u8, HashSet<u128>)], addr: &Ipv6Addr) -> Option<String> {
+        let addr_u128 = u128::from(*addr);
+        allocations.iter().find_map(|(prefix_length, networks)| {
+            let mask = u128::MAX
+                .checked_shl(128 - *prefix_length as u32)
+                .unwrap_or(0);
+            let network = addr_u128 & mask;
+            networks
+                .contains(&network)
+                .then(|| format!("{}/{}", Ipv6Addr::from(network), prefix_length))
+        })
+    }
     fn get_subnet(&self, addr: &Ipv6Addr) -> String {
         let addr_u128 = u128::from_be_bytes(addr.octets());
         let prefix = if self.prefix_length == 128 {
@@ -37,10 +73,13 @@ impl SubnetAnalysis {
 }
 
 impl AbsorbField<Ipv6Addr> for SubnetAnalysis {
-    type Config = SubnetConfig;
-
     fn absorb(&mut self, addr: Ipv6Addr) {
-        let subnet = self.get_subnet(&addr);
+        let subnet = match &self.allocations {
+            Some(allocations) => {
+                Self::get_allocation(allocations, &addr).unwrap_or_else(|| UNALLOCATED.to_string())
+            }
+            None => self.get_subnet(&addr),
+        };
         *self.subnet_counts.entry(subnet).or_insert(0) += 1;
     }
 
@@ -93,3 +132,33 @@ impl std::fmt::Display for SubnetResults {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addresses_attributed_to_most_specific_allocation() {
+        let allocations: Vec<Ipv6Net> = vec![
+            "2001:db8::/32".parse().unwrap(),
+            "2001:db8:1000::/36".parse().unwrap(),
+        ];
+        let mut analyzer = SubnetAnalysis::new_with_allocations(10, &allocations);
+        for addr in [
+            "2001:db8::1",
+            "2001:db8:2::1",
+            "2001:db8:1000::1",
+            "2001:db8:1fff::1",
+            "2001:db8:1abc::1",
+            "2001:db9::1",
+        ] {
+            analyzer.absorb(addr.parse().unwrap());
+        }
+
+        let results = SubnetResults::from_dataframe(&analyzer.finalize());
+        let counts: HashMap<String, usize> = results.subnets.into_iter().collect();
+        assert_eq!(counts["2001:db8:1000::/36"], 3);
+        assert_eq!(counts["2001:db8::/32"], 2);
+        assert_eq!(counts[UNALLOCATED], 1);
+    }
+}
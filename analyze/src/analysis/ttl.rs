@@ -0,0 +1,109 @@
+use plugin::contracts::AbsorbField;
+use polars::prelude::*;
+use std::collections::HashMap;
+
+/// Infers each reply's initial TTL and hop count from its received `ttl`, tallying
+/// replies per (initial TTL, hop count).
+pub struct TtlAnalysis {
+    counts: HashMap<(u8, u8), u64>,
+}
+
+impl TtlAnalysis {
+    pub fn new() -> Self {
+        Self {
+            counts: HashMap::new(),
+        }
+    }
+
+    /// The smallest common initial TTL (64, 128 or 255) at or above `ttl`.
+    pub fn initial_ttl(ttl: u8) -> u8 {
+        match ttl {
+            0..=64 => 64,
+            65..=128 => 128,
+            _ => 255,
+        }
+    }
+
+    /// Routers traversed on the way back, assuming the sender used `initial_ttl(ttl)`.
+    pub fn hop_count(ttl: u8) -> u8 {
+        Self::initial_ttl(ttl) - ttl
+    }
+
+    /// The operating systems that typically send with `initial_ttl`.
+    pub fn os_guess(initial_ttl: u8) -> &'static str {
+        match initial_ttl {
+            64 => "linux_unix",
+            128 => "windows",
+            _ => "network_device",
+        }
+    }
+}
+
+impl AbsorbField<u8> for TtlAnalysis {
+    fn absorb(&mut self, ttl: u8) {
+        let key = (Self::initial_ttl(ttl), Self::hop_count(ttl));
+        *self.counts.entry(key).or_insert(0) += 1;
+    }
+
+    fn finalize(&mut self) -> DataFrame {
+        let mut rows: Vec<_> = self.counts.iter().map(|(k, c)| (*k, *c)).collect();
+        rows.sort_by_key(|&(key, _)| key);
+
+        let os: Vec<&str> = rows
+            .iter()
+            .map(|((initial, _), _)| Self::os_guess(*initial))
+            .collect();
+        let initial: Vec<u32> = rows.iter().map(|((i, _), _)| *i as u32).collect();
+        let hops: Vec<u32> = rows.iter().map(|((_, h), _)| *h as u32).collect();
+        let counts: Vec<u64> = rows.iter().map(|(_, c)| *c).collect();
+
+        DataFrame::new(vec![
+            Column::new("os_guess".into(), &os),
+            Column::new("initial_ttl".into(), &initial),
+            Column::new("hops".into(), &hops),
+            Column::new("count".into(), &counts),
+        ])
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infers_hop_counts_from_ttls() {
+        assert_eq!(TtlAnalysis::hop_count(54), 10);
+        assert_eq!(TtlAnalysis::hop_count(250), 5);
+
+        let series = Series::new("ttl".into(), &[54i64, 250, 54, 118]);
+        let out = TtlAnalysis::new().absorb_series(&series);
+
+        let column = |name: &str| -> Vec<u32> {
+            out.column(name)
+                .unwrap()
+                .u32()
+                .unwrap()
+                .into_no_null_iter()
+                .collect()
+        };
+        assert_eq!(column("initial_ttl"), [64, 128, 255]);
+        assert_eq!(column("hops"), [10, 10, 5]);
+        let os: Vec<&str> = out
+            .column("os_guess")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(os, ["linux_unix", "windows", "network_device"]);
+        let counts: Vec<u64> = out
+            .column("count")
+            .unwrap()
+            .u64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(counts, [2, 1, 1]);
+    }
+}
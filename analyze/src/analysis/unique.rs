@@ -1,10 +1,10 @@
 use crate::analysis::predicates::*;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{ParallelProgressIterator, ProgressStyle};
 use plugin::contracts::Predicate;
 use polars::prelude::*;
 use rayon::prelude::*;
 use std::net::Ipv6Addr;
-use tracing::{Level, info, span, warn};
+use tracing::{Level, info, span};
 
 pub struct UniqueAnalysis {
     predicate_name: Option<String>,
@@ -15,6 +15,8 @@ impl UniqueAnalysis {
         Self { predicate_name }
     }
 
+    /// Parses addresses in parallel on the rayon pool this is called from, so callers
+    /// size it with `ThreadPool::install`.
     pub fn analyze(&self, series: &Series) -> Result<DataFrame, Box<dyn std::error::Error>> {
         let span = span!(
             Level::INFO,
@@ -23,18 +25,11 @@ impl UniqueAnalysis {
         );
         let _enter = span.enter();
 
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(8)
-            .build_global()
-            .unwrap_or_else(|_| {
-                warn!("Could not set thread pool size (may already be initialized)");
-            });
-
         let all_predicates = get_all_predicates();
         let predicates_to_run = if let Some(ref name) = self.predicate_name {
             all_predicates
                 .into_iter()
-                .filter(|(pred_name, _)| pred_name == name)
+                .filter(|(pred_name, _, _)| pred_name == name)
                 .collect::<Vec<_>>()
         } else {
             all_predicates
@@ -59,7 +54,7 @@ impl UniqueAnalysis {
             utf8_series.len()
         );
 
-        let parse_pb = ProgressBar::new(utf8_series.len() as u64);
+        let parse_pb = crate::progress::new_progress_bar(utf8_series.len() as u64);
         parse_pb.set_style(
             ProgressStyle::default_bar()
                 .template("[{elapsed_precise}] {msg} [{bar:20.cyan/grey}] {pos}/{len}")
@@ -68,28 +63,15 @@ impl UniqueAnalysis {
         );
         parse_pb.set_message("Parsing IPv6 addresses...");
 
-        let mut parsed_ips = Vec::new();
-        for (i, opt_str) in utf8_series.into_iter().enumerate() {
-            if let Some(s) = opt_str {
-                if let Ok(addr) = s.parse::<Ipv6Addr>() {
-                    parsed_ips.push(Some(addr));
-                } else {
-                    parsed_ips.push(None);
-                }
-            } else {
-                parsed_ips.push(None);
-            }
-
-            if i % 1000 == 0 {
-                parse_pb.set_position(i as u64);
-            }
-        }
+        let strings: Vec<Option<&str>> = utf8_series.into_iter().collect();
+        let unique_addresses: std::collections::HashSet<Ipv6Addr> = strings
+            .par_iter()
+            .progress_with(parse_pb.clone())
+            .filter_map(|opt_str| opt_str.and_then(|s| s.parse::<Ipv6Addr>().ok()))
+            .collect();
         parse_pb.finish_with_message("IP address parsing complete!");
         info!("IP address parsing complete!");
 
-        let unique_addresses: std::collections::HashSet<Ipv6Addr> =
-            parsed_ips.into_iter().filter_map(|opt| opt).collect();
-
         info!("Found {} unique IPv6 addresses", unique_addresses.len());
 
         if unique_addresses.is_empty() {
@@ -119,3 +101,35 @@ impl UniqueResults {
         Self { dataframe }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_runs_on_the_callers_pool() {
+        let series = Series::new(
+            "address".into(),
+            &[
+                "2001:db8::1",
+                "2001:db8::1",
+                "2001:db8::2",
+                "not an address",
+            ],
+        );
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+
+        let df = pool.install(|| {
+            assert_eq!(rayon::current_num_threads(), 2);
+            UniqueAnalysis::new(None).analyze(&series).unwrap()
+        });
+        assert_eq!(df.height(), 2);
+        assert_eq!(
+            UniqueAnalysis::new(None).analyze(&series).unwrap().height(),
+            2
+        );
+    }
+}
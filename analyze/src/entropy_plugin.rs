@@ -1,66 +1,101 @@
-use std::sync::Arc;
+use crate::analysis::shannon_entropy;
+use clap::Parser;
+use plugin::{FieldSpec, Plugin, Result, register_plugin};
+use polars::prelude::*;
+use std::net::Ipv6Addr;
 
-use anyhow::Result;
-use async_trait::async_trait;
-use clap::{ArgMatches, Parser};
-
-#[derive(Parser, Clone)]
+#[derive(Parser, Clone, Default)]
 pub struct EntropyCfg {
+    /// Only measure the last N bytes of each address
     #[arg(long)]
     tail_bytes: Option<usize>,
 }
 
-impl EntropyCfg {
-    pub fn from_arg_matches(m: &ArgMatches) -> Result<Self> {
-        Ok(Self {
-            tail_bytes: m.get_one::<usize>("tail-bytes").cloned(),
-        })
-    }
-}
-
-pub struct Entropy {
-    cfg: EntropyCfg,
-}
+/// Reference `Plugin`: Shannon byte-entropy of each address.
+#[derive(Default)]
+pub struct Entropy;
 
 impl Entropy {
-    pub const NAME:    &'static str = "entropy";
+    pub const NAME: &'static str = "entropy";
     pub const VERSION: &'static str = "0.1.0";
-    pub const STAGE:   Stage      = Stage::Analyse;
-    pub const ABOUT:   &'static str = "Compute Shannon entropy per IPv6 address";
+    pub const ABOUT: &'static str = "Compute Shannon entropy per IPv6 address";
 
-    pub const INPUT: &'static [FieldSpec] = &[
-        FieldSpec::new("addr", DataType::FixedSizeBinary(16), false),
-    ];
-    pub const OUTPUT: &'static [FieldSpec] = &[
-        FieldSpec::new("entropy", DataType::Float64, false),
-    ];
+    pub const INPUT: &'static [FieldSpec] = &[FieldSpec::new("addr", DataType::String, false)];
+    /// Null where `addr` is null or not an IPv6 address
+    pub const OUTPUT: &'static [FieldSpec] = &[FieldSpec::new("entropy", DataType::Float64, true)];
 
-    pub fn new(cfg: EntropyCfg) -> Self {
-        Self { cfg }
-    }
-
-    fn entropy(&self, buf: &[u8]) -> f64 {
-        let slice = if let Some(n) = self.cfg.tail_bytes {
+    fn entropy(cfg: &EntropyCfg, buf: &[u8]) -> f64 {
+        let slice = if let Some(n) = cfg.tail_bytes {
             let start = buf.len().saturating_sub(n);
             &buf[start..]
         } else {
             buf
         };
-        let mut counts = [0u32; 256];
-        for &b in slice { counts[b as usize] += 1; }
-        let len = slice.len() as f64;
-        counts.iter()
-              .filter(|&&c| c>0)
-              .map(|&c| { let p=c as f64/len; -p*p.log2() })
-              .sum()
+        let mut counts = [0u64; 256];
+        for &b in slice {
+            counts[b as usize] += 1;
+        }
+        shannon_entropy(&counts)
     }
 }
 
-#[async_trait]
-impl Plugin for Entropy {
-    async fn run(&mut self, batch: RecordBatch) -> Result<Option<RecordBatch>> {
-        Ok(None)
+impl Plugin<DataFrame, DataFrame> for Entropy {
+    type Config = EntropyCfg;
+
+    async fn run(&self, cfg: EntropyCfg, input: DataFrame) -> Result<DataFrame> {
+        let addrs = input.column(Self::INPUT[0].name)?;
+        let entropies: Float64Chunked = addrs
+            .str()?
+            .into_iter()
+            .map(|opt_str| {
+                opt_str
+                    .and_then(|s| s.trim().parse::<Ipv6Addr>().ok())
+                    .map(|addr| Self::entropy(&cfg, &addr.octets()))
+            })
+            .collect();
+
+        DataFrame::new(vec![
+            addrs.clone(),
+            entropies
+                .with_name(Self::OUTPUT[0].name.into())
+                .into_column(),
+        ])
     }
 }
 
-register_plugin!(Entropy, EntropyCfg);
\ No newline at end of file
+register_plugin!(Entropy, Entropy::NAME, Entropy::ABOUT);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plugin::block_on;
+
+    fn run(cfg: EntropyCfg, addrs: &[&str]) -> Vec<Option<f64>> {
+        let input = DataFrame::new(vec![Column::new("addr".into(), addrs)]).unwrap();
+        let out = block_on(Entropy.run(cfg, input)).unwrap();
+        assert_eq!(out.get_column_names_str(), ["addr", "entropy"]);
+        out.column("entropy")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_run_computes_entropy_per_address() {
+        let entropies = run(
+            EntropyCfg::default(),
+            &["::", "1:203:405:607:809:a0b:c0d:e0f", "bogus"],
+        );
+        assert_eq!(entropies, [Some(0.0), Some(4.0), None]);
+    }
+
+    #[test]
+    fn test_run_honors_tail_bytes() {
+        let cfg = EntropyCfg {
+            tail_bytes: Some(2),
+        };
+        assert_eq!(run(cfg, &["::1"]), [Some(1.0)]);
+    }
+}
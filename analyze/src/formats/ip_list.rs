@@ -9,6 +9,7 @@ pub struct IpListIterator<R> {
     line_buffer: String,
     total_lines: usize,
     bytes_read: u64,
+    lenient: bool,
 }
 
 impl<R: BufRead> IpListIterator<R> {
@@ -18,9 +19,16 @@ impl<R: BufRead> IpListIterator<R> {
             line_buffer: String::new(),
             total_lines: 0,
             bytes_read: 0,
+            lenient: false,
         }
     }
 
+    /// Accepts `%zone` suffixes and `[addr]:port` / `addr:port` wrapping around each address.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
     pub fn bytes_read(&self) -> u64 {
         self.bytes_read
     }
@@ -54,7 +62,12 @@ impl<R: BufRead> Iterator for IpListIterator<R> {
                         continue;
                     }
                     let trimmed = unsafe { std::str::from_utf8_unchecked(&line[start..=end]) };
-                    match trimmed.parse::<IpAddr>() {
+                    let candidate = if self.lenient {
+                        strip_decorations(trimmed)
+                    } else {
+                        trimmed
+                    };
+                    match candidate.parse::<IpAddr>() {
                         Ok(addr) => return Some(Ok(addr)),
                         Err(e) => {
                             return Some(Err(IoError::new(
@@ -74,3 +87,55 @@ impl<R: BufRead> Iterator for IpListIterator<R> {
         (0, None)
     }
 }
+
+/// Strips `[...]:port` or `v4:port` wrapping and a trailing `%zone` from an address.
+fn strip_decorations(s: &str) -> &str {
+    let addr = if let Some(rest) = s.strip_prefix('[') {
+        rest.split(']').next().unwrap_or(rest)
+    } else if s.matches(':').count() == 1 {
+        // A single colon cannot be IPv6, so it separates an IPv4 address from its port
+        s.split(':').next().unwrap_or(s)
+    } else {
+        s
+    };
+    addr.split('%').next().unwrap_or(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn parse(input: &str, lenient: bool) -> Vec<Result<IpAddr, IoError>> {
+        IpListIterator::new(Cursor::new(input.to_string()))
+            .lenient(lenient)
+            .collect()
+    }
+
+    #[test]
+    fn test_lenient_strips_zone_id() {
+        let addrs = parse("fe80::1%eth0\n", true);
+        assert_eq!(
+            addrs[0].as_ref().unwrap(),
+            &"fe80::1".parse::<IpAddr>().unwrap()
+        );
+
+        assert!(parse("fe80::1%eth0\n", false)[0].is_err());
+    }
+
+    #[test]
+    fn test_lenient_strips_brackets_and_port() {
+        let addrs = parse("[2001:db8::1]:443\n[fe80::2%en0]:22\n192.0.2.1:80\n", true);
+        let addrs: Vec<IpAddr> = addrs.into_iter().map(Result::unwrap).collect();
+        assert_eq!(
+            addrs,
+            vec![
+                "2001:db8::1".parse::<IpAddr>().unwrap(),
+                "fe80::2".parse::<IpAddr>().unwrap(),
+                "192.0.2.1".parse::<IpAddr>().unwrap(),
+            ]
+        );
+
+        assert!(parse("[2001:db8::1]:443\n", false)[0].is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use polars::prelude::*;
 use std::io::{BufRead, Error as IoError};
 use std::net::Ipv6Addr;
 
@@ -12,6 +13,7 @@ pub struct ScanResultIterator<R> {
     reader: R,
     line_buffer: String,
     bytes_read: u64,
+    columns: Vec<String>,
     saddr_idx: usize,
     type_idx: Option<usize>,
     header_read: bool,
@@ -23,6 +25,7 @@ impl<R: BufRead> ScanResultIterator<R> {
             reader,
             line_buffer: String::new(),
             bytes_read: 0,
+            columns: Vec::new(),
             saddr_idx: 0,
             type_idx: None,
             header_read: false,
@@ -42,6 +45,7 @@ impl<R: BufRead> ScanResultIterator<R> {
                 })?;
                 let type_idx = columns.iter().position(|&c| c == "type");
 
+                iter.columns = columns.iter().map(|c| c.trim().to_string()).collect();
                 iter.saddr_idx = saddr_idx;
                 iter.type_idx = type_idx;
                 iter.header_read = true;
@@ -55,6 +59,54 @@ impl<R: BufRead> ScanResultIterator<R> {
     pub fn bytes_read(&self) -> u64 {
         self.bytes_read
     }
+
+    /// The header's column names, in file order.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Consumes the remaining rows into a DataFrame with a column per header field.
+    /// Columns whose values all parse as integers become `Int64`, then as floats
+    /// `Float64`, and `String` otherwise; empty or missing fields are null.
+    pub fn into_dataframe(self) -> Result<DataFrame, IoError> {
+        let names = self.columns.clone();
+        let mut values: Vec<Vec<String>> = vec![Vec::new(); names.len()];
+        for row in self {
+            let mut fields = row?.raw_fields.into_iter();
+            for column in values.iter_mut() {
+                column.push(fields.next().unwrap_or_default());
+            }
+        }
+
+        let columns = names
+            .iter()
+            .zip(values)
+            .map(|(name, values)| typed_column(name, values))
+            .collect();
+        DataFrame::new(columns)
+            .map_err(|e| IoError::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+/// Builds the narrowest of `Int64`, `Float64` or `String` that holds every value.
+fn typed_column(name: &str, values: Vec<String>) -> Column {
+    let present = || values.iter().filter(|v| !v.is_empty());
+    let parse_all =
+        |parse: fn(&str) -> bool| present().next().is_some() && present().all(|v| parse(v));
+
+    if parse_all(|v| v.parse::<i64>().is_ok()) {
+        let parsed: Vec<Option<i64>> = values.iter().map(|v| v.parse().ok()).collect();
+        Column::new(name.into(), parsed)
+    } else if parse_all(|v| v.parse::<f64>().is_ok()) {
+        let parsed: Vec<Option<f64>> = values.iter().map(|v| v.parse().ok()).collect();
+        Column::new(name.into(), parsed)
+    } else {
+        let strings: Vec<Option<String>> = values
+            .into_iter()
+            .map(|v| (!v.is_empty()).then_some(v))
+            .collect();
+        Column::new(name.into(), strings)
+    }
 }
 
 impl<R: BufRead> Iterator for ScanResultIterator<R> {
@@ -115,3 +167,34 @@ impl<R: BufRead> Iterator for ScanResultIterator<R> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_dataframe_keeps_every_field_typed() {
+        let csv = "saddr,type,rtt_ms,ttl,classification\n\
+                   2001:db8::1,129,12.5,54,echoreply\n\
+                   2001:db8::2,1,,250,unreach\n";
+        let df = ScanResultIterator::new(csv.as_bytes())
+            .unwrap()
+            .into_dataframe()
+            .unwrap();
+
+        assert_eq!(df.shape(), (2, 5));
+        let dtypes: Vec<DataType> = df.dtypes();
+        assert_eq!(
+            dtypes,
+            [
+                DataType::String,
+                DataType::Int64,
+                DataType::Float64,
+                DataType::Int64,
+                DataType::String,
+            ]
+        );
+        assert_eq!(df.column("rtt_ms").unwrap().null_count(), 1);
+        assert_eq!(df.column("ttl").unwrap().i64().unwrap().get(1), Some(250));
+    }
+}
@@ -1,2 +1,615 @@
 pub mod analysis;
+pub mod entropy_plugin;
 mod formats;
+mod progress;
+
+use ipnet::Ipv6Net;
+use plugin::contracts::AbsorbField;
+use polars::prelude::*;
+use std::io::Error as IoError;
+use std::net::Ipv6Addr;
+
+use analysis::{
+    ByteEntropyAnalysis, CombinedAnalysis, CountAnalysis, DispersionAnalysis, HierarchyAnalysis,
+    IidPatternAnalysis, PredicateAnomalyAnalysis, PrefixAnalysis, ResponseTypeAnalysis,
+    ShannonEntropyAnalysis, StatisticsAnalysis, SubnetAnalysis, TtlAnalysis, rtt_summary,
+};
+pub use formats::{Format, IpListIterator, ScanResultIterator, ScanResultRow, identify_format};
+use progress::ProgressTracker;
+pub use progress::set_progress_hidden;
+
+/// The analyses that can be run over a column of IPv6 addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnalysisType {
+    /// Pairwise Hamming distance statistics
+    Dispersion,
+    /// Shannon entropy over a bit range
+    Entropy { start_bit: u8, end_bit: u8 },
+    /// Shannon entropy of the byte distribution over a byte range, in bits per byte
+    ByteEntropy { start_byte: u8, end_byte: u8 },
+    /// Most populated subnets at a prefix length
+    Subnets {
+        max_subnets: usize,
+        prefix_length: u8,
+    },
+    /// Most populated allocations, attributing each address to the most specific one containing it
+    Allocations {
+        max_subnets: usize,
+        allocations: Vec<Ipv6Net>,
+    },
+    /// Most populated subnets at each of several prefix lengths, counted in one pass
+    Hierarchy {
+        prefix_lengths: Vec<u8>,
+        max_subnets: usize,
+    },
+    /// Number of addresses matching each predicate, or only the named one
+    Counts { predicate: Option<String> },
+    /// Breakdown of interface identifiers into low-byte, EUI-64, wordy, embedded-IPv4 and random
+    IidPatterns,
+    /// The predicates each address matches, flagging combinations that should be impossible
+    PredicateAnomalies { flagged_only: bool },
+    /// Total, unique and duplicate address counts; `approximate` estimates uniques in bounded memory
+    Statistics { approximate: bool },
+    /// Number of scan results per ICMPv6 response type
+    ResponseTypes,
+    /// Count, min, max, mean, median, p95 and p99 of scan round-trip times
+    RttSummary,
+    /// Replies per inferred initial TTL (and so likely OS) and hop count
+    TtlHops,
+    /// Number of addresses contained in each of the given prefixes
+    Prefixes { prefixes: Vec<Ipv6Net> },
+    /// Counts, dispersion, entropy and subnets in a single pass, as one long report
+    All {
+        max_subnets: usize,
+        prefix_length: u8,
+    },
+}
+
+impl AnalysisType {
+    /// Names and types of the columns [`analyze`] returns for this analysis, in order.
+    pub fn output_schema(&self) -> Vec<(&'static str, DataType)> {
+        match self {
+            AnalysisType::Dispersion => vec![
+                ("min_distance", DataType::UInt32),
+                ("max_distance", DataType::UInt32),
+                ("avg_distance", DataType::Float64),
+                ("total_pairs", DataType::UInt64),
+            ],
+            AnalysisType::Entropy { .. } => vec![
+                ("entropy", DataType::Float64),
+                ("total_bits", DataType::UInt64),
+                ("bit_distribution", DataType::String),
+            ],
+            AnalysisType::ByteEntropy { .. } => vec![
+                ("entropy", DataType::Float64),
+                ("total_bytes", DataType::UInt64),
+                ("distinct_bytes", DataType::UInt64),
+            ],
+            AnalysisType::Subnets { .. } | AnalysisType::Allocations { .. } => {
+                vec![("subnet", DataType::String), ("count", DataType::UInt64)]
+            }
+            AnalysisType::Hierarchy { .. } => vec![
+                ("prefix_length", DataType::UInt32),
+                ("subnet", DataType::String),
+                ("count", DataType::UInt64),
+            ],
+            AnalysisType::Counts { .. } => vec![
+                ("predicate", DataType::String),
+                ("count", DataType::UInt64),
+                ("percentage", DataType::Float64),
+            ],
+            AnalysisType::IidPatterns => vec![
+                ("pattern", DataType::String),
+                ("count", DataType::UInt64),
+                ("percentage", DataType::Float64),
+            ],
+            AnalysisType::PredicateAnomalies { .. } => vec![
+                ("address", DataType::String),
+                ("predicates", DataType::String),
+                ("anomalous", DataType::Boolean),
+                ("reason", DataType::String),
+            ],
+            AnalysisType::Statistics { .. } => vec![
+                ("total_count", DataType::UInt64),
+                ("unique_count", DataType::UInt64),
+                ("unique_count_error", DataType::Float64),
+                ("duplicate_count", DataType::UInt64),
+                ("duplication_ratio", DataType::Float64),
+            ],
+            AnalysisType::ResponseTypes => vec![
+                ("type", DataType::UInt32),
+                ("name", DataType::String),
+                ("count", DataType::UInt64),
+            ],
+            AnalysisType::RttSummary => vec![
+                ("count", DataType::UInt64),
+                ("min", DataType::Float64),
+                ("max", DataType::Float64),
+                ("mean", DataType::Float64),
+                ("median", DataType::Float64),
+                ("p95", DataType::Float64),
+                ("p99", DataType::Float64),
+            ],
+            AnalysisType::TtlHops => vec![
+                ("os_guess", DataType::String),
+                ("initial_ttl", DataType::UInt32),
+                ("hops", DataType::UInt32),
+                ("count", DataType::UInt64),
+            ],
+            AnalysisType::Prefixes { .. } => vec![
+                ("prefix", DataType::String),
+                ("count", DataType::UInt64),
+                ("percentage", DataType::Float64),
+            ],
+            AnalysisType::All { .. } => vec![
+                ("analysis", DataType::String),
+                ("metric", DataType::String),
+                ("value", DataType::String),
+            ],
+        }
+    }
+}
+
+/// Runs `analysis_type` over the first column of `df`, which must hold address strings
+/// (or ICMPv6 type values for [`AnalysisType::ResponseTypes`], round-trip times for
+/// [`AnalysisType::RttSummary`], received TTLs for [`AnalysisType::TtlHops`]).
+pub fn analyze(df: DataFrame, analysis_type: AnalysisType) -> Result<DataFrame, IoError> {
+    let Some(series) = df.get_columns().first() else {
+        return Err(IoError::new(
+            std::io::ErrorKind::InvalidData,
+            "No data to analyze",
+        ));
+    };
+
+    match analysis_type {
+        AnalysisType::Dispersion => {
+            let mut analyzer = DispersionAnalysis::new();
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::Entropy { start_bit, end_bit } => {
+            let mut analyzer = ShannonEntropyAnalysis::new_with_options(start_bit, end_bit);
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::ByteEntropy {
+            start_byte,
+            end_byte,
+        } => {
+            let mut analyzer = ByteEntropyAnalysis::new_with_options(start_byte, end_byte);
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::Subnets {
+            max_subnets,
+            prefix_length,
+        } => {
+            let mut analyzer = SubnetAnalysis::new_with_options(max_subnets, prefix_length);
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::Hierarchy {
+            prefix_lengths,
+            max_subnets,
+        } => {
+            let mut analyzer = HierarchyAnalysis::new(&prefix_lengths, max_subnets)
+                .map_err(|e| IoError::new(std::io::ErrorKind::InvalidInput, e))?;
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::Allocations {
+            max_subnets,
+            allocations,
+        } => {
+            let mut analyzer = SubnetAnalysis::new_with_allocations(max_subnets, &allocations);
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::Counts { predicate } => {
+            let mut analyzer = CountAnalysis::new(predicate.clone());
+            if analyzer.evaluated_predicates().is_empty() {
+                return Err(IoError::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "No predicate found with name: {}",
+                        predicate.unwrap_or_default()
+                    ),
+                ));
+            }
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::PredicateAnomalies { flagged_only } => {
+            let mut analyzer = PredicateAnomalyAnalysis::new(flagged_only);
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::IidPatterns => {
+            let mut analyzer = IidPatternAnalysis::new();
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::Statistics { approximate } => {
+            let mut analyzer = if approximate {
+                StatisticsAnalysis::new_approximate()
+            } else {
+                StatisticsAnalysis::new()
+            };
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::Prefixes { prefixes } => {
+            let mut analyzer = PrefixAnalysis::new(prefixes);
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::All {
+            max_subnets,
+            prefix_length,
+        } => {
+            let mut analyzer = CombinedAnalysis::new(max_subnets, prefix_length);
+            analyze_column(series, &mut analyzer, df.height())?;
+            Ok(analyzer.finalize())
+        }
+        AnalysisType::ResponseTypes => {
+            let mut analyzer = ResponseTypeAnalysis::new();
+            Ok(analyzer.absorb_series(series.as_materialized_series()))
+        }
+        AnalysisType::TtlHops => {
+            let mut analyzer = TtlAnalysis::new();
+            Ok(analyzer.absorb_series(series.as_materialized_series()))
+        }
+        AnalysisType::RttSummary => rtt_summary(series).map_err(|e| {
+            IoError::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Failed to summarize round-trip times: {}", e),
+            )
+        }),
+    }
+}
+
+fn analyze_column<A: AbsorbField<Ipv6Addr>>(
+    series: &Column,
+    analyzer: &mut A,
+    total_rows: usize,
+) -> Result<(), IoError> {
+    let mut tracker = ProgressTracker::new(total_rows as u64, "addresses");
+    for item in series.str().map_err(|e| {
+        IoError::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Failed to convert series to string: {}", e),
+        )
+    })? {
+        if let Some(addr_str) = item {
+            if let Ok(addr) = addr_str.parse::<Ipv6Addr>() {
+                analyzer.absorb(addr);
+            }
+
+            tracker.increment(tracker.count as u64);
+        }
+    }
+
+    tracker.finish(true);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses(addrs: &[&str]) -> DataFrame {
+        DataFrame::new(vec![Column::new("address".into(), addrs)]).unwrap()
+    }
+
+    #[test]
+    fn test_dispersion_returns_pairwise_distances() {
+        let df = addresses(&["::", "::1", "::3"]);
+        let out = analyze(df, AnalysisType::Dispersion).unwrap();
+        assert_eq!(
+            out.get_column_names_str(),
+            [
+                "min_distance",
+                "max_distance",
+                "avg_distance",
+                "total_pairs"
+            ]
+        );
+        assert_eq!(
+            out.column("min_distance").unwrap().u32().unwrap().get(0),
+            Some(1)
+        );
+        assert_eq!(
+            out.column("max_distance").unwrap().u32().unwrap().get(0),
+            Some(2)
+        );
+        assert_eq!(
+            out.column("total_pairs").unwrap().u64().unwrap().get(0),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_entropy_returns_single_row() {
+        let df = addresses(&["::", "::ff"]);
+        let analysis = AnalysisType::Entropy {
+            start_bit: 120,
+            end_bit: 128,
+        };
+        let out = analyze(df, analysis).unwrap();
+        assert_eq!(
+            out.get_column_names_str(),
+            ["entropy", "total_bits", "bit_distribution"]
+        );
+        assert_eq!(
+            out.column("total_bits").unwrap().u64().unwrap().get(0),
+            Some(16)
+        );
+        assert_eq!(
+            out.column("entropy").unwrap().f64().unwrap().get(0),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn test_byte_entropy_of_zero_and_random_addresses() {
+        let byte_entropy = |df: DataFrame| {
+            let analysis = AnalysisType::ByteEntropy {
+                start_byte: 0,
+                end_byte: 16,
+            };
+            let out = analyze(df, analysis).unwrap();
+            out.column("entropy")
+                .unwrap()
+                .f64()
+                .unwrap()
+                .get(0)
+                .unwrap()
+        };
+
+        assert_eq!(byte_entropy(addresses(&["::", "::"])), 0.0);
+
+        // xorshift64 stands in for uniformly random addresses
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let random: Vec<String> = (0..2000)
+            .map(|_| {
+                let bits = (u128::from(next()) << 64) | u128::from(next());
+                Ipv6Addr::from(bits).to_string()
+            })
+            .collect();
+        let random: Vec<&str> = random.iter().map(String::as_str).collect();
+        let entropy = byte_entropy(addresses(&random));
+        assert!(entropy > 7.9 && entropy <= 8.0, "{}", entropy);
+    }
+
+    #[test]
+    fn test_subnets_returns_top_prefixes() {
+        let df = addresses(&["2001:db8::1", "2001:db8::2", "2001:db8:1::1"]);
+        let analysis = AnalysisType::Subnets {
+            max_subnets: 1,
+            prefix_length: 48,
+        };
+        let out = analyze(df, analysis).unwrap();
+        assert_eq!(out.get_column_names_str(), ["subnet", "count"]);
+        assert_eq!(out.height(), 1);
+        assert_eq!(
+            out.column("subnet").unwrap().str().unwrap().get(0),
+            Some("2001:db8::/48")
+        );
+        assert_eq!(out.column("count").unwrap().u64().unwrap().get(0), Some(2));
+    }
+
+    #[test]
+    fn test_counts_returns_predicate_matches() {
+        let df = addresses(&["::1", "2001:db8::1", "fe80::1"]);
+        let out = analyze(df, AnalysisType::Counts { predicate: None }).unwrap();
+        assert_eq!(
+            out.get_column_names_str(),
+            ["predicate", "count", "percentage"]
+        );
+        let predicates: Vec<&str> = out
+            .column("predicate")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert!(predicates.contains(&"loopback"));
+        assert!(predicates.contains(&"documentation"));
+        assert!(predicates.contains(&"link_local"));
+    }
+
+    #[test]
+    fn test_prefixes_returns_per_prefix_counts() {
+        let df = addresses(&["2001:db8::1", "2001:db8:1::1", "fe80::1"]);
+        let analysis = AnalysisType::Prefixes {
+            prefixes: vec!["2001:db8::/32".parse().unwrap()],
+        };
+        let out = analyze(df, analysis).unwrap();
+        assert_eq!(
+            out.get_column_names_str(),
+            ["prefix", "count", "percentage"]
+        );
+        assert_eq!(out.column("count").unwrap().u64().unwrap().get(0), Some(2));
+    }
+
+    #[test]
+    fn test_declared_schema_matches_output() {
+        let addrs = || addresses(&["::", "::1", "2001:db8::1"]);
+        let analyses = [
+            (AnalysisType::Dispersion, addrs()),
+            (
+                AnalysisType::Entropy {
+                    start_bit: 0,
+                    end_bit: 128,
+                },
+                addrs(),
+            ),
+            (
+                AnalysisType::ByteEntropy {
+                    start_byte: 0,
+                    end_byte: 16,
+                },
+                addrs(),
+            ),
+            (
+                AnalysisType::Subnets {
+                    max_subnets: 10,
+                    prefix_length: 64,
+                },
+                addrs(),
+            ),
+            (
+                AnalysisType::Allocations {
+                    max_subnets: 10,
+                    allocations: vec!["2001:db8::/32".parse().unwrap()],
+                },
+                addrs(),
+            ),
+            (
+                AnalysisType::Hierarchy {
+                    prefix_lengths: vec![32, 64],
+                    max_subnets: 10,
+                },
+                addrs(),
+            ),
+            (AnalysisType::Counts { predicate: None }, addrs()),
+            (AnalysisType::IidPatterns, addrs()),
+            (
+                AnalysisType::PredicateAnomalies {
+                    flagged_only: false,
+                },
+                addrs(),
+            ),
+            (AnalysisType::Statistics { approximate: false }, addrs()),
+            (AnalysisType::Statistics { approximate: true }, addrs()),
+            (
+                AnalysisType::Prefixes {
+                    prefixes: vec!["2001:db8::/32".parse().unwrap()],
+                },
+                addrs(),
+            ),
+            (
+                AnalysisType::All {
+                    max_subnets: 10,
+                    prefix_length: 64,
+                },
+                addrs(),
+            ),
+            (
+                AnalysisType::ResponseTypes,
+                DataFrame::new(vec![Column::new("type".into(), &[129i64, 1])]).unwrap(),
+            ),
+            (
+                AnalysisType::RttSummary,
+                DataFrame::new(vec![Column::new("rtt_ms".into(), &[1u64, 2])]).unwrap(),
+            ),
+            (
+                AnalysisType::TtlHops,
+                DataFrame::new(vec![Column::new("ttl".into(), &[54i64, 250])]).unwrap(),
+            ),
+        ];
+        for (analysis, df) in analyses {
+            let out = analyze(df, analysis.clone()).unwrap();
+            let actual: Vec<(&str, DataType)> = out
+                .get_columns()
+                .iter()
+                .map(|c| (c.name().as_str(), c.dtype().clone()))
+                .collect();
+            assert_eq!(actual, analysis.output_schema(), "{:?}", analysis);
+        }
+    }
+
+    #[test]
+    fn test_combined_report_matches_individual_analyses() {
+        let addrs = [
+            "2001:db8::1",
+            "2001:db8::2",
+            "2001:db8:1::5",
+            "fe80::1",
+            "::1",
+        ];
+        let report = analyze(
+            addresses(&addrs),
+            AnalysisType::All {
+                max_subnets: 10,
+                prefix_length: 64,
+            },
+        )
+        .unwrap();
+        let value = |analysis: &str, metric: &str| {
+            (0..report.height())
+                .find(|&i| {
+                    report.column("analysis").unwrap().str().unwrap().get(i) == Some(analysis)
+                        && report.column("metric").unwrap().str().unwrap().get(i) == Some(metric)
+                })
+                .map(|i| {
+                    report
+                        .column("value")
+                        .unwrap()
+                        .str()
+                        .unwrap()
+                        .get(i)
+                        .unwrap()
+                        .to_string()
+                })
+        };
+
+        let counts = analyze(addresses(&addrs), AnalysisType::Counts { predicate: None }).unwrap();
+        assert!(counts.height() > 0);
+        let predicates = counts.column("predicate").unwrap().str().unwrap();
+        let totals = counts.column("count").unwrap().u64().unwrap();
+        for (predicate, count) in predicates
+            .into_no_null_iter()
+            .zip(totals.into_no_null_iter())
+        {
+            assert_eq!(
+                value("counts", predicate),
+                Some(count.to_string()),
+                "{}",
+                predicate
+            );
+        }
+
+        let dispersion = analyze(addresses(&addrs), AnalysisType::Dispersion).unwrap();
+        let pairs = dispersion
+            .column("total_pairs")
+            .unwrap()
+            .u64()
+            .unwrap()
+            .get(0)
+            .unwrap();
+        assert_eq!(value("dispersion", "total_pairs"), Some(pairs.to_string()));
+
+        let subnets = AnalysisType::Subnets {
+            max_subnets: 10,
+            prefix_length: 64,
+        };
+        let subnets = analyze(addresses(&addrs), subnets).unwrap();
+        let subnet = subnets
+            .column("subnet")
+            .unwrap()
+            .str()
+            .unwrap()
+            .get(0)
+            .unwrap();
+        let count = subnets
+            .column("count")
+            .unwrap()
+            .u64()
+            .unwrap()
+            .get(0)
+            .unwrap();
+        assert_eq!(value("subnets", subnet), Some(count.to_string()));
+    }
+
+    #[test]
+    fn test_empty_frame_is_an_error() {
+        assert!(analyze(DataFrame::empty(), AnalysisType::Counts { predicate: None }).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+static PROGRESS_HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Hides the progress bars analyses draw from now on, e.g. for a `--quiet` run.
+pub fn set_progress_hidden(hidden: bool) {
+    PROGRESS_HIDDEN.store(hidden, Ordering::Relaxed);
+}
+
+/// A progress bar of length `len`, hidden if [`set_progress_hidden`] was set.
+pub(crate) fn new_progress_bar(len: u64) -> ProgressBar {
+    if PROGRESS_HIDDEN.load(Ordering::Relaxed) {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(len)
+    }
+}
+
+pub(crate) struct ProgressTracker {
+    pb: ProgressBar,
+    pub(crate) count: usize,
+    bytes_read: u64,
+    item_type: &'static str,
+    last_update: Instant,
+    update_interval: Duration,
+}
+
+impl ProgressTracker {
+    pub(crate) fn new(total_size: u64, item_type: &'static str) -> Self {
+        let pb = new_progress_bar(total_size);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] {msg} [{bar:20.cyan/grey}] {bytes}/{total_bytes}")
+                .expect("Failed to create progress bar template")
+                .progress_chars("█░"),
+        );
+        pb.set_message(format!("0 {}", item_type));
+
+        Self {
+            pb,
+            count: 0,
+            bytes_read: 0,
+            item_type,
+            last_update: Instant::now(),
+            update_interval: Duration::from_millis(50),
+        }
+    }
+
+    pub(crate) fn increment(&mut self, current_bytes: u64) {
+        self.count += 1;
+        self.bytes_read = current_bytes;
+
+        let now = Instant::now();
+        if now.duration_since(self.last_update) >= self.update_interval {
+            self.update_progress();
+            self.last_update = now;
+        }
+    }
+
+    fn update_progress(&mut self) {
+        self.pb.set_position(self.bytes_read);
+        self.pb
+            .set_message(format!("Processed {} {}", self.count, self.item_type));
+    }
+
+    pub(crate) fn finish(mut self, success: bool) {
+        self.update_progress();
+
+        if success {
+            self.pb.finish_with_message("Processing complete!");
+        } else {
+            self.pb.abandon_with_message("Processing failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hidden_progress_bars_draw_nothing() {
+        set_progress_hidden(true);
+        let hidden = new_progress_bar(10).is_hidden();
+        set_progress_hidden(false);
+        assert!(hidden);
+    }
+}
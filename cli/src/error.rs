@@ -0,0 +1,68 @@
+use polars::prelude::PolarsError;
+
+/// Why a command failed, grouped so callers such as the gRPC frontend can react to
+/// the category without parsing the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError {
+    /// A file could not be opened, read or written
+    Io(String),
+    /// Input contents such as addresses, networks or CSV fields did not parse
+    Parse(String),
+    /// A dataframe operation failed
+    Polars(String),
+    /// An analysis could not be computed
+    Analysis(String),
+    /// Probing or discovering hosts failed
+    Scan(String),
+    /// Training or sampling a target generation algorithm failed
+    Tga(String),
+    /// Arguments that are inconsistent in a way clap cannot reject on its own
+    InvalidArgument(String),
+    /// The command was cancelled before it finished
+    Cancelled,
+}
+
+impl CliError {
+    /// Short name of the category, e.g. for metric labels.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CliError::Io(_) => "io",
+            CliError::Parse(_) => "parse",
+            CliError::Polars(_) => "polars",
+            CliError::Analysis(_) => "analysis",
+            CliError::Scan(_) => "scan",
+            CliError::Tga(_) => "tga",
+            CliError::InvalidArgument(_) => "invalid_argument",
+            CliError::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Io(msg)
+            | CliError::Parse(msg)
+            | CliError::Polars(msg)
+            | CliError::Analysis(msg)
+            | CliError::Scan(msg)
+            | CliError::Tga(msg)
+            | CliError::InvalidArgument(msg) => f.write_str(msg),
+            CliError::Cancelled => f.write_str("Command cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<PolarsError> for CliError {
+    fn from(e: PolarsError) -> Self {
+        CliError::Polars(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(e: std::io::Error) -> Self {
+        CliError::Io(e.to_string())
+    }
+}
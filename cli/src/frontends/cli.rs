@@ -1,5 +1,6 @@
 use clap::Parser;
 use std::path::PathBuf;
+use tracing_subscriber::filter::LevelFilter;
 
 pub use crate::runner::Commands;
 
@@ -11,18 +12,94 @@ pub use crate::runner::Commands;
     author = "Chase Kanipe"
 )]
 pub struct Cli {
+    /// Log more: -v for info, -vv for debug, -vvv for trace (overrides RUST_LOG)
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub verbose: u8,
 
+    /// Hide progress bars and status lines; results are still printed
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Append logs and status lines to this file instead of printing them to stderr
     #[arg(short, long, value_name = "LOG_FILE")]
     pub log: Option<PathBuf>,
 
+    /// Write the full result to this file (Parquet if it ends in .parquet, CSV otherwise)
+    /// instead of printing it; `-` prints to stdout
     #[arg(short = 'o', long, default_value = "-")]
     pub output_file: String,
 
+    /// Add rows to the end of an existing CSV --output-file instead of overwriting it;
+    /// its columns must match, and a header is only written to a new or empty file
+    #[arg(long)]
+    pub append: bool,
+
+    /// Print at most this many rows, eliding the middle of longer results
+    #[arg(long, value_name = "N")]
+    pub max_rows: Option<usize>,
+
+    /// Decimal places to print float values with
+    #[arg(long, value_name = "DIGITS")]
+    pub precision: Option<usize>,
+
+    /// Disable colors and text styling (also set by a non-empty NO_COLOR variable)
+    #[arg(long)]
+    pub no_color: bool,
+
     #[arg(long, value_name = "SERVER_ADDR")]
     pub remote: Option<String>,
 
+    /// Connection attempts before giving up on the --remote server
+    #[arg(long, value_name = "N", default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    pub connect_attempts: u32,
+
+    /// Seconds each connection attempt to the --remote server may take
+    #[arg(long, value_name = "SECONDS", default_value_t = 5)]
+    pub connect_timeout: u64,
+
+    /// Number of worker threads for parallel analysis (default: available parallelism)
+    #[arg(long, value_name = "N")]
+    pub threads: Option<usize>,
+
+    /// Sort output rows by numeric address value
+    #[arg(long)]
+    pub sort: bool,
+
+    /// Column name addresses are written to and filtered on (default: address)
+    #[arg(long, value_name = "NAME")]
+    pub address_column: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
+
+impl Cli {
+    /// The log level the -v flags ask for, or `None` without any, leaving it to
+    /// RUST_LOG or the default.
+    pub fn verbosity_level(&self) -> Option<LevelFilter> {
+        match self.verbose {
+            0 => None,
+            1 => Some(LevelFilter::INFO),
+            2 => Some(LevelFilter::DEBUG),
+            _ => Some(LevelFilter::TRACE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity_count_sets_level() {
+        let level = |args: &[&str]| {
+            let cli = Cli::try_parse_from(["rmap"].iter().chain(args).chain(&["info"])).unwrap();
+            cli.verbosity_level()
+        };
+        assert_eq!(level(&[]), None);
+        assert_eq!(level(&["-v"]), Some(LevelFilter::INFO));
+        assert_eq!(level(&["-vv"]), Some(LevelFilter::DEBUG));
+        assert_eq!(level(&["-vvv"]), Some(LevelFilter::TRACE));
+        assert_eq!(level(&["-v", "-v", "-v", "-v"]), Some(LevelFilter::TRACE));
+    }
+}
@@ -1,18 +1,24 @@
+use crate::error::CliError;
 use crate::frontends::cli;
+use crate::runner::{Progress, RunContext};
 use indicatif::{ProgressBar, ProgressStyle};
 use ipnet::IpNet;
 use metrics::{counter, decrement_gauge, gauge, histogram, increment_gauge};
 use metrics_exporter_prometheus;
 use polars::prelude::*;
+use prost::Message;
 use serde_json;
 use std::net::IpAddr;
 use std::net::Ipv6Addr;
 use std::sync::Arc;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use tga::{EntropyIpTga, TGA};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, mpsc};
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status, transport::Server};
-use tracing::{Level, info, span};
+use tracing::{Level, info, span, warn};
 
 pub mod rmap {
     tonic::include_proto!("rmap");
@@ -20,12 +26,24 @@ pub mod rmap {
 
 use rmap::rmap_service_server::{RmapService, RmapServiceServer};
 use rmap::{
-    DataframeResponse, DiscoverRequest, ExecuteCommandRequest, GenerateRequest, ScanRequest,
+    CommandEvent, DataframeResponse, DiscoverRequest, ExecuteCommandRequest, GenerateRequest,
+    GetStatsRequest, ProgressUpdate, ScanRequest, StatsResponse, command_event,
 };
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct RmapServiceImpl {
     metrics: Arc<Mutex<ServerMetrics>>,
+    max_duration: Option<Duration>,
+}
+
+/// Signals a running command to stop once the request that spawned it goes away,
+/// either because it timed out or because the client disconnected.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
 }
 
 #[derive(Default)]
@@ -42,9 +60,15 @@ impl RmapServiceImpl {
     pub fn new() -> Self {
         Self {
             metrics: Arc::new(Mutex::new(ServerMetrics::default())),
+            max_duration: None,
         }
     }
 
+    pub fn with_max_duration(mut self, max_duration: Option<Duration>) -> Self {
+        self.max_duration = max_duration;
+        self
+    }
+
     async fn record_request(&self, success: bool, operation: &'static str) {
         let mut metrics = self.metrics.lock().await;
         metrics.total_requests += 1;
@@ -66,83 +90,93 @@ impl RmapServiceImpl {
     async fn record_error(&self, error_type: &'static str, operation: &'static str) {
         counter!("rmap_errors_total", 1, "error_type" => error_type, "operation" => operation);
     }
-}
-
-#[tonic::async_trait]
-impl RmapService for RmapServiceImpl {
-    async fn generate(
-        &self,
-        _request: Request<GenerateRequest>,
-    ) -> Result<Response<DataframeResponse>, Status> {
-        let _span = span!(Level::INFO, "grpc_generate").entered();
-
-        let result = Err(Status::unimplemented("Use ExecuteCommand for all commands"));
-
-        info!("Generate command completed");
-        result
-    }
 
-    async fn scan(
-        &self,
-        _request: Request<ScanRequest>,
-    ) -> Result<Response<DataframeResponse>, Status> {
-        let _span = span!(Level::INFO, "grpc_scan").entered();
-
-        let result = Err(Status::unimplemented("Use ExecuteCommand for all commands"));
-
-        info!("Scan command completed");
-        result
-    }
-
-    async fn discover(
-        &self,
-        _request: Request<DiscoverRequest>,
-    ) -> Result<Response<DataframeResponse>, Status> {
-        let _span = span!(Level::INFO, "grpc_discover").entered();
-
-        let result = Err(Status::unimplemented("Use ExecuteCommand for all commands"));
-
-        info!("Discover command completed");
-        result
+    async fn record_addresses(&self, command: &cli::Commands, count: u64) {
+        let mut metrics = self.metrics.lock().await;
+        match command {
+            cli::Commands::Generate { .. } => {
+                metrics.total_addresses_generated += count;
+                counter!("rmap_addresses_generated_total", count);
+            }
+            cli::Commands::Scan { .. } => {
+                metrics.total_addresses_scanned += count;
+                counter!("rmap_addresses_scanned_total", count);
+            }
+            cli::Commands::Discover { .. } => {
+                metrics.total_addresses_discovered += count;
+                counter!("rmap_addresses_discovered_total", count);
+            }
+            _ => {}
+        }
     }
 
-    async fn execute_command(
+    async fn run_command(
         &self,
-        request: Request<ExecuteCommandRequest>,
-    ) -> Result<Response<DataframeResponse>, Status> {
+        command_json: &str,
+        mut ctx: RunContext,
+    ) -> Result<DataframeResponse, Status> {
         let start_time = Instant::now();
-        let req = request.into_inner();
-        let command: cli::Commands = match serde_json::from_str(&req.command_json) {
+        let command = match cli::Commands::from_json(command_json) {
             Ok(cmd) => cmd,
             Err(e) => {
-                return Ok(Response::new(DataframeResponse {
-                    dataframe_json: "".to_string(),
-                    success: false,
-                    error: format!("Failed to deserialize command: {}", e),
-                }));
+                self.record_request(false, "execute_command").await;
+                self.record_error("deserialize", "execute_command").await;
+                return Err(failure_status(
+                    tonic::Code::InvalidArgument,
+                    format!("Failed to deserialize command: {}", e),
+                ));
             }
         };
-        let result = command.run();
+        let operation = operation_name(&command);
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let _cancel_guard = CancelOnDrop(Arc::clone(&cancel));
+        ctx.cancel = Arc::clone(&cancel);
+        let handle = tokio::task::spawn_blocking(move || {
+            let result = command.run_with_context(&ctx);
+            (command, result)
+        });
+
+        let joined = match self.max_duration {
+            Some(limit) => match tokio::time::timeout(limit, handle).await {
+                Ok(joined) => joined,
+                Err(_) => {
+                    cancel.store(true, Ordering::Relaxed);
+                    self.record_request(false, operation).await;
+                    self.record_error("deadline_exceeded", operation).await;
+                    return Err(Status::deadline_exceeded(format!(
+                        "Command exceeded maximum duration of {}s",
+                        limit.as_secs_f64()
+                    )));
+                }
+            },
+            None => handle.await,
+        };
+        let (command, result) =
+            joined.map_err(|e| Status::internal(format!("Command task failed: {}", e)))?;
         let duration = start_time.elapsed();
         match result {
             Ok(df) => {
                 let df_json = match serde_json::to_string(&df) {
                     Ok(json) => json,
                     Err(e) => {
-                        return Ok(Response::new(DataframeResponse {
-                            dataframe_json: "".to_string(),
-                            success: false,
-                            error: format!("Failed to serialize DataFrame: {}", e),
-                        }));
+                        self.record_request(false, operation).await;
+                        self.record_error("serialize", operation).await;
+                        return Err(failure_status(
+                            tonic::Code::Internal,
+                            format!("Failed to serialize DataFrame: {}", e),
+                        ));
                     }
                 };
                 histogram!(
                     "rmap_execute_command_duration_ms",
                     duration.as_millis() as f64
                 );
+                self.record_request(true, operation).await;
+                self.record_addresses(&command, df.height() as u64).await;
 
                 match command {
-                    cli::Commands::Generate { count, unique } => {
+                    cli::Commands::Generate { count, unique, .. } => {
                         info!(
                             "Generate command completed: {} addresses, unique: {}",
                             count, unique
@@ -156,12 +190,36 @@ impl RmapService for RmapServiceImpl {
                             scan_type, target
                         );
                     }
-                    cli::Commands::Discover => {
+                    cli::Commands::Discover { .. } => {
                         info!("Discover command completed");
                     }
+                    cli::Commands::Filter { file, export, .. } => {
+                        info!(
+                            "Filter command completed: file {:?}, exported to {:?}",
+                            file, export
+                        );
+                    }
+                    cli::Commands::Join { left, right, how } => {
+                        info!(
+                            "Join command completed: {:?} and {:?}, how: {:?}",
+                            left, right, how
+                        );
+                    }
+                    cli::Commands::Diff { a, b } | cli::Commands::Intersect { a, b } => {
+                        info!("Set operation {} completed: {:?} and {:?}", operation, a, b);
+                    }
+                    cli::Commands::Sample { file, .. } => {
+                        info!("Sample command completed: file {:?}", file);
+                    }
                     cli::Commands::View { file, .. } => {
                         info!("View command completed: file {:?}", file);
                     }
+                    cli::Commands::Plugin { file, args } => {
+                        info!(
+                            "Plugin command completed: file {:?}, args: {:?}",
+                            file, args
+                        );
+                    }
                     cli::Commands::Analyze { file, analysis, .. } => {
                         info!(
                             "Analyze command completed: file {:?}, analysis: {:?}",
@@ -171,41 +229,203 @@ impl RmapService for RmapServiceImpl {
                     cli::Commands::Train => {
                         info!("Train command completed");
                     }
+                    cli::Commands::ListPredicates => {
+                        info!("List predicates command completed");
+                    }
+                    cli::Commands::Info => {
+                        info!("Info command completed");
+                    }
+                    cli::Commands::DescribeAnalysis { analysis } => {
+                        info!("Describe analysis command completed: {:?}", analysis);
+                    }
                     cli::Commands::Serve { .. } => {
                     }
                 }
 
-                Ok(Response::new(DataframeResponse {
+                Ok(DataframeResponse {
                     dataframe_json: df_json,
                     success: true,
                     error: "".to_string(),
-                }))
+                })
+            }
+            Err(e) => {
+                self.record_request(false, operation).await;
+                self.record_error(e.kind(), operation).await;
+                Err(command_status(&e))
             }
-            Err(e) => Ok(Response::new(DataframeResponse {
-                dataframe_json: "".to_string(),
-                success: false,
-                error: e,
-            })),
         }
     }
 }
 
+/// The status a failed command is reported with, so clients can tell bad input
+/// from server-side failures without parsing the message.
+fn command_status(e: &CliError) -> Status {
+    let code = match e {
+        CliError::InvalidArgument(_) | CliError::Parse(_) => tonic::Code::InvalidArgument,
+        CliError::Io(_) => tonic::Code::FailedPrecondition,
+        CliError::Scan(_) => tonic::Code::Unavailable,
+        CliError::Polars(_) | CliError::Analysis(_) | CliError::Tga(_) => tonic::Code::Internal,
+        CliError::Cancelled => tonic::Code::Cancelled,
+    };
+    failure_status(code, e.to_string())
+}
+
+/// A `code` status whose details carry the failed `DataframeResponse`, for clients
+/// that still read `success` and `error` from the body.
+fn failure_status(code: tonic::Code, error: String) -> Status {
+    let response = DataframeResponse {
+        dataframe_json: "".to_string(),
+        success: false,
+        error: error.clone(),
+    };
+    Status::with_details(code, error, response.encode_to_vec().into())
+}
+
+fn operation_name(command: &cli::Commands) -> &'static str {
+    match command {
+        cli::Commands::Scan { .. } => "scan",
+        cli::Commands::Discover { .. } => "discover",
+        cli::Commands::Generate { .. } => "generate",
+        cli::Commands::Train => "train",
+        cli::Commands::ListPredicates => "list_predicates",
+        cli::Commands::Info => "info",
+        cli::Commands::DescribeAnalysis { .. } => "describe_analysis",
+        cli::Commands::Analyze { .. } => "analyze",
+        cli::Commands::Filter { .. } => "filter",
+        cli::Commands::Join { .. } => "join",
+        cli::Commands::Diff { .. } => "diff",
+        cli::Commands::Intersect { .. } => "intersect",
+        cli::Commands::Sample { .. } => "sample",
+        cli::Commands::View { .. } => "view",
+        cli::Commands::Plugin { .. } => "plugin",
+        cli::Commands::Serve { .. } => "serve",
+    }
+}
+
+#[tonic::async_trait]
+impl RmapService for RmapServiceImpl {
+    async fn generate(
+        &self,
+        _request: Request<GenerateRequest>,
+    ) -> Result<Response<DataframeResponse>, Status> {
+        let _span = span!(Level::INFO, "grpc_generate").entered();
+
+        let result = Err(Status::unimplemented("Use ExecuteCommand for all commands"));
+
+        info!("Generate command completed");
+        result
+    }
+
+    async fn scan(
+        &self,
+        _request: Request<ScanRequest>,
+    ) -> Result<Response<DataframeResponse>, Status> {
+        let _span = span!(Level::INFO, "grpc_scan").entered();
+
+        let result = Err(Status::unimplemented("Use ExecuteCommand for all commands"));
+
+        info!("Scan command completed");
+        result
+    }
+
+    async fn discover(
+        &self,
+        _request: Request<DiscoverRequest>,
+    ) -> Result<Response<DataframeResponse>, Status> {
+        let _span = span!(Level::INFO, "grpc_discover").entered();
+
+        let result = Err(Status::unimplemented("Use ExecuteCommand for all commands"));
+
+        info!("Discover command completed");
+        result
+    }
+
+    async fn execute_command(
+        &self,
+        request: Request<ExecuteCommandRequest>,
+    ) -> Result<Response<DataframeResponse>, Status> {
+        let req = request.into_inner();
+        let response = self
+            .run_command(&req.command_json, RunContext::default())
+            .await?;
+        Ok(Response::new(response))
+    }
+
+    type ExecuteCommandStreamStream = ReceiverStream<Result<CommandEvent, Status>>;
+
+    async fn execute_command_stream(
+        &self,
+        request: Request<ExecuteCommandRequest>,
+    ) -> Result<Response<Self::ExecuteCommandStreamStream>, Status> {
+        let req = request.into_inner();
+        let (tx, rx) = mpsc::channel(32);
+
+        let progress_tx = tx.clone();
+        let ctx = RunContext {
+            on_progress: Some(Box::new(move |progress: Progress| {
+                // Progress is best-effort; drop updates rather than stall the command.
+                let _ = progress_tx.try_send(Ok(CommandEvent {
+                    event: Some(command_event::Event::Progress(ProgressUpdate {
+                        message: progress.message,
+                        position: progress.position,
+                        total: progress.total,
+                    })),
+                }));
+            })),
+            ..Default::default()
+        };
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                result = service.run_command(&req.command_json, ctx) => {
+                    let event = result.map(|response| CommandEvent {
+                        event: Some(command_event::Event::Result(response)),
+                    });
+                    let _ = tx.send(event).await;
+                }
+                _ = tx.closed() => {
+                    info!("Client disconnected, cancelling command");
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn get_stats(
+        &self,
+        _request: Request<GetStatsRequest>,
+    ) -> Result<Response<StatsResponse>, Status> {
+        let metrics = self.metrics.lock().await;
+        Ok(Response::new(StatsResponse {
+            total_requests: metrics.total_requests,
+            successful_requests: metrics.successful_requests,
+            failed_requests: metrics.failed_requests,
+            total_addresses_generated: metrics.total_addresses_generated,
+            total_addresses_scanned: metrics.total_addresses_scanned,
+            total_addresses_discovered: metrics.total_addresses_discovered,
+        }))
+    }
+}
+
 pub async fn run_server(
     addr: &str,
     metrics_port: Option<u16>,
+    max_duration: Option<Duration>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let addr = addr.parse()?;
-    let service = RmapServiceImpl::new();
+    let service = RmapServiceImpl::new().with_max_duration(max_duration);
     let metrics_port = metrics_port.unwrap_or(9090);
     if metrics_port == 0 {
-        println!("Metrics disabled (port 0 specified)");
+        info!("Metrics disabled (port 0 specified)");
     } else {
         match metrics_exporter_prometheus::PrometheusBuilder::new()
             .with_http_listener(([0, 0, 0, 0], metrics_port))
             .install()
         {
             Ok(_) => {
-                println!(
+                info!(
                     "Prometheus metrics available at http://0.0.0.0:{}/metrics",
                     metrics_port
                 );
@@ -213,19 +433,19 @@ pub async fn run_server(
                 gauge!("rmap_server_up", 1.0);
             }
             Err(e) => {
-                eprintln!(
-                    "Warning: Failed to install Prometheus metrics exporter on port {}: {}",
+                warn!(
+                    "Failed to install Prometheus metrics exporter on port {}: {}",
                     metrics_port, e
                 );
-                eprintln!(
+                warn!(
                     "Metrics will not be available. Try a different port or ensure port {} is not in use.",
                     metrics_port
                 );
-                eprintln!("You can disable metrics by using --metrics-port 0");
+                warn!("You can disable metrics by using --metrics-port 0");
             }
         }
     }
-    println!("Starting gRPC server on {}", addr);
+    info!("Starting gRPC server on {}", addr);
     Server::builder()
         .add_service(RmapServiceServer::new(service))
         .serve(addr)
@@ -236,8 +456,14 @@ pub async fn run_server(
 pub async fn execute_remote_command(
     server_addr: &str,
     command: &cli::Commands,
+    quiet: bool,
+    retry: RetryPolicy,
 ) -> Result<DataFrame, Box<dyn std::error::Error>> {
-    let pb = ProgressBar::new_spinner();
+    let pb = if quiet {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new_spinner()
+    };
     pb.set_style(
         ProgressStyle::default_spinner()
             .template("{spinner:.green} {msg}")
@@ -247,16 +473,43 @@ pub async fn execute_remote_command(
     pb.set_message("Connecting to server...");
     pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
-    let mut client = GrpcClient::new(server_addr.to_string()).await?;
+    let mut client =
+        GrpcClient::connect_with_retry(server_addr.to_string(), retry, |attempt, backoff, e| {
+            pb.set_message(format!(
+                "Server unavailable ({}), retrying in {:?} (attempt {}/{})...",
+                e, backoff, attempt, retry.attempts
+            ));
+        })
+        .await?;
 
     pb.set_message("Executing command...");
     let command_json = serde_json::to_string(command)?;
     let request = ExecuteCommandRequest { command_json };
-    let response = client.client.execute_command(request).await?;
+    let mut stream = client
+        .client
+        .execute_command_stream(request)
+        .await?
+        .into_inner();
+
+    let mut response = None;
+    while let Some(event) = stream.next().await {
+        match event?.event {
+            Some(command_event::Event::Progress(progress)) => {
+                pb.set_message(format!(
+                    "{} {}/{}",
+                    progress.message, progress.position, progress.total
+                ));
+            }
+            Some(command_event::Event::Result(result)) => {
+                response = Some(result);
+            }
+            None => {}
+        }
+    }
 
     pb.finish_and_clear();
 
-    let response = response.into_inner();
+    let response = response.ok_or("Server closed the stream without a result")?;
     if !response.success {
         return Err(response.error.into());
     }
@@ -264,20 +517,274 @@ pub async fn execute_remote_command(
     Ok(df)
 }
 
+/// How a client retries a server that is not accepting connections yet.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total connection attempts, including the first
+    pub attempts: u32,
+    /// Longest a single attempt may take
+    pub connect_timeout: Duration,
+    /// Wait after the first failure; doubled after each further one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            connect_timeout: Duration::from_secs(5),
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
 pub struct GrpcClient {
     client: rmap::rmap_service_client::RmapServiceClient<tonic::transport::Channel>,
 }
 
 impl GrpcClient {
     pub async fn new(addr: String) -> Result<Self, tonic::transport::Error> {
+        Self::connect_with_retry(
+            addr,
+            RetryPolicy {
+                attempts: 1,
+                ..Default::default()
+            },
+            |_, _, _| {},
+        )
+        .await
+    }
+
+    /// Connects to `addr`, backing off exponentially between failed attempts.
+    /// `on_retry` is told the number of the attempt that failed, the wait before the
+    /// next one and the error.
+    pub async fn connect_with_retry<F>(
+        addr: String,
+        policy: RetryPolicy,
+        on_retry: F,
+    ) -> Result<Self, tonic::transport::Error>
+    where
+        F: Fn(u32, Duration, &tonic::transport::Error),
+    {
         let url = if addr.starts_with("http://") || addr.starts_with("https://") {
             addr
         } else {
             format!("http://{}", addr)
         };
+        let endpoint =
+            tonic::transport::Endpoint::from_shared(url)?.connect_timeout(policy.connect_timeout);
+
+        let mut backoff = policy.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            match endpoint.connect().await {
+                Ok(channel) => {
+                    let client = rmap::rmap_service_client::RmapServiceClient::new(channel);
+                    return Ok(GrpcClient { client });
+                }
+                Err(e) if attempt < policy.attempts => {
+                    on_retry(attempt, backoff, &e);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(policy.max_backoff);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub async fn get_stats(&mut self) -> Result<StatsResponse, Status> {
+        let response = self.client.get_stats(GetStatsRequest {}).await?;
+        Ok(response.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_client_retries_until_server_is_up() {
+        // Reserve a free port, then leave it closed until the server starts
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            Server::builder()
+                .add_service(RmapServiceServer::new(RmapServiceImpl::new()))
+                .serve(addr)
+                .await
+                .unwrap();
+        });
+
+        let retries = std::sync::atomic::AtomicU32::new(0);
+        let policy = RetryPolicy {
+            attempts: 20,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let mut client = GrpcClient::connect_with_retry(addr.to_string(), policy, |_, _, _| {
+            retries.fetch_add(1, Ordering::Relaxed);
+        })
+        .await
+        .unwrap();
+
+        assert!(retries.load(Ordering::Relaxed) > 0);
+        assert_eq!(client.get_stats().await.unwrap().total_requests, 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_stats_reflects_requests() {
+        let service = RmapServiceImpl::new();
+        let command = cli::Commands::Generate {
+            count: 5,
+            unique: false,
+            enumerate: false,
+            jsonl: false,
+            constrain: vec![],
+            seed_count: tga::DEMO_SEED_COUNT,
+            seed_hostnames: None,
+            seed: None,
+        };
+        let request = ExecuteCommandRequest {
+            command_json: serde_json::to_string(&command).unwrap(),
+        };
+        let response = service
+            .execute_command(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.success, "{}", response.error);
 
-        let client = rmap::rmap_service_client::RmapServiceClient::connect(url).await?;
+        let bad_request = ExecuteCommandRequest {
+            command_json: "not json".to_string(),
+        };
+        let status = service
+            .execute_command(Request::new(bad_request))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        let body = DataframeResponse::decode(status.details()).unwrap();
+        assert!(!body.success);
+        assert!(body.error.contains("deserialize"), "{}", body.error);
 
-        Ok(GrpcClient { client })
+        let stats = service
+            .get_stats(Request::new(GetStatsRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(stats.total_requests, 2);
+        assert_eq!(stats.successful_requests, 1);
+        assert_eq!(stats.failed_requests, 1);
+        assert_eq!(stats.total_addresses_generated, 5);
+        assert_eq!(stats.total_addresses_scanned, 0);
+        assert_eq!(stats.total_addresses_discovered, 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_command_maps_to_status_code() {
+        let service = RmapServiceImpl::new();
+        let command = cli::Commands::Sample {
+            file: "targets.txt".into(),
+            count: None,
+            fraction: Some(1.5),
+            seed: None,
+        };
+        let request = ExecuteCommandRequest {
+            command_json: serde_json::to_string(&command).unwrap(),
+        };
+        let status = service
+            .execute_command(Request::new(request))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(
+            status.message().contains("between 0 and 1"),
+            "{}",
+            status.message()
+        );
+
+        assert_eq!(
+            command_status(&CliError::Io("missing".to_string())).code(),
+            tonic::Code::FailedPrecondition
+        );
+        assert_eq!(
+            command_status(&CliError::Cancelled).code(),
+            tonic::Code::Cancelled
+        );
+    }
+
+    #[tokio::test]
+    async fn test_command_exceeding_max_duration_is_aborted() {
+        let service = RmapServiceImpl::new().with_max_duration(Some(Duration::from_millis(1)));
+        let command = cli::Commands::Generate {
+            count: 100_000_000,
+            unique: false,
+            enumerate: false,
+            jsonl: false,
+            constrain: vec![],
+            seed_count: tga::DEMO_SEED_COUNT,
+            seed_hostnames: None,
+            seed: None,
+        };
+        let request = ExecuteCommandRequest {
+            command_json: serde_json::to_string(&command).unwrap(),
+        };
+        let status = service
+            .execute_command(Request::new(request))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+
+        let stats = service
+            .get_stats(Request::new(GetStatsRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(stats.failed_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_reports_progress_before_result() {
+        let service = RmapServiceImpl::new();
+        let command = cli::Commands::Generate {
+            count: 5000,
+            unique: false,
+            enumerate: false,
+            jsonl: false,
+            constrain: vec![],
+            seed_count: tga::DEMO_SEED_COUNT,
+            seed_hostnames: None,
+            seed: None,
+        };
+        let request = ExecuteCommandRequest {
+            command_json: serde_json::to_string(&command).unwrap(),
+        };
+        let stream = service
+            .execute_command_stream(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        let events: Vec<_> = stream
+            .map(|event| event.unwrap().event.unwrap())
+            .collect()
+            .await;
+
+        let (last, progress) = events.split_last().unwrap();
+        assert!(!progress.is_empty());
+        assert!(
+            progress
+                .iter()
+                .all(|event| matches!(event, command_event::Event::Progress(_)))
+        );
+        match last {
+            command_event::Event::Result(response) => assert!(response.success),
+            _ => panic!("Expected the final event to be the result"),
+        }
     }
 }
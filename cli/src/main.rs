@@ -7,58 +7,144 @@ use hickory_resolver::config::{ResolverConfig, ResolverOpts};
 use ipnet::IpNet;
 use polars::lazy::dsl::col;
 use polars::prelude::*;
-use sink::print_dataframe;
-use std::fs::File;
+use sink::{PrintOptions, RowWriter, StreamFormat, print_dataframe};
+use std::fs::{File, OpenOptions};
 use std::net::{IpAddr, ToSocketAddrs};
 use std::path::PathBuf;
+use std::sync::Mutex;
 use tracing_indicatif::IndicatifLayer;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
 use indicatif::{ProgressState, ProgressStyle};
 use std::time::Duration;
 use time;
-use tracing::{error, info, info_span};
+use tracing::{error, info_span};
 
-mod analyze;
+mod error;
 mod frontends;
 mod runner;
 mod sink;
 mod source;
+mod units;
 
+use error::CliError;
 use frontends::cli::{Cli, Commands};
-use frontends::grpc::{execute_remote_command, run_server};
+use frontends::grpc::{RetryPolicy, execute_remote_command, run_server};
+use runner::{GenerationMode, RunContext};
 
 fn elapsed_subsec(state: &ProgressState, writer: &mut dyn std::fmt::Write) {
     let elapsed = state.elapsed();
     let _ = write!(writer, "{:.1}s", elapsed.as_secs_f64());
 }
 
+/// Runs `f` inside a dedicated rayon pool rather than resizing the global one.
+fn run_in_pool<T: Send>(
+    threads: Option<usize>,
+    f: impl FnOnce() -> Result<T, CliError> + Send,
+) -> Result<T, CliError> {
+    let mut builder = rayon::ThreadPoolBuilder::new();
+    if let Some(threads) = threads {
+        builder = builder.num_threads(threads);
+    }
+    let pool = builder
+        .build()
+        .map_err(|e| CliError::Io(format!("Failed to build thread pool: {}", e)))?;
+    pool.install(f)
+}
+
+/// Prints `df` (capped at `--max-rows`), or writes all of it to `--output-file`,
+/// after any rows already there with `--append`.
+fn emit_dataframe(mut df: DataFrame, cli: &Cli) {
+    if cli.output_file == "-" {
+        let opts = PrintOptions {
+            max_rows: cli.max_rows,
+            precision: cli.precision,
+            no_color: sink::color_disabled(cli.no_color),
+        };
+        print_dataframe(&df, &opts);
+        return;
+    }
+
+    let path = std::path::Path::new(&cli.output_file);
+    let written = match cli.append {
+        true => sink::append_dataframe(&mut df, path),
+        false => sink::write_dataframe(&mut df, path),
+    };
+    if let Err(e) = written {
+        error!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Whether generated rows can be written to a CSV `--output-file` as they are produced,
+/// rather than collected into one table first; sorting needs the whole table.
+fn streams_csv(cli: &Cli) -> bool {
+    let path = std::path::Path::new(&cli.output_file);
+    let is_parquet = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("parquet"));
+    cli.output_file != "-" && !is_parquet && !cli.sort
+}
+
 fn main() {
+    let cli = Cli::parse();
+
+    // Logs and status lines go to stderr, or to --log, so stdout carries only result rows
+    let (log_writer, ansi) = match &cli.log {
+        Some(path) => match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => (BoxMakeWriter::new(Mutex::new(file)), false),
+            Err(e) => {
+                eprintln!("Failed to open log file {}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        },
+        None => (
+            BoxMakeWriter::new(std::io::stderr),
+            !sink::color_disabled(cli.no_color),
+        ),
+    };
     let fmt_layer = fmt::layer()
+        .with_writer(log_writer)
+        .with_ansi(ansi)
         .with_target(false)
         .with_span_events(fmt::format::FmtSpan::NONE)
         .with_timer(fmt::time::LocalTime::new(
             time::macros::format_description!("[hour]:[minute]:[second]"),
         ));
-    let filter_layer = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    // Status lines are logged at info, so quiet mode only lets warnings through
+    let default_level = if cli.quiet { "warn" } else { "info" };
+    let filter_layer = match cli.verbosity_level() {
+        Some(level) => EnvFilter::default().add_directive(level.into()),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level)),
+    };
 
     tracing_subscriber::registry()
         .with(filter_layer)
         .with(fmt_layer)
         .init();
 
-    let cli = Cli::parse();
+    analyze::set_progress_hidden(cli.quiet);
 
-    if let Some(log_path) = &cli.log {
-        info!("Logging to file: {:?}", log_path);
+    if let Err(e) = cli.command.validate() {
+        error!("Invalid arguments: {}", e);
+        std::process::exit(2);
     }
 
     if let Some(server_addr) = &cli.remote {
         let rt = tokio::runtime::Runtime::new().unwrap();
-        match rt.block_on(execute_remote_command(server_addr, &cli.command)) {
-            Ok(df) => {
-                print_dataframe(&df);
-            }
+        let retry = RetryPolicy {
+            attempts: cli.connect_attempts,
+            connect_timeout: Duration::from_secs(cli.connect_timeout),
+            ..Default::default()
+        };
+        match rt.block_on(execute_remote_command(
+            server_addr,
+            &cli.command,
+            cli.quiet,
+            retry,
+        )) {
+            Ok(df) => emit_dataframe(df, &cli),
             Err(e) => {
                 error!("Remote execution failed: {}", e);
                 std::process::exit(1);
@@ -68,19 +154,115 @@ fn main() {
     }
 
     match &cli.command {
-        Commands::Serve { addr, metrics_port } => {
+        Commands::Generate {
+            count,
+            unique,
+            enumerate,
+            jsonl,
+            constrain,
+            seed_count,
+            seed_hostnames,
+            seed,
+        } if *jsonl || streams_csv(&cli) => {
+            let format = match jsonl {
+                true => StreamFormat::JsonLines,
+                false => StreamFormat::Csv,
+            };
+            let ctx = RunContext {
+                address_column: cli.address_column.clone(),
+                quiet: cli.quiet,
+                sort: cli.sort,
+                ..Default::default()
+            };
+            let columns = [ctx.address_column()];
+            let mode = GenerationMode::new(*unique, *enumerate);
+            let seeds = match Commands::generation_seeds(*seed_count, seed_hostnames) {
+                Ok(seeds) => seeds,
+                Err(e) => {
+                    error!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let result = if cli.output_file == "-" {
+                RowWriter::new(std::io::stdout().lock(), format, &columns).and_then(|writer| {
+                    Commands::stream_generate(*count, mode, constrain, &seeds, *seed, writer, &ctx)
+                })
+            } else {
+                let path = std::path::Path::new(&cli.output_file);
+                let opened = if cli.append {
+                    sink::open_append(path, format, &columns)
+                } else {
+                    File::create(path).map(|file| (file, true)).map_err(|e| {
+                        CliError::Io(format!("Failed to create {}: {}", cli.output_file, e))
+                    })
+                };
+                opened
+                    .and_then(|(file, write_header)| {
+                        RowWriter::new_with_options(file, format, &columns, write_header)
+                    })
+                    .and_then(|writer| {
+                        Commands::stream_generate(
+                            *count, mode, constrain, &seeds, *seed, writer, &ctx,
+                        )
+                    })
+            };
+            if let Err(e) = result {
+                error!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Serve {
+            addr,
+            metrics_port,
+            max_duration,
+        } => {
             let rt = tokio::runtime::Runtime::new().unwrap();
-            if let Err(e) = rt.block_on(frontends::grpc::run_server(addr, Some(*metrics_port))) {
+            let max_duration = max_duration.map(Duration::from_secs);
+            if let Err(e) = rt.block_on(frontends::grpc::run_server(
+                addr,
+                Some(*metrics_port),
+                max_duration,
+            )) {
                 error!("Failed to start server: {}", e);
                 std::process::exit(1);
             }
         }
-        _ => match cli.command.run() {
-            Ok(df) => print_dataframe(&df),
-            Err(e) => {
-                error!("Error: {}", e);
-                std::process::exit(1);
+        _ => {
+            let ctx = RunContext {
+                address_column: cli.address_column.clone(),
+                quiet: cli.quiet,
+                sort: cli.sort,
+                ..Default::default()
+            };
+            match run_in_pool(cli.threads, || cli.command.run_with_context(&ctx)) {
+                Ok(df) => emit_dataframe(df, &cli),
+                Err(e) => {
+                    error!("Error: {}", e);
+                    std::process::exit(1);
+                }
             }
-        },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_in_pool_sizes_the_pool() {
+        let threads = run_in_pool(Some(3), || Ok(rayon::current_num_threads())).unwrap();
+        assert_eq!(threads, 3);
+
+        // Work inside the pool, such as unique analysis, runs on it too
+        let df = run_in_pool(Some(2), || {
+            assert_eq!(rayon::current_num_threads(), 2);
+            let series = Series::new("address".into(), &["2001:db8::1", "2001:db8::1"]);
+            analyze::analysis::UniqueAnalysis::new(None)
+                .analyze(&series)
+                .map_err(|e| CliError::Io(e.to_string()))
+        })
+        .unwrap();
+        assert_eq!(df.height(), 1);
     }
 }
@@ -1,13 +1,25 @@
-use analyze::analysis::predicates::get_all_predicates;
+use crate::error::CliError;
+use crate::sink::{RowWriter, StreamFormat};
+use crate::source::{CsvOptions, SampleSize, parse_csv_char};
+use crate::units::{parse_count, parse_rate};
+use analyze::analysis::PrefixAnalysis;
+use analyze::analysis::predicates::{get_all_predicates, get_predicate_infos};
 use clap::{Subcommand, ValueEnum};
 use indicatif::{ProgressBar, ProgressStyle};
-use ipnet::IpNet;
+use ipnet::{IpNet, Ipv6Net};
 use polars::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use serde::{Deserialize, Serialize};
-use std::net::IpAddr;
+use std::collections::HashSet;
+use std::io::Write;
+use std::net::{IpAddr, Ipv6Addr};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use tga::TGA;
-use tracing::info;
+use tracing::{info, warn};
 
 #[derive(Debug)]
 pub enum TargetError {
@@ -28,22 +40,48 @@ impl std::fmt::Display for TargetError {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Target {
     SingleIp(IpAddr),
     Network(IpNet),
     Hostname(String, Vec<IpAddr>),
 }
 
+/// Shows the address or CIDR as given, or a hostname followed by what it resolved to.
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::SingleIp(ip) => write!(f, "{}", ip),
+            Target::Network(net) => write!(f, "{}", net),
+            Target::Hostname(name, addresses) if addresses.is_empty() => {
+                write!(f, "{} (unresolved)", name)
+            }
+            Target::Hostname(name, addresses) => {
+                let addresses: Vec<String> = addresses.iter().map(IpAddr::to_string).collect();
+                write!(f, "{} ({})", name, addresses.join(", "))
+            }
+        }
+    }
+}
+
 impl Target {
+    /// Parses an address or CIDR. A CIDR with host bits set, such as `2001:db8::5/64`,
+    /// is normalized to its network address with a warning. Hostnames are not resolved
+    /// yet and fail to parse.
     pub fn parse(input: &str) -> Result<Self, TargetError> {
         if let Ok(ip) = input.parse::<IpAddr>() {
             return Ok(Target::SingleIp(ip));
         }
 
-        if let Ok(net) = input.parse::<IpNet>() {
-            return Ok(Target::Network(net));
+        let net = input.parse::<IpNet>().map_err(TargetError::IpNetParse)?;
+        if Self::has_host_bits(&net) {
+            warn!(
+                "Target {} has host bits set; scanning {} instead",
+                net,
+                net.trunc()
+            );
         }
+        Ok(Target::Network(net.trunc()))
 
         /*let resolver = AsyncResolver::tokio(
             ResolverConfig::default(),
@@ -60,11 +98,16 @@ impl Target {
         }
 
         Ok(Target::Hostname(input.to_string(), addresses))*/
-        todo!()
+    }
+
+    /// Whether `net` has address bits set past its prefix length, which usually means
+    /// the user meant a single host rather than the whole network.
+    pub fn has_host_bits(net: &IpNet) -> bool {
+        net.addr() != net.network()
     }
 }
 
-#[derive(Clone, ValueEnum, Serialize, Deserialize)]
+#[derive(Clone, Copy, ValueEnum, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[value(rename_all = "snake_case")]
 pub enum ProbeModule {
     TcpSynScan,
@@ -72,7 +115,7 @@ pub enum ProbeModule {
     UdpScan,
 }
 
-#[derive(Clone, Copy, ValueEnum, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, ValueEnum, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[value(rename_all = "snake_case")]
 pub enum ScanType {
     Icmpv4,
@@ -80,6 +123,35 @@ pub enum ScanType {
     LinkLocal,
 }
 
+/// Prints the name accepted on the command line, e.g. `tcp_syn_scan`.
+impl std::fmt::Display for ProbeModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+/// Prints the name accepted on the command line, e.g. `link_local`.
+impl std::fmt::Display for ScanType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+/// How an input file is interpreted.
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[value(rename_all = "kebab-case")]
+pub enum InputFormat {
+    /// One address per line, without a header (a leading non-address line is skipped)
+    IpList,
+    /// CSV scan output with a `saddr` column, which is moved to the front
+    ScanResult,
+    /// CSV with a header row
+    Csv,
+    /// Guess from the first non-comment line
+    #[default]
+    Auto,
+}
+
 #[derive(Clone, ValueEnum, Serialize, Deserialize)]
 #[value(rename_all = "snake_case")]
 pub enum ReservedPredicate {
@@ -144,7 +216,7 @@ pub enum Eui64Predicate {
     LowByteHost,
 }
 
-#[derive(Clone, ValueEnum, Serialize, Deserialize)]
+#[derive(Clone, Debug, ValueEnum, Serialize, Deserialize)]
 #[value(rename_all = "snake_case")]
 pub enum AddressPredicate {
     // Reserved predicates
@@ -254,6 +326,11 @@ pub enum AnalyzeCommand {
         /// End bit position (1-128) for entropy calculation
         #[arg(short = 'e', long, value_parser = clap::value_parser!(u8).range(1..=128), default_value_t = 128)]
         end_bit: u8,
+
+        /// Measure the distribution of individual bits, or of whole bytes (bits per byte);
+        /// byte granularity needs byte-aligned start and end bits
+        #[arg(short = 'g', long, value_enum, default_value = "bit")]
+        granularity: EntropyGranularity,
     },
     /// Subnet distribution analysis
     Subnets {
@@ -264,9 +341,361 @@ pub enum AnalyzeCommand {
         /// CIDR prefix length (default: 64)
         #[arg(short = 'l', long, value_parser = clap::value_parser!(u8).range(1..=128), default_value_t = 64)]
         prefix_length: u8,
+
+        /// Attribute addresses to the most specific allocation listed in this file
+        /// (one CIDR per line, or an RIR delegation file) instead of fixed-length prefixes
+        #[arg(short = 'a', long, value_name = "FILE")]
+        allocations: Option<PathBuf>,
+    },
+    /// Subnet counts at several prefix lengths at once, in a single pass
+    Hierarchy {
+        /// Prefix lengths to count at, comma-separated
+        #[arg(
+            short = 'l',
+            long = "levels",
+            value_delimiter = ',',
+            value_parser = clap::value_parser!(u8).range(1..=128),
+            default_value = "32,48,56,64"
+        )]
+        prefix_lengths: Vec<u8>,
+
+        /// Maximum number of subnets to show per prefix length
+        #[arg(short = 'n', long, default_value_t = 10)]
+        max_subnets: usize,
     },
     /// Count addresses matching each predicate
-    Counts,
+    Counts {
+        /// Only report total, unique and duplicate counts, skipping predicate evaluation
+        #[arg(long, conflicts_with = "predicate")]
+        count_only: bool,
+
+        /// Count matches for this predicate only
+        #[arg(short = 'p', long, value_enum)]
+        predicate: Option<AddressPredicate>,
+    },
+    /// Classify interface identifiers (low-byte, EUI-64, wordy, embedded IPv4, random)
+    IidPatterns,
+    /// List the predicates each address matches and flag impossible combinations, such as
+    /// an address that is both documentation and globally routable
+    Anomalies {
+        /// Only list addresses with an impossible combination
+        #[arg(long)]
+        flagged_only: bool,
+    },
+    /// Total, unique and duplicate address counts
+    Statistics {
+        /// Estimate the unique count with HyperLogLog in bounded memory instead of counting exactly
+        #[arg(long)]
+        approximate: bool,
+    },
+    /// Count scan results per ICMPv6 response type (reads the `type` column by default)
+    ResponseTypes,
+    /// Round-trip time summary of scan results (reads the `rtt_ms` column by default)
+    Rtt,
+    /// Hop counts and likely OS from the initial TTL of scan replies (reads the `ttl` column by default)
+    Ttl,
+    /// Count addresses contained in each of the given prefixes
+    Prefixes {
+        /// CIDR prefix to count addresses in (can be specified multiple times)
+        #[arg(short = 'p', long = "prefix", value_name = "CIDR", required = true)]
+        prefixes: Vec<Ipv6Net>,
+    },
+    /// Counts, dispersion, entropy and subnets from a single read of the input
+    All {
+        /// Maximum number of subnets to show (default: 10)
+        #[arg(short = 'n', long, value_parser = clap::value_parser!(usize), default_value_t = 10)]
+        max_subnets: usize,
+
+        /// CIDR prefix length of the subnets (default: 64)
+        #[arg(short = 'l', long, value_parser = clap::value_parser!(u8).range(1..=128), default_value_t = 64)]
+        prefix_length: u8,
+    },
+}
+
+impl AnalyzeCommand {
+    /// The analysis this subcommand runs. With `--allocations` the allocation list is
+    /// left empty, as only `run_analyze` reads the file.
+    pub fn analysis_type(&self) -> analyze::AnalysisType {
+        match self {
+            AnalyzeCommand::Dispersion => analyze::AnalysisType::Dispersion,
+            AnalyzeCommand::Entropy {
+                start_bit,
+                end_bit,
+                granularity: EntropyGranularity::Bit,
+            } => analyze::AnalysisType::Entropy {
+                start_bit: *start_bit,
+                end_bit: *end_bit,
+            },
+            AnalyzeCommand::Entropy {
+                start_bit,
+                end_bit,
+                granularity: EntropyGranularity::Byte,
+            } => analyze::AnalysisType::ByteEntropy {
+                start_byte: start_bit / 8,
+                end_byte: end_bit / 8,
+            },
+            AnalyzeCommand::Subnets {
+                max_subnets,
+                allocations: Some(_),
+                ..
+            } => analyze::AnalysisType::Allocations {
+                max_subnets: *max_subnets,
+                allocations: Vec::new(),
+            },
+            AnalyzeCommand::Subnets {
+                max_subnets,
+                prefix_length,
+                allocations: None,
+            } => analyze::AnalysisType::Subnets {
+                max_subnets: *max_subnets,
+                prefix_length: *prefix_length,
+            },
+            AnalyzeCommand::Hierarchy {
+                prefix_lengths,
+                max_subnets,
+            } => analyze::AnalysisType::Hierarchy {
+                prefix_lengths: prefix_lengths.clone(),
+                max_subnets: *max_subnets,
+            },
+            AnalyzeCommand::Counts {
+                count_only: true, ..
+            } => analyze::AnalysisType::Statistics { approximate: false },
+            AnalyzeCommand::Counts {
+                count_only: false,
+                predicate,
+            } => analyze::AnalysisType::Counts {
+                predicate: predicate.as_ref().map(|p| p.to_filter_name()),
+            },
+            AnalyzeCommand::IidPatterns => analyze::AnalysisType::IidPatterns,
+            AnalyzeCommand::Anomalies { flagged_only } => {
+                analyze::AnalysisType::PredicateAnomalies {
+                    flagged_only: *flagged_only,
+                }
+            }
+            AnalyzeCommand::Statistics { approximate } => analyze::AnalysisType::Statistics {
+                approximate: *approximate,
+            },
+            AnalyzeCommand::ResponseTypes => analyze::AnalysisType::ResponseTypes,
+            AnalyzeCommand::Rtt => analyze::AnalysisType::RttSummary,
+            AnalyzeCommand::Ttl => analyze::AnalysisType::TtlHops,
+            AnalyzeCommand::Prefixes { prefixes } => analyze::AnalysisType::Prefixes {
+                prefixes: prefixes.clone(),
+            },
+            AnalyzeCommand::All {
+                max_subnets,
+                prefix_length,
+            } => analyze::AnalysisType::All {
+                max_subnets: *max_subnets,
+                prefix_length: *prefix_length,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[value(rename_all = "snake_case")]
+pub enum EntropyGranularity {
+    Bit,
+    Byte,
+}
+
+/// Which rows `join` keeps.
+#[derive(Clone, Copy, ValueEnum, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[value(rename_all = "snake_case")]
+pub enum JoinKind {
+    /// Addresses present in both files
+    Inner,
+    /// Every address in the left file, with right-hand columns where it matched
+    Left,
+    /// Every address in either file; a null address on one side marks where it is missing
+    Outer,
+}
+
+impl From<JoinKind> for JoinType {
+    fn from(kind: JoinKind) -> Self {
+        match kind {
+            JoinKind::Inner => JoinType::Inner,
+            JoinKind::Left => JoinType::Left,
+            JoinKind::Outer => JoinType::Full,
+        }
+    }
+}
+
+/// A progress snapshot reported by a long-running command.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub message: String,
+    pub position: u64,
+    pub total: u64,
+}
+
+/// How generation draws addresses from the trained model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationMode {
+    /// Independent samples, which may repeat
+    Sample,
+    /// Samples, re-drawing any address already produced
+    Unique,
+    /// The model's address space in order, each address once, via [`TGA::enumerate`]
+    Enumerate,
+}
+
+impl GenerationMode {
+    /// The mode selected by generation's `--unique` and `--enumerate` flags.
+    pub fn new(unique: bool, enumerate: bool) -> Self {
+        match (unique, enumerate) {
+            (_, true) => GenerationMode::Enumerate,
+            (true, false) => GenerationMode::Unique,
+            (false, false) => GenerationMode::Sample,
+        }
+    }
+}
+
+/// Summary of a finished generation run, used to benchmark TGAs against each other.
+#[derive(Debug, Clone)]
+pub struct GenerationReport {
+    pub generated: usize,
+    /// Candidates rejected because they were already generated (only with `--unique`)
+    pub duplicates: usize,
+    pub elapsed: Duration,
+}
+
+impl GenerationReport {
+    pub fn addresses_per_second(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.generated as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    pub fn duplicate_fraction(&self) -> f64 {
+        let candidates = self.generated + self.duplicates;
+        if candidates > 0 {
+            self.duplicates as f64 / candidates as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+impl std::fmt::Display for GenerationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Generated {} addresses in {:.2?} ({:.0} addresses/sec, {:.2}% duplicates rejected)",
+            self.generated,
+            self.elapsed,
+            self.addresses_per_second(),
+            self.duplicate_fraction() * 100.0
+        )
+    }
+}
+
+/// Builds the table scan commands return: one row per target with its address,
+/// probe status, round-trip time (reachable targets only) and any detail message.
+pub fn probe_results_to_dataframe(
+    results: &[(IpAddr, probe::ProbeResult)],
+    address_column: &str,
+) -> Result<DataFrame, CliError> {
+    let addresses: Vec<String> = results.iter().map(|(addr, _)| addr.to_string()).collect();
+    let statuses: Vec<&str> = results.iter().map(|(_, r)| r.status()).collect();
+    let rtts: Vec<Option<u64>> = results.iter().map(|(_, r)| r.rtt_ms()).collect();
+    let details: Vec<Option<&str>> = results.iter().map(|(_, r)| r.detail()).collect();
+    DataFrame::new(vec![
+        Series::new(address_column.into(), addresses).into(),
+        Series::new("status".into(), statuses).into(),
+        Series::new("rtt_ms".into(), rtts).into(),
+        Series::new("detail".into(), details).into(),
+    ])
+    .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
+}
+
+/// Sorts rows by the numeric value of `address_column`, so `::2` comes before `::10`.
+/// IPv4 addresses sort as their IPv4-mapped form; unparseable values go last.
+pub fn sort_by_address(df: &DataFrame, address_column: &str) -> Result<DataFrame, CliError> {
+    let addresses = df
+        .column(address_column)
+        .map_err(|e| CliError::Polars(format!("Failed to find address column: {}", e)))?
+        .str()
+        .map_err(|e| CliError::Polars(format!("Failed to convert to string series: {}", e)))?;
+    let mut keyed: Vec<(Option<u128>, IdxSize)> = addresses
+        .into_iter()
+        .enumerate()
+        .map(|(i, opt_str)| {
+            let key = opt_str
+                .and_then(|s| s.parse::<IpAddr>().ok())
+                .map(|addr| match addr {
+                    IpAddr::V4(v4) => u128::from(v4.to_ipv6_mapped()),
+                    IpAddr::V6(v6) => u128::from(v6),
+                });
+            (key, i as IdxSize)
+        })
+        .collect();
+    // None sorts before Some, so order on (is_none, value) to put unparseable rows last
+    keyed.sort_by_key(|(key, _)| (key.is_none(), *key));
+    let order = IdxCa::from_vec("order".into(), keyed.into_iter().map(|(_, i)| i).collect());
+    df.take(&order)
+        .map_err(|e| CliError::Polars(format!("Failed to sort by address: {}", e)))
+}
+
+/// Most targets a scan or dry run covers when `--max-targets` is not given.
+const MAX_UNBOUNDED_TARGETS: u64 = 1 << 24;
+
+/// Most candidate addresses a scan skips as blocklisted or excluded before it stops
+/// looking, so a target that is almost entirely excluded still finishes.
+const MAX_SKIPPED_TARGETS: u64 = 1 << 24;
+
+/// Most consecutive generated addresses rejected as repeats or by `--constrain`
+/// before generation gives up.
+const MAX_GENERATION_ATTEMPTS: usize = 1_000_000;
+
+/// Column name commands write addresses to and filters read them from by default.
+pub const DEFAULT_ADDRESS_COLUMN: &str = "address";
+
+/// Per-invocation state threaded through a command run.
+#[derive(Default)]
+pub struct RunContext {
+    pub cancel: Arc<AtomicBool>,
+    pub on_progress: Option<Box<dyn Fn(Progress) + Send + Sync>>,
+    /// Overrides [`DEFAULT_ADDRESS_COLUMN`]
+    pub address_column: Option<String>,
+    /// Hide progress bars
+    pub quiet: bool,
+    /// Sort the output rows by numeric address value
+    pub sort: bool,
+}
+
+impl RunContext {
+    pub fn address_column(&self) -> &str {
+        self.address_column
+            .as_deref()
+            .unwrap_or(DEFAULT_ADDRESS_COLUMN)
+    }
+
+    /// A progress bar of length `len`, or a hidden one in quiet mode.
+    pub fn progress_bar(&self, len: u64) -> ProgressBar {
+        if self.quiet {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new(len)
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn report(&self, message: &str, position: u64, total: u64) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(Progress {
+                message: message.to_string(),
+                position,
+                total,
+            });
+        }
+    }
 }
 
 #[derive(Subcommand, Serialize, Deserialize)]
@@ -277,7 +706,7 @@ pub enum Commands {
         #[arg(short = 's', long, value_enum, default_value = "icmpv4")]
         scan_type: ScanType,
 
-        /// Target specification (IP, hostname, or CIDR range) - not needed for link-local scans
+        /// Target specification (IP or CIDR range) - not needed for link-local scans
         #[arg(value_name = "TARGET")]
         target: Option<String>,
 
@@ -293,12 +722,12 @@ pub enum Commands {
         #[arg(short = 'w', long)]
         allowlist_file: Option<PathBuf>,
 
-        /// Maximum number of targets to probe
-        #[arg(short = 'n', long)]
-        max_targets: Option<String>,
+        /// Maximum number of targets to probe (accepts k, M and G suffixes)
+        #[arg(short = 'n', long, value_parser = parse_count)]
+        max_targets: Option<u64>,
 
-        /// Send rate in packets per second
-        #[arg(short = 'r', long, default_value = "10000")]
+        /// Send rate in packets per second (accepts k, M and G suffixes)
+        #[arg(short = 'r', long, default_value = "10000", value_parser = parse_rate)]
         rate: u32,
 
         /// Number of probes to send to each target
@@ -328,9 +757,56 @@ pub enum Commands {
         /// Type of probe to send
         #[arg(short = 'M', long, value_enum, default_value = "tcp_syn_scan")]
         probe_module: ProbeModule,
+
+        /// State file used to checkpoint progress and resume an interrupted scan
+        #[arg(long, value_name = "STATE_FILE")]
+        resume: Option<PathBuf>,
+
+        /// List the targets that would be probed, after all filtering, without sending anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Receive buffer size in bytes; must hold the largest expected reply
+        #[arg(long, value_name = "BYTES", default_value_t = scan::icmp6::MAX_PACKET_SIZE)]
+        buffer_size: usize,
+
+        /// ICMP echo identifier marking this scan's probes; random by default so
+        /// concurrent scans never accept each other's replies
+        #[arg(long, value_name = "ID")]
+        identifier: Option<u16>,
+
+        /// Longest link-local scans listen for replies, in seconds; they stop sooner
+        /// once replies dry up
+        #[arg(long, value_name = "SECONDS", default_value = "5")]
+        listen_window: u64,
+
+        /// Echo request payload after its send timestamp: zeros, ones, incrementing,
+        /// or a hex string (e.g. deadbeef) repeated to fill it
+        #[arg(long, value_name = "PATTERN", default_value = "zeros")]
+        payload_pattern: String,
+
+        /// Echo request payload size in bytes, including the 4 byte send timestamp
+        #[arg(long, value_name = "BYTES", default_value_t = scan::icmp6::DEFAULT_PAYLOAD_SIZE)]
+        payload_size: usize,
+
+        /// Never probe IPv6 targets matching these predicates, such as multicast (can be
+        /// specified multiple times)
+        #[arg(long, value_enum)]
+        exclude_predicate: Vec<AddressPredicate>,
+
+        /// IPv6 extension header to send in front of each ICMPv6 echo request:
+        /// hop-by-hop or destination-options
+        #[arg(long, value_name = "HEADER")]
+        extension_header: Option<String>,
     },
     /// Discover new targets by scanning the address space
-    Discover,
+    Discover {
+        /// Longest to listen for replies, in seconds; discovery stops sooner once
+        /// replies dry up
+        #[arg(long, value_name = "SECONDS", default_value = "5")]
+        #[serde(default = "default_listen_window")]
+        listen_window: u64,
+    },
     /// Generate a set of targets
     Generate {
         /// Number of addresses to generate
@@ -340,18 +816,60 @@ pub enum Commands {
         /// Ensure generated addresses are unique
         #[arg(short = 'u', long)]
         unique: bool,
+
+        /// List the model's address space in order instead of sampling it; stops early,
+        /// with a warning, once every address has been produced
+        #[arg(long, conflicts_with = "seed")]
+        #[serde(default)]
+        enumerate: bool,
+
+        /// Stream addresses as JSON lines while they are generated instead of building a table
+        #[arg(long)]
+        jsonl: bool,
+
+        /// Reject generated addresses matching these predicates (can be specified multiple times)
+        #[arg(long, value_enum)]
+        constrain: Vec<AddressPredicate>,
+
+        /// Number of built-in demo seed addresses to train the model on
+        #[arg(long, value_name = "N", default_value_t = tga::DEMO_SEED_COUNT)]
+        seed_count: usize,
+
+        /// Train on the AAAA records of the hostnames in this file, one per line,
+        /// instead of the demo seeds
+        #[arg(long, value_name = "FILE", conflicts_with = "seed_count")]
+        seed_hostnames: Option<PathBuf>,
+
+        /// Random seed, for reproducible output
+        #[arg(long)]
+        seed: Option<u64>,
     },
     /// Train the TGA
     Train,
+    /// List the address predicates accepted by --include and --exclude
+    ListPredicates,
+    /// Report the version and build capabilities (TGAs, predicates, Python TGA support)
+    Info,
+    /// List the columns and types an analysis outputs, without reading any input
+    DescribeAnalysis {
+        /// Analysis to describe
+        #[command(subcommand)]
+        analysis: AnalyzeCommand,
+    },
     /// Analyze data with various metrics
     Analyze {
-        /// Path to file containing data to analyze
+        /// Path to file containing data to analyze, a directory of files to concatenate,
+        /// or `-` to read standard input
         #[arg(value_name = "FILE")]
         file: PathBuf,
 
-        /// Column name to select from input data
+        /// How to interpret the input file
+        #[arg(long, value_enum, default_value = "auto")]
+        input_format: InputFormat,
+
+        /// Column names to select from input data (can be specified multiple times)
         #[arg(short = 'f', long, value_name = "FIELD")]
-        field: Option<String>,
+        field: Vec<String>,
 
         /// Include addresses matching these predicates (can be specified multiple times)
         #[arg(long, value_enum)]
@@ -361,23 +879,133 @@ pub enum Commands {
         #[arg(long, value_enum)]
         exclude: Vec<AddressPredicate>,
 
+        /// Keep only addresses inside one of these CIDR prefixes (can be specified multiple times)
+        #[arg(long = "prefix", value_name = "CIDR")]
+        prefix: Vec<Ipv6Net>,
+
         /// Remove duplicate addresses from input dataset before analysis
         #[arg(short = 'u', long)]
         unique: bool,
 
+        /// Only read the first N rows of the input file
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Field separator of CSV input, e.g. ';' or '\t' for tab-separated files
+        #[arg(long, value_name = "CHAR", default_value = ",", value_parser = parse_csv_char)]
+        csv_delimiter: u8,
+
+        /// Quote character of CSV input
+        #[arg(long, value_name = "CHAR", default_value = "\"", value_parser = parse_csv_char)]
+        csv_quote: u8,
+
         /// Analysis subcommand to run
         #[command(subcommand)]
         analysis: AnalyzeCommand,
     },
+    /// Filter addresses and export the surviving rows to a CSV or Parquet file
+    Filter {
+        /// Path to file containing data to filter, or `-` to read standard input
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// How to interpret the input file
+        #[arg(long, value_enum, default_value = "auto")]
+        input_format: InputFormat,
+
+        /// Column names to select from input data (can be specified multiple times)
+        #[arg(short = 'f', long, value_name = "FIELD")]
+        field: Vec<String>,
+
+        /// Include addresses matching these predicates (can be specified multiple times)
+        #[arg(long, value_enum)]
+        include: Vec<AddressPredicate>,
+
+        /// Exclude addresses matching these predicates (can be specified multiple times)
+        #[arg(long, value_enum)]
+        exclude: Vec<AddressPredicate>,
+
+        /// Keep only addresses inside one of these CIDR prefixes (can be specified multiple times)
+        #[arg(long = "prefix", value_name = "CIDR")]
+        prefix: Vec<Ipv6Net>,
+
+        /// Remove duplicate addresses
+        #[arg(short = 'u', long)]
+        unique: bool,
+
+        /// Output file; written as Parquet if it ends in .parquet, CSV otherwise
+        #[arg(short = 'x', long, value_name = "PATH")]
+        export: PathBuf,
+    },
+    /// Join two result files on their address column, e.g. to compare two scans
+    Join {
+        /// Left-hand file
+        #[arg(value_name = "LEFT")]
+        left: PathBuf,
+
+        /// Right-hand file
+        #[arg(value_name = "RIGHT")]
+        right: PathBuf,
+
+        /// Which addresses to keep
+        #[arg(long, value_enum, default_value = "inner")]
+        how: JoinKind,
+    },
+    /// List addresses in the first file that are not in the second
+    Diff {
+        /// Addresses to keep
+        #[arg(value_name = "A")]
+        a: PathBuf,
+
+        /// Addresses to remove
+        #[arg(value_name = "B")]
+        b: PathBuf,
+    },
+    /// List addresses present in both files
+    Intersect {
+        #[arg(value_name = "A")]
+        a: PathBuf,
+
+        #[arg(value_name = "B")]
+        b: PathBuf,
+    },
+    /// Draw a random sample of addresses from an IP list too large to load at once
+    Sample {
+        /// IP list to sample from (one address per line), or `-` to read standard input
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Number of addresses to keep
+        #[arg(
+            long = "n",
+            value_name = "COUNT",
+            required_unless_present = "fraction",
+            value_parser = parse_count
+        )]
+        count: Option<u64>,
+
+        /// Keep each address with this probability (0.0 to 1.0)
+        #[arg(long, conflicts_with = "count")]
+        fraction: Option<f64>,
+
+        /// Random seed, for a reproducible sample
+        #[arg(long)]
+        seed: Option<u64>,
+    },
     /// View data in an interactive TUI
     View {
-        /// Path to file containing data to view
+        /// Path to file containing data to view, a directory of files to concatenate,
+        /// or `-` to read standard input
         #[arg(value_name = "FILE")]
         file: PathBuf,
 
-        /// Column name to select from input data
+        /// How to interpret the input file
+        #[arg(long, value_enum, default_value = "auto")]
+        input_format: InputFormat,
+
+        /// Column names to select from input data (can be specified multiple times)
         #[arg(short = 'f', long, value_name = "FIELD")]
-        field: Option<String>,
+        field: Vec<String>,
 
         /// Include addresses matching these predicates (can be specified multiple times)
         #[arg(long, value_enum)]
@@ -387,14 +1015,37 @@ pub enum Commands {
         #[arg(long, value_enum)]
         exclude: Vec<AddressPredicate>,
 
+        /// Keep only addresses inside one of these CIDR prefixes (can be specified multiple times)
+        #[arg(long = "prefix", value_name = "CIDR")]
+        prefix: Vec<Ipv6Net>,
+
         /// Remove duplicate addresses from input dataset before analysis
         #[arg(short = 'u', long)]
         unique: bool,
 
+        /// Only read the first N rows of the input file
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
         /// Show the resulting dataframe in an interactive TUI
         #[arg(long)]
         tui: bool,
     },
+    /// Run a registered plugin on a data file
+    Plugin {
+        /// Path to the plugin's input data, or `-` to read standard input
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Plugin name followed by its own arguments
+        #[arg(
+            value_name = "PLUGIN",
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        args: Vec<String>,
+    },
     /// Start gRPC server for remote command execution
     Serve {
         /// Server address to bind to (default: 127.0.0.1:50051)
@@ -404,86 +1055,475 @@ pub enum Commands {
         /// Prometheus metrics port (default: 9090, use 0 to disable)
         #[arg(short = 'm', long, default_value = "9090")]
         metrics_port: u16,
+
+        /// Maximum time in seconds a remote command may run before it is aborted
+        #[arg(long, value_name = "SECONDS")]
+        max_duration: Option<u64>,
     },
 }
 
+fn default_listen_window() -> u64 {
+    scan::link_local::DEFAULT_LISTEN_WINDOW.as_secs()
+}
+
 impl Commands {
-    pub fn run(&self) -> Result<DataFrame, String> {
+    /// Parses a command sent as JSON by a gRPC client. Clients from before `discover`
+    /// took options send it as the bare string `"Discover"`, which is still accepted.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        if value == "Discover" {
+            value = serde_json::json!({ "Discover": {} });
+        }
+        serde_json::from_value(value)
+    }
+
+    pub fn run(&self) -> Result<DataFrame, CliError> {
+        self.run_with_context(&RunContext::default())
+    }
+
+    /// Checks argument combinations that clap cannot express on its own.
+    pub fn validate(&self) -> Result<(), CliError> {
+        match self {
+            Commands::Scan {
+                scan_type,
+                target,
+                input_file,
+                payload_pattern,
+                payload_size,
+                exclude_predicate,
+                extension_header,
+                ..
+            } => {
+                Self::validate_scan(scan_type, target, input_file)?;
+                if extension_header.is_some() && !matches!(scan_type, ScanType::Icmpv6) {
+                    return Err(CliError::InvalidArgument(
+                        "--extension-header applies to icmpv6 scans only".to_string(),
+                    ));
+                }
+                Self::extension_header(extension_header)?;
+                if matches!(scan_type, ScanType::Icmpv4) && !exclude_predicate.is_empty() {
+                    return Err(CliError::InvalidArgument(
+                        "--exclude-predicate matches IPv6 addresses only and cannot filter icmpv4 scans"
+                            .to_string(),
+                    ));
+                }
+                Self::echo_payload(payload_pattern, *payload_size).map(|_| ())
+            }
+            Commands::Analyze { analysis, .. } => Self::validate_analysis(analysis),
+            Commands::Sample {
+                fraction: Some(fraction),
+                ..
+            } if !(0.0..=1.0).contains(fraction) => Err(CliError::InvalidArgument(format!(
+                "Sample fraction {} is out of range; expected a value between 0 and 1",
+                fraction
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_scan(
+        scan_type: &ScanType,
+        target: &Option<String>,
+        input_file: &Option<PathBuf>,
+    ) -> Result<(), CliError> {
+        let target = match (scan_type, target) {
+            (ScanType::LinkLocal, Some(target)) => {
+                return Err(CliError::InvalidArgument(format!(
+                    "Link-local scans discover hosts on the local link and do not take a target (got '{}'); drop the target or use --scan-type icmpv6",
+                    target
+                )));
+            }
+            (ScanType::LinkLocal, None) => return Ok(()),
+            (_, None) if input_file.is_none() => {
+                return Err(CliError::InvalidArgument(format!(
+                    "A target or --input-file is required for {:?} scans",
+                    scan_type
+                )));
+            }
+            (_, None) => return Ok(()),
+            (_, Some(target)) => target,
+        };
+
+        let is_ipv6 = if let Ok(ip) = target.parse::<IpAddr>() {
+            ip.is_ipv6()
+        } else if let Ok(net) = target.parse::<IpNet>() {
+            matches!(net, IpNet::V6(_))
+        } else {
+            return Err(CliError::InvalidArgument(format!(
+                "Target '{}' is not an IP address or CIDR range; hostnames are not supported",
+                target
+            )));
+        };
+
+        match (scan_type, is_ipv6) {
+            (ScanType::Icmpv4, true) => Err(CliError::InvalidArgument(format!(
+                "Target '{}' is an IPv6 address but the scan type is icmpv4; use --scan-type icmpv6",
+                target
+            ))),
+            (ScanType::Icmpv6, false) => Err(CliError::InvalidArgument(format!(
+                "Target '{}' is an IPv4 address but the scan type is icmpv6; use --scan-type icmpv4",
+                target
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn echo_payload(pattern: &str, size: usize) -> Result<scan::icmp6::EchoPayload, CliError> {
+        let pattern = pattern.parse().map_err(CliError::InvalidArgument)?;
+        scan::icmp6::EchoPayload::new(pattern, size).map_err(CliError::InvalidArgument)
+    }
+
+    fn extension_header(
+        header: &Option<String>,
+    ) -> Result<Option<probe::ExtensionHeader>, CliError> {
+        header
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(CliError::InvalidArgument)
+    }
+
+    fn validate_analysis(analysis: &AnalyzeCommand) -> Result<(), CliError> {
+        match analysis {
+            AnalyzeCommand::Entropy {
+                start_bit,
+                end_bit,
+                granularity,
+            } => {
+                if *start_bit > 127 || *end_bit < 1 || *end_bit > 128 {
+                    return Err(CliError::InvalidArgument(format!(
+                        "Entropy bit range {}..{} is out of bounds; start_bit must be 0-127 and end_bit 1-128",
+                        start_bit, end_bit
+                    )));
+                }
+                if start_bit >= end_bit {
+                    return Err(CliError::InvalidArgument(format!(
+                        "start_bit ({}) must be less than end_bit ({})",
+                        start_bit, end_bit
+                    )));
+                }
+                if *granularity == EntropyGranularity::Byte
+                    && (start_bit % 8 != 0 || end_bit % 8 != 0)
+                {
+                    return Err(CliError::InvalidArgument(format!(
+                        "Byte entropy needs byte-aligned bits, got {}..{}; use multiples of 8",
+                        start_bit, end_bit
+                    )));
+                }
+                Ok(())
+            }
+            AnalyzeCommand::Subnets { prefix_length, .. }
+            | AnalyzeCommand::All { prefix_length, .. } => {
+                if !(1..=128).contains(prefix_length) {
+                    return Err(CliError::InvalidArgument(format!(
+                        "Prefix length {} is out of range; expected a value between 1 and 128",
+                        prefix_length
+                    )));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs the command, reporting progress to `ctx` and bailing out early from
+    /// long-running loops once it is cancelled.
+    pub fn run_with_context(&self, ctx: &RunContext) -> Result<DataFrame, CliError> {
+        let df = self.run_unsorted(ctx)?;
+        if ctx.sort && df.column(ctx.address_column()).is_ok() {
+            sort_by_address(&df, ctx.address_column())
+        } else {
+            Ok(df)
+        }
+    }
+
+    fn run_unsorted(&self, ctx: &RunContext) -> Result<DataFrame, CliError> {
+        self.validate()?;
         match self {
-            Commands::Generate { count, unique } => Self::run_generate(*count, *unique),
+            Commands::Generate {
+                count,
+                unique,
+                enumerate,
+                constrain,
+                seed_count,
+                seed_hostnames,
+                seed,
+                ..
+            } => {
+                let seeds = Self::generation_seeds(*seed_count, seed_hostnames)?;
+                let mode = GenerationMode::new(*unique, *enumerate);
+                Self::run_generate(*count, mode, constrain, &seeds, *seed, ctx)
+            }
+            Commands::Scan {
+                scan_type,
+                target,
+                input_file,
+                blocklist_file,
+                allowlist_file,
+                max_targets,
+                exclude_predicate,
+                dry_run: true,
+                ..
+            } => {
+                if let ScanType::LinkLocal = scan_type {
+                    return Err(CliError::InvalidArgument(
+                        "Link-local scans discover hosts by multicast and have no target list to preview"
+                            .to_string(),
+                    ));
+                }
+                let targets = Self::scan_targets(
+                    target,
+                    input_file,
+                    allowlist_file,
+                    blocklist_file,
+                    *max_targets,
+                    &Self::predicate_fns(exclude_predicate)?,
+                )?;
+                let addresses: Vec<String> = targets.iter().map(IpAddr::to_string).collect();
+                DataFrame::new(vec![
+                    Series::new(ctx.address_column().into(), addresses).into(),
+                ])
+                .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
+            }
             Commands::Scan {
-                scan_type, target, ..
-            } => self.run_scan(scan_type, target),
-            Commands::Discover => self.run_discover(),
+                scan_type,
+                target,
+                input_file,
+                blocklist_file,
+                allowlist_file,
+                max_targets,
+                resume,
+                buffer_size,
+                identifier,
+                listen_window,
+                payload_pattern,
+                payload_size,
+                exclude_predicate,
+                extension_header,
+                ..
+            } => {
+                let options = scan::icmp6::EchoScanOptions {
+                    buffer_size: *buffer_size,
+                    identifier: identifier.unwrap_or_else(scan::icmp6::random_identifier),
+                    payload: Self::echo_payload(payload_pattern, *payload_size)?,
+                    exclude: Self::predicate_fns(exclude_predicate)?,
+                    cancel: Arc::clone(&ctx.cancel),
+                    extension_header: Self::extension_header(extension_header)?,
+                };
+                // Link-local scans find their hosts by multicast instead of a target list
+                let targets = match scan_type {
+                    ScanType::LinkLocal => Vec::new(),
+                    _ => Self::scan_targets(
+                        target,
+                        input_file,
+                        allowlist_file,
+                        blocklist_file,
+                        *max_targets,
+                        &options.exclude,
+                    )?,
+                };
+                let state = match resume {
+                    Some(path) => {
+                        let key = match (target, input_file) {
+                            (Some(target), _) => target.clone(),
+                            (None, Some(file)) => file.display().to_string(),
+                            (None, None) => String::new(),
+                        };
+                        Some(
+                            scan::state::ScanState::load_or_new(path, &key)
+                                .map_err(CliError::Io)?,
+                        )
+                    }
+                    None => None,
+                };
+                Self::run_scan(
+                    scan_type,
+                    &targets,
+                    state,
+                    Duration::from_secs(*listen_window),
+                    &options,
+                    ctx,
+                )
+            }
+            Commands::Discover { listen_window } => {
+                self.run_discover(Duration::from_secs(*listen_window), ctx)
+            }
             Commands::Train => self.run_train(),
+            Commands::ListPredicates => Self::run_list_predicates(),
+            Commands::Info => Self::run_info(),
+            Commands::DescribeAnalysis { analysis } => Self::run_describe_analysis(analysis),
+            Commands::Filter {
+                file,
+                input_format,
+                field,
+                include,
+                exclude,
+                prefix,
+                unique,
+                export,
+            } => self.run_filter(
+                file,
+                *input_format,
+                field,
+                include,
+                exclude,
+                prefix,
+                unique,
+                export,
+                ctx,
+            ),
+            Commands::Join { left, right, how } => Self::run_join(left, right, *how, ctx),
+            Commands::Diff { a, b } => Self::run_set_operation(a, b, false, ctx),
+            Commands::Sample {
+                file,
+                count,
+                fraction,
+                seed,
+            } => Self::run_sample(file, *count, *fraction, *seed, ctx),
+            Commands::Intersect { a, b } => Self::run_set_operation(a, b, true, ctx),
             Commands::View {
                 file,
+                input_format,
                 field,
                 include,
                 exclude,
+                prefix,
                 unique,
+                limit,
                 tui: _,
-            } => self.run_view(file, field, include, exclude, unique),
+            } => self.run_view(
+                file,
+                *input_format,
+                field,
+                include,
+                exclude,
+                prefix,
+                unique,
+                *limit,
+                ctx,
+            ),
             Commands::Analyze {
                 file,
+                input_format,
+                field,
+                include,
+                exclude,
+                prefix,
+                unique,
+                limit,
+                csv_delimiter,
+                csv_quote,
+                analysis,
+            } => self.run_analyze(
+                file,
+                *input_format,
                 field,
                 include,
                 exclude,
+                prefix,
                 unique,
+                *limit,
+                &CsvOptions {
+                    separator: *csv_delimiter,
+                    quote_char: *csv_quote,
+                },
                 analysis,
-            } => self.run_analyze(file, field, include, exclude, unique, analysis),
-            Commands::Serve { .. } => Err("Serve command cannot be executed remotely".to_string()),
+                ctx,
+            ),
+            Commands::Plugin { file, args } => Self::run_plugin(file, args, ctx),
+            Commands::Serve { .. } => Err(CliError::InvalidArgument(
+                "Serve command cannot be executed remotely".to_string(),
+            )),
         }
     }
 
-    pub fn run_generate(count: usize, unique: bool) -> Result<DataFrame, String> {
-        // Load seed addresses for TGA training
-        let seed_ips = vec![
-            "2001:db8::1"
-                .parse::<std::net::Ipv6Addr>()
-                .unwrap()
-                .octets(),
-            "2001:db8::2"
-                .parse::<std::net::Ipv6Addr>()
-                .unwrap()
-                .octets(),
-            "2001:db8::3"
-                .parse::<std::net::Ipv6Addr>()
-                .unwrap()
-                .octets(),
-            "2001:db8::4"
-                .parse::<std::net::Ipv6Addr>()
-                .unwrap()
-                .octets(),
-            "2001:db8::5"
-                .parse::<std::net::Ipv6Addr>()
-                .unwrap()
-                .octets(),
-            "2001:db8::6"
-                .parse::<std::net::Ipv6Addr>()
-                .unwrap()
-                .octets(),
-            "2001:db8::7"
-                .parse::<std::net::Ipv6Addr>()
-                .unwrap()
-                .octets(),
-            "2001:db8::8"
-                .parse::<std::net::Ipv6Addr>()
-                .unwrap()
-                .octets(),
-            "2001:db8::9"
-                .parse::<std::net::Ipv6Addr>()
-                .unwrap()
-                .octets(),
-        ];
+    /// Training seeds for generation: the resolved `seed_hostnames` if given,
+    /// otherwise the first `seed_count` demo seeds.
+    pub fn generation_seeds(
+        seed_count: usize,
+        seed_hostnames: &Option<PathBuf>,
+    ) -> Result<Vec<[u8; 16]>, CliError> {
+        match seed_hostnames {
+            Some(file) => crate::source::load_seeds_from_hostnames(file),
+            None => Ok(tga::demo_seeds(seed_count)),
+        }
+    }
+
+    pub fn run_generate(
+        count: usize,
+        mode: GenerationMode,
+        constrain: &[AddressPredicate],
+        seeds: &[[u8; 16]],
+        seed: Option<u64>,
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
+        let mut addresses = Vec::new();
+        Self::generate_addresses(count, mode, constrain, seeds, seed, ctx, |addr| {
+            addresses.push(addr.to_string());
+            Ok(())
+        })?;
 
-        let tga = match tga::EntropyIpTga::train(seed_ips) {
+        DataFrame::new(vec![
+            Series::new(ctx.address_column().into(), addresses).into(),
+        ])
+        .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
+    }
+
+    /// Writes each generated address to `writer`, whose only column is the address
+    /// column, as soon as it is produced, without buffering the full set. Returns the
+    /// number of rows written.
+    pub fn stream_generate<W: Write>(
+        count: usize,
+        mode: GenerationMode,
+        constrain: &[AddressPredicate],
+        seeds: &[[u8; 16]],
+        seed: Option<u64>,
+        mut writer: RowWriter<W>,
+        ctx: &RunContext,
+    ) -> Result<usize, CliError> {
+        Self::generate_addresses(count, mode, constrain, seeds, seed, ctx, |addr| {
+            writer.write_row(&[&addr.to_string()])
+        })?;
+        writer.finish()
+    }
+
+    /// Samples `count` addresses from a model trained on `seeds`, re-sampling any that
+    /// repeat (with `GenerationMode::Unique`) or match a `constrain` predicate, up to a
+    /// bounded number of consecutive attempts. The same `seed` always yields the same
+    /// addresses. `GenerationMode::Enumerate` lists the model's addresses in order
+    /// instead and may return fewer than `count`.
+    fn generate_addresses<F>(
+        count: usize,
+        mode: GenerationMode,
+        constrain: &[AddressPredicate],
+        seeds: &[[u8; 16]],
+        seed: Option<u64>,
+        ctx: &RunContext,
+        mut emit: F,
+    ) -> Result<GenerationReport, CliError>
+    where
+        F: FnMut(std::net::Ipv6Addr) -> Result<(), CliError>,
+    {
+        let constraints = Self::predicate_fns(constrain)?;
+
+        let tga = match tga::EntropyIpTga::train(seeds.iter().copied()) {
             Ok(tga) => tga,
-            Err(e) => return Err(format!("Failed to train model: {}", e)),
+            Err(e) => return Err(CliError::Tga(format!("Failed to train model: {}", e))),
         };
+        let unique = mode == GenerationMode::Unique;
+        // Fail before sampling rather than spin through duplicates of a small model
+        let too_small = |capacity: &u128| unique && count as u128 > *capacity;
+        if let Some(capacity) = tga.capacity().filter(too_small) {
+            return Err(CliError::InvalidArgument(format!(
+                "Requested {} unique addresses but a model trained on {} seeds can only produce {}; lower --count or train on more seeds",
+                count,
+                seeds.len(),
+                capacity
+            )));
+        }
 
         // Create progress bar for generation
-        let pb = ProgressBar::new(count as u64);
+        let pb = ctx.progress_bar(count as u64);
         pb.set_style(
             ProgressStyle::default_bar()
                 .template("{elapsed_precise} {msg} [{bar:20.cyan/blue}] {pos}/{len}")
@@ -492,60 +1532,183 @@ impl Commands {
         );
         pb.set_message("Generating IPv6 addresses...");
 
+        if mode == GenerationMode::Enumerate {
+            let Some(addresses) = tga.enumerate() else {
+                pb.finish_and_clear();
+                return Err(CliError::InvalidArgument(format!(
+                    "{} cannot enumerate its address space; drop --enumerate",
+                    tga.name()
+                )));
+            };
+            return Self::emit_enumerated(addresses, count, &constraints, &pb, ctx, emit);
+        }
+
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let started = Instant::now();
         let mut generated = std::collections::HashSet::new();
-        let mut addresses = Vec::new();
+        let mut emitted = 0;
+        let mut duplicates = 0;
         let mut attempts = 0;
-        const MAX_ATTEMPTS: usize = 1_000_000;
 
-        while addresses.len() < count {
-            let generated_bytes = tga.generate();
+        while emitted < count {
+            if ctx.is_cancelled() {
+                pb.finish_and_clear();
+                return Err(CliError::Cancelled);
+            }
+
+            let generated_bytes = tga.generate_with_rng(&mut rng);
             let generated_ip = std::net::Ipv6Addr::from(generated_bytes);
-            if !unique || generated.insert(generated_ip) {
-                addresses.push(generated_ip.to_string());
+            let allowed = !constraints.iter().any(|matches| matches(generated_ip));
+            if allowed && (!unique || generated.insert(generated_ip)) {
+                if let Err(e) = emit(generated_ip) {
+                    pb.finish_and_clear();
+                    return Err(e);
+                }
+                emitted += 1;
                 attempts = 0;
-                pb.set_position(addresses.len() as u64);
+                pb.set_position(emitted as u64);
+                if emitted % 1000 == 0 {
+                    ctx.report("Generated addresses", emitted as u64, count as u64);
+                }
             } else {
                 attempts += 1;
-                if attempts >= MAX_ATTEMPTS {
+                if allowed {
+                    duplicates += 1;
+                }
+                if attempts >= MAX_GENERATION_ATTEMPTS {
                     pb.suspend(|| {
-                        info!("Generation failed - too many duplicate attempts");
+                        info!("Generation failed - too many rejected attempts");
                     });
                     pb.finish_and_clear();
-                    return Err(format!(
-                        "Could only generate {}/{} unique addresses after {} attempts",
-                        addresses.len(),
-                        count,
-                        MAX_ATTEMPTS
-                    ));
+                    return Err(CliError::Tga(format!(
+                        "Could only generate {}/{} acceptable addresses after {} attempts",
+                        emitted, count, MAX_GENERATION_ATTEMPTS
+                    )));
                 }
             }
         }
 
         pb.finish_and_clear();
-
-        DataFrame::new(vec![Series::new("address".into(), addresses).into()])
-            .map_err(|e| format!("Failed to create DataFrame: {}", e))
+        let report = GenerationReport {
+            generated: emitted,
+            duplicates,
+            elapsed: started.elapsed(),
+        };
+        info!("{}", report);
+        Ok(report)
     }
 
-    fn run_scan(&self, scan_type: &ScanType, target: &Option<String>) -> Result<DataFrame, String> {
-        let target = match target {
-            Some(t) => t,
-            None => return Err("Target is required for non-link-local scans".to_string()),
+    /// Emits the first `count` of `addresses` not matching a constraint, warning when
+    /// they run out first.
+    fn emit_enumerated<F>(
+        addresses: impl Iterator<Item = [u8; 16]>,
+        count: usize,
+        constraints: &[fn(std::net::Ipv6Addr) -> bool],
+        pb: &ProgressBar,
+        ctx: &RunContext,
+        mut emit: F,
+    ) -> Result<GenerationReport, CliError>
+    where
+        F: FnMut(std::net::Ipv6Addr) -> Result<(), CliError>,
+    {
+        let started = Instant::now();
+        let mut emitted = 0;
+        let mut attempts = 0;
+
+        for address in addresses {
+            if emitted == count {
+                break;
+            }
+            if ctx.is_cancelled() {
+                pb.finish_and_clear();
+                return Err(CliError::Cancelled);
+            }
+
+            let address = std::net::Ipv6Addr::from(address);
+            if constraints.iter().any(|matches| matches(address)) {
+                attempts += 1;
+                if attempts >= MAX_GENERATION_ATTEMPTS {
+                    pb.finish_and_clear();
+                    return Err(CliError::Tga(format!(
+                        "Could only enumerate {}/{} acceptable addresses; the next {} were all constrained out",
+                        emitted, count, MAX_GENERATION_ATTEMPTS
+                    )));
+                }
+                continue;
+            }
+            if let Err(e) = emit(address) {
+                pb.finish_and_clear();
+                return Err(e);
+            }
+            emitted += 1;
+            attempts = 0;
+            pb.set_position(emitted as u64);
+            if emitted % 1000 == 0 {
+                ctx.report("Enumerated addresses", emitted as u64, count as u64);
+            }
+        }
+
+        pb.finish_and_clear();
+        if emitted < count {
+            warn!(
+                "Address space exhausted after {} of {} requested addresses",
+                emitted, count
+            );
+        }
+        let report = GenerationReport {
+            generated: emitted,
+            duplicates: 0,
+            elapsed: started.elapsed(),
         };
-        let parsed_target = match Target::parse(target) {
-            Ok(t) => t,
-            Err(e) => return Err(format!("Failed to parse target: {}", e)),
+        info!("{}", report);
+        Ok(report)
+    }
+
+    /// Probes exactly `targets`, as listed by [`Commands::scan_targets`], so a dry run
+    /// shows what is sent.
+    fn run_scan(
+        scan_type: &ScanType,
+        targets: &[IpAddr],
+        mut state: Option<scan::state::ScanState>,
+        listen_window: Duration,
+        options: &scan::icmp6::EchoScanOptions,
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
+        let on_progress = |progress: scan::icmp6::ScanProgress| {
+            ctx.report(
+                &format!("Sent probes ({} replies)", progress.replies),
+                progress.sent as u64,
+                progress.total as u64,
+            );
         };
-        let results = match (scan_type, parsed_target) {
-            (ScanType::Icmpv4, Target::Network(ipnet::IpNet::V4(net))) => {
-                scan::icmp6::icmp4_scan(net)
+        info!("Starting {} scan of {} targets", scan_type, targets.len());
+        let results = match scan_type {
+            ScanType::Icmpv4 => {
+                let hosts = Self::targets_of_family(targets, scan_type, |addr| match addr {
+                    IpAddr::V4(v4) => Some(v4),
+                    IpAddr::V6(_) => None,
+                })?;
+                scan::icmp6::icmp4_scan_resumable(&hosts, &on_progress, state.as_mut(), options)
+                    .map_err(CliError::Scan)?
             }
-            (ScanType::Icmpv6, Target::Network(ipnet::IpNet::V6(net))) => {
-                scan::icmp6::icmp6_scan(net)
+            ScanType::Icmpv6 => {
+                let hosts = Self::targets_of_family(targets, scan_type, |addr| match addr {
+                    IpAddr::V6(v6) => Some(v6),
+                    IpAddr::V4(_) => None,
+                })?;
+                scan::icmp6::icmp6_scan_resumable(&hosts, &on_progress, state.as_mut(), options)
+                    .map_err(CliError::Scan)?
             }
-            (ScanType::LinkLocal, _) => {
-                let hosts = scan::link_local::discover_all_ipv6_link_local()
-                    .map_err(|e| format!("Discovery failed: {}", e))?;
+            ScanType::LinkLocal if ctx.is_cancelled() => Vec::new(),
+            ScanType::LinkLocal => {
+                let hosts = scan::link_local::discover_all_ipv6_link_local(
+                    options.identifier,
+                    listen_window,
+                )
+                .map_err(|e| CliError::Scan(format!("Discovery failed: {}", e)))?;
                 hosts
                     .into_iter()
                     .map(|host| scan::icmp6::ProbeResult {
@@ -554,52 +1717,413 @@ impl Commands {
                     })
                     .collect()
             }
-            _ => return Err("Unsupported scan type and target combination".to_string()),
         };
-        let addresses: Vec<String> = results.iter().map(|r| r.addr.to_string()).collect();
-        let rtts: Vec<u64> = results.iter().map(|r| r.rtt.as_millis() as u64).collect();
+        let results: Vec<(IpAddr, probe::ProbeResult)> =
+            results.iter().map(|r| (r.addr, r.into())).collect();
+        probe_results_to_dataframe(&results, ctx.address_column())
+    }
+
+    /// `targets` narrowed to one address family by `family`, failing on any address of
+    /// the other family, which `scan_type` cannot probe.
+    fn targets_of_family<A>(
+        targets: &[IpAddr],
+        scan_type: &ScanType,
+        family: impl Fn(IpAddr) -> Option<A>,
+    ) -> Result<Vec<A>, CliError> {
+        targets
+            .iter()
+            .map(|addr| {
+                family(*addr).ok_or_else(|| {
+                    CliError::InvalidArgument(format!(
+                        "Target {} is the wrong address family for {} scans",
+                        addr, scan_type
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    /// Expands the scan target and input file into the addresses that would be probed,
+    /// keeping those inside an allowlisted network (if any) and outside every
+    /// blocklisted one, and dropping IPv6 addresses matching an `exclude` predicate,
+    /// up to `max_targets`.
+    fn scan_targets(
+        target: &Option<String>,
+        input_file: &Option<PathBuf>,
+        allowlist_file: &Option<PathBuf>,
+        blocklist_file: &Option<PathBuf>,
+        max_targets: Option<u64>,
+        exclude: &[fn(std::net::Ipv6Addr) -> bool],
+    ) -> Result<Vec<IpAddr>, CliError> {
+        let mut networks = Vec::new();
+        if let Some(target) = target {
+            let net = match Target::parse(target) {
+                Ok(Target::SingleIp(addr)) => IpNet::from(addr),
+                Ok(Target::Network(net)) => net,
+                Ok(Target::Hostname(..)) | Err(_) => {
+                    return Err(CliError::Parse(format!(
+                        "Scans need an address or CIDR target, got '{}'",
+                        target
+                    )));
+                }
+            };
+            networks.push(net);
+        }
+        if let Some(input_file) = input_file {
+            networks.extend(crate::source::load_networks(input_file)?);
+        }
+        let allowlist = match allowlist_file {
+            Some(file) => crate::source::load_networks(file)?,
+            None => Vec::new(),
+        };
+        let blocklist = match blocklist_file {
+            Some(file) => crate::source::load_networks(file)?,
+            None => Vec::new(),
+        };
+
+        // Drop networks no allowed host can come from before walking them. CIDRs either
+        // nest or are disjoint, and a range predicate such as documentation matches a
+        // whole network exactly when it matches both ends of it
+        networks.retain(|net| {
+            let allowed = allowlist.is_empty()
+                || allowlist
+                    .iter()
+                    .any(|allowed| allowed.contains(net) || net.contains(allowed));
+            let blocked = blocklist.iter().any(|blocked| blocked.contains(net));
+            let excluded = match net {
+                IpNet::V6(v6) => exclude
+                    .iter()
+                    .any(|matches| matches(v6.network()) && matches(v6.broadcast())),
+                IpNet::V4(_) => false,
+            };
+            allowed && !blocked && !excluded
+        });
+
+        let limit = max_targets.unwrap_or(MAX_UNBOUNDED_TARGETS + 1);
+        let mut targets: Vec<IpAddr> = Vec::new();
+        let mut skipped = 0;
+        for addr in networks.iter().flat_map(IpNet::hosts) {
+            if targets.len() as u64 >= limit {
+                break;
+            }
+            let wanted = (allowlist.is_empty() || allowlist.iter().any(|net| net.contains(&addr)))
+                && !blocklist.iter().any(|net| net.contains(&addr))
+                && match addr {
+                    IpAddr::V6(v6) => !scan::icmp6::is_excluded(v6, exclude),
+                    IpAddr::V4(_) => true,
+                };
+            if wanted {
+                targets.push(addr);
+                continue;
+            }
+            skipped += 1;
+            if skipped >= MAX_SKIPPED_TARGETS {
+                warn!(
+                    "Stopped looking for targets after skipping {} excluded addresses",
+                    skipped
+                );
+                break;
+            }
+        }
+        if max_targets.is_none() && targets.len() as u64 > MAX_UNBOUNDED_TARGETS {
+            return Err(CliError::InvalidArgument(format!(
+                "Scan would cover more than {} targets; set --max-targets to bound it",
+                MAX_UNBOUNDED_TARGETS
+            )));
+        }
+        Ok(targets)
+    }
+
+    fn run_discover(
+        &self,
+        listen_window: Duration,
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
+        let identifier = scan::icmp6::random_identifier();
+        let hosts = scan::link_local::discover_all_ipv6_link_local(identifier, listen_window)
+            .map_err(|e| CliError::Scan(format!("Discovery failed: {}", e)))?;
+        let results: Vec<(IpAddr, probe::ProbeResult)> = hosts
+            .into_iter()
+            .map(|host| {
+                let result = probe::ProbeResult::Reachable {
+                    rtt_ms: 0,
+                    details: None,
+                };
+                (IpAddr::V6(host), result)
+            })
+            .collect();
+        probe_results_to_dataframe(&results, ctx.address_column())
+    }
+
+    fn run_train(&self) -> Result<DataFrame, CliError> {
+        let message = "Training functionality not yet implemented".to_string();
+        DataFrame::new(vec![Series::new("message".into(), vec![message]).into()])
+            .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
+    }
+
+    /// Key/value rows describing this build, for bug reports.
+    fn run_info() -> Result<DataFrame, CliError> {
+        let mut tgas = tga::TgaRegistry::get_available_tgas();
+        tgas.sort_unstable();
+        let python_tgas = match tga::get_available_python_tga_infos() {
+            Ok(infos) if !infos.is_empty() => {
+                let names: Vec<String> = infos.into_iter().map(|info| info.name).collect();
+                format!("available ({})", names.join(", "))
+            }
+            _ => "unavailable".to_string(),
+        };
+
+        let rows = [
+            ("version", env!("CARGO_PKG_VERSION").to_string()),
+            ("tga_count", tgas.len().to_string()),
+            ("tgas", tgas.join(", ")),
+            ("predicate_count", get_all_predicates().len().to_string()),
+            ("python_tgas", python_tgas),
+        ];
+        let keys: Vec<&str> = rows.iter().map(|(key, _)| *key).collect();
+        let values: Vec<&str> = rows.iter().map(|(_, value)| value.as_str()).collect();
         DataFrame::new(vec![
-            Series::new("address".into(), addresses).into(),
-            Series::new("rtt_ms".into(), rtts).into(),
+            Series::new("key".into(), keys).into(),
+            Series::new("value".into(), values).into(),
         ])
-        .map_err(|e| format!("Failed to create DataFrame: {}", e))
+        .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
     }
 
-    fn run_discover(&self) -> Result<DataFrame, String> {
-        let hosts = scan::link_local::discover_all_ipv6_link_local()
-            .map_err(|e| format!("Discovery failed: {}", e))?;
-        let results: Vec<scan::icmp6::ProbeResult> = hosts
-            .into_iter()
-            .map(|host| scan::icmp6::ProbeResult {
-                addr: std::net::IpAddr::V6(host),
-                rtt: std::time::Duration::from_millis(0),
+    fn run_describe_analysis(analysis: &AnalyzeCommand) -> Result<DataFrame, CliError> {
+        let analysis_type = analysis.analysis_type();
+        let schema = analysis_type.output_schema();
+        let columns: Vec<&str> = schema.iter().map(|(name, _)| *name).collect();
+        let dtypes: Vec<String> = schema.iter().map(|(_, dtype)| dtype.to_string()).collect();
+        DataFrame::new(vec![
+            Series::new("column".into(), columns).into(),
+            Series::new("dtype".into(), dtypes).into(),
+        ])
+        .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
+    }
+
+    /// Joins `left` and `right` on the address column. Columns both files share get a
+    /// `_right` suffix on the right-hand side.
+    fn run_join(
+        left: &PathBuf,
+        right: &PathBuf,
+        how: JoinKind,
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
+        let address_column = ctx.address_column();
+        let load = |file: &PathBuf| -> Result<LazyFrame, CliError> {
+            let mut df = crate::source::load_input(
+                file,
+                InputFormat::Auto,
+                &[],
+                None,
+                address_column,
+                &CsvOptions::default(),
+            )?;
+            let name = Self::address_series(&df, ctx)?.name().clone();
+            if name.as_str() != address_column {
+                df.rename(&name, address_column.into()).map_err(|e| {
+                    CliError::Polars(format!(
+                        "Failed to rename {} in {}: {}",
+                        name,
+                        file.display(),
+                        e
+                    ))
+                })?;
+            }
+            Ok(df.lazy())
+        };
+
+        load(left)?
+            .join(
+                load(right)?,
+                [col(address_column)],
+                [col(address_column)],
+                JoinArgs::new(how.into()),
+            )
+            .collect()
+            .map_err(|e| {
+                CliError::Polars(format!(
+                    "Failed to join {} and {}: {}",
+                    left.display(),
+                    right.display(),
+                    e
+                ))
             })
+    }
+
+    /// Parses `args` against the registered plugin subcommands and runs the chosen
+    /// plugin on `file`.
+    fn run_plugin(
+        file: &PathBuf,
+        args: &[String],
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
+        let matches = plugin::attach_all_subcommands(
+            clap::Command::new("plugin")
+                .no_binary_name(true)
+                .subcommand_required(true),
+        )
+        .try_get_matches_from(args)
+        .map_err(|e| CliError::InvalidArgument(e.to_string()))?;
+        let df = crate::source::load_input(
+            file,
+            InputFormat::Auto,
+            &[],
+            None,
+            ctx.address_column(),
+            &CsvOptions::default(),
+        )?;
+
+        plugin::block_on(plugin::dispatch(&matches, df))
+            .map_err(|e| CliError::Polars(format!("Plugin failed: {}", e)))?
+            .ok_or_else(|| CliError::InvalidArgument("No plugin selected".to_string()))
+    }
+
+    fn run_sample(
+        file: &PathBuf,
+        count: Option<u64>,
+        fraction: Option<f64>,
+        seed: Option<u64>,
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
+        let size = match (count, fraction) {
+            (Some(count), _) => SampleSize::Count(count as usize),
+            (None, Some(fraction)) => SampleSize::Fraction(fraction),
+            (None, None) => {
+                return Err(CliError::InvalidArgument(
+                    "Either --n or --fraction is required".to_string(),
+                ));
+            }
+        };
+        let addresses: Vec<String> = crate::source::sample_ip_list(file, size, seed)?
+            .iter()
+            .map(IpAddr::to_string)
             .collect();
-        let addresses: Vec<String> = results.iter().map(|r| r.addr.to_string()).collect();
-        let rtts: Vec<u64> = results.iter().map(|r| r.rtt.as_millis() as u64).collect();
+
         DataFrame::new(vec![
-            Series::new("address".into(), addresses).into(),
-            Series::new("rtt_ms".into(), rtts).into(),
+            Series::new(ctx.address_column().into(), addresses).into(),
         ])
-        .map_err(|e| format!("Failed to create DataFrame: {}", e))
+        .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
     }
 
-    fn run_train(&self) -> Result<DataFrame, String> {
-        let message = "Training functionality not yet implemented".to_string();
-        DataFrame::new(vec![Series::new("message".into(), vec![message]).into()])
-            .map_err(|e| format!("Failed to create DataFrame: {}", e))
+    /// The distinct addresses of `a`, in file order and canonical form, that are in `b`
+    /// (with `in_both`) or not in it. Addresses are compared as `Ipv6Addr`s, so
+    /// `2001:DB8::01` and `2001:db8::1` are the same address.
+    fn run_set_operation(
+        a: &PathBuf,
+        b: &PathBuf,
+        in_both: bool,
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
+        let b: HashSet<Ipv6Addr> = Self::load_addresses(b, ctx)?.into_iter().collect();
+        let mut seen = HashSet::new();
+        let addresses: Vec<String> = Self::load_addresses(a, ctx)?
+            .into_iter()
+            .filter(|addr| b.contains(addr) == in_both && seen.insert(*addr))
+            .map(|addr| addr.to_string())
+            .collect();
+
+        DataFrame::new(vec![
+            Series::new(ctx.address_column().into(), addresses).into(),
+        ])
+        .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
+    }
+
+    /// Every IPv6 address in `file`'s address column, skipping values that do not parse.
+    fn load_addresses(file: &PathBuf, ctx: &RunContext) -> Result<Vec<Ipv6Addr>, CliError> {
+        let df = crate::source::load_input(
+            file,
+            InputFormat::Auto,
+            &[],
+            None,
+            ctx.address_column(),
+            &CsvOptions::default(),
+        )?;
+        let column = Self::address_series(&df, ctx)?
+            .str()
+            .map_err(|e| CliError::Polars(format!("Failed to convert to string series: {}", e)))?;
+
+        let addresses: Vec<Ipv6Addr> = column
+            .into_no_null_iter()
+            .filter_map(|s| s.trim().parse().ok())
+            .collect();
+        let skipped = column.len() - addresses.len();
+        if skipped > 0 {
+            warn!(
+                "Skipped {} values in {} that are not IPv6 addresses",
+                skipped,
+                file.display()
+            );
+        }
+        Ok(addresses)
+    }
+
+    fn run_list_predicates() -> Result<DataFrame, CliError> {
+        let infos = get_predicate_infos();
+        let categories: Vec<&str> = infos.iter().map(|info| info.category).collect();
+        let names: Vec<&str> = infos.iter().map(|info| info.name).collect();
+        let descriptions: Vec<&str> = infos.iter().map(|info| info.description).collect();
+        DataFrame::new(vec![
+            Series::new("category".into(), categories).into(),
+            Series::new("name".into(), names).into(),
+            Series::new("description".into(), descriptions).into(),
+        ])
+        .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
+    }
+
+    fn run_filter(
+        &self,
+        file: &PathBuf,
+        input_format: InputFormat,
+        field: &[String],
+        include: &Vec<AddressPredicate>,
+        exclude: &Vec<AddressPredicate>,
+        prefix: &[Ipv6Net],
+        unique: &bool,
+        export: &PathBuf,
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
+        let df = crate::source::load_input(
+            file,
+            input_format,
+            field,
+            None,
+            ctx.address_column(),
+            &CsvOptions::default(),
+        )?;
+        let mut processed_df =
+            self.apply_filter_and_unique(df, include, exclude, prefix, unique, ctx)?;
+        crate::sink::write_dataframe(&mut processed_df, export)?;
+
+        DataFrame::new(vec![
+            Series::new("exported".into(), vec![export.display().to_string()]).into(),
+            Series::new("rows".into(), vec![processed_df.height() as u64]).into(),
+        ])
+        .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
     }
 
     fn run_view(
         &self,
         file: &PathBuf,
-        field: &Option<String>,
+        input_format: InputFormat,
+        field: &[String],
         include: &Vec<AddressPredicate>,
         exclude: &Vec<AddressPredicate>,
+        prefix: &[Ipv6Net],
         unique: &bool,
-    ) -> Result<DataFrame, String> {
-        let df = crate::source::load_file(file, field);
-        let processed_df = self.apply_filter_and_unique(df, include, exclude, unique)?;
+        limit: Option<usize>,
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
+        let df = crate::source::load_input(
+            file,
+            input_format,
+            field,
+            limit,
+            ctx.address_column(),
+            &CsvOptions::default(),
+        )?;
+        let processed_df =
+            self.apply_filter_and_unique(df, include, exclude, prefix, unique, ctx)?;
         Ok(processed_df)
     }
 
@@ -608,20 +2132,33 @@ impl Commands {
         df: DataFrame,
         include: &Vec<AddressPredicate>,
         exclude: &Vec<AddressPredicate>,
+        prefix: &[Ipv6Net],
         unique: &bool,
-    ) -> Result<DataFrame, String> {
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
         let mut processed_df = df;
 
+        if !prefix.is_empty() {
+            processed_df = self.apply_prefix_filter(processed_df, prefix, ctx)?;
+        }
+
         for predicate in include {
-            processed_df = self.apply_filter(processed_df, predicate, true)?;
+            processed_df = self.apply_filter(processed_df, predicate, true, ctx)?;
         }
 
         for predicate in exclude {
-            processed_df = self.apply_filter(processed_df, predicate, false)?;
+            processed_df = self.apply_filter(processed_df, predicate, false, ctx)?;
         }
 
         if *unique {
-            processed_df = self.apply_unique(processed_df)?;
+            // String dedup would treat different spellings of one address as distinct
+            let (normalized_df, normalized) = self.apply_normalize(processed_df, ctx)?;
+            info!(
+                "Normalized {} of {} addresses to canonical form",
+                normalized,
+                normalized_df.height()
+            );
+            processed_df = self.apply_unique(normalized_df, ctx)?;
         }
 
         Ok(processed_df)
@@ -632,32 +2169,21 @@ impl Commands {
         df: DataFrame,
         filter_predicate: &AddressPredicate,
         include: bool,
-    ) -> Result<DataFrame, String> {
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
         // Check if dataframe is empty
         if df.height() == 0 {
             return Ok(df);
         }
 
         let filter_name = filter_predicate.to_filter_name();
-        let all_predicates = get_all_predicates();
-        let predicate_fn = all_predicates
-            .into_iter()
-            .find(|(name, _)| name == &filter_name)
-            .map(|(_, func)| func)
-            .ok_or_else(|| format!("No predicate found with name: {}", filter_name))?;
+        let predicate_fn = Self::predicate_fn(filter_predicate)?;
 
-        let columns = df.get_columns();
-        let series = if columns.len() == 1 {
-            columns[0].as_series().unwrap()
-        } else {
-            columns[0].as_series().unwrap()
-        };
-
-        let utf8_series = series
+        let utf8_series = Self::address_series(&df, ctx)?
             .str()
-            .map_err(|e| format!("Failed to convert to string series: {}", e))?;
+            .map_err(|e| CliError::Polars(format!("Failed to convert to string series: {}", e)))?;
 
-        let filter_pb = ProgressBar::new(utf8_series.len() as u64);
+        let filter_pb = ctx.progress_bar(utf8_series.len() as u64);
         filter_pb.set_style(
             ProgressStyle::default_bar()
                 .template("[{elapsed_precise}] {msg} [{bar:20.cyan/blue}] {pos}/{len}")
@@ -670,38 +2196,146 @@ impl Commands {
             mode, filter_name
         ));
 
-        let mut filtered_addresses = Vec::new();
+        // Build a row mask rather than copying addresses out, so every other column
+        // (rtt, type, ...) survives aligned with its address
+        let mut keep = Vec::with_capacity(utf8_series.len());
         for (i, opt_str) in utf8_series.into_iter().enumerate() {
-            if let Some(s) = opt_str {
-                if let Ok(addr) = s.parse::<std::net::Ipv6Addr>() {
-                    let matches = predicate_fn(addr);
-                    if (include && matches) || (!include && !matches) {
-                        filtered_addresses.push(s);
-                    }
-                }
-            }
+            let matches = opt_str
+                .and_then(|s| s.parse::<std::net::Ipv6Addr>().ok())
+                .map(|addr| predicate_fn(addr) == include);
+            keep.push(matches.unwrap_or(false));
 
             if i % 1000 == 0 {
                 filter_pb.set_position(i as u64);
             }
         }
+        let mask = BooleanChunked::from_slice("mask".into(), &keep);
+
+        let filtered = df
+            .filter(&mask)
+            .map_err(|e| CliError::Polars(format!("Failed to create filtered DataFrame: {}", e)))?;
 
         filter_pb.finish_with_message(format!(
             "{} complete! Found {} matching addresses",
             mode,
-            filtered_addresses.len()
+            filtered.height()
         ));
 
-        DataFrame::new(vec![
-            Series::new("address".into(), filtered_addresses).into(),
-        ])
-        .map_err(|e| format!("Failed to create filtered DataFrame: {}", e))
+        Ok(filtered)
+    }
+
+    fn predicate_fns(
+        predicates: &[AddressPredicate],
+    ) -> Result<Vec<fn(std::net::Ipv6Addr) -> bool>, CliError> {
+        predicates.iter().map(Self::predicate_fn).collect()
+    }
+
+    /// Looks up the function implementing `predicate`.
+    fn predicate_fn(
+        predicate: &AddressPredicate,
+    ) -> Result<fn(std::net::Ipv6Addr) -> bool, CliError> {
+        let filter_name = predicate.to_filter_name();
+        get_all_predicates()
+            .into_iter()
+            .find(|(name, _, _)| name == &filter_name)
+            .map(|(_, _, func)| func)
+            .ok_or_else(|| {
+                CliError::InvalidArgument(format!("No predicate found with name: {}", filter_name))
+            })
     }
 
-    fn apply_unique(&self, df: DataFrame) -> Result<DataFrame, String> {
+    /// The column filters read addresses from: the address column if the frame has it,
+    /// otherwise the first string column, so files with another header still work. A
+    /// column named with `--address-column` must exist.
+    fn address_series<'a>(df: &'a DataFrame, ctx: &RunContext) -> Result<&'a Column, CliError> {
+        if let Ok(column) = df.column(ctx.address_column()) {
+            return Ok(column);
+        }
+        if let Some(name) = &ctx.address_column {
+            let columns: Vec<&str> = df.get_column_names_str();
+            return Err(CliError::InvalidArgument(format!(
+                "Address column '{}' not found in input (columns: {})",
+                name,
+                columns.join(", ")
+            )));
+        }
+        df.get_columns()
+            .iter()
+            .find(|c| c.dtype() == &DataType::String)
+            .or_else(|| df.get_columns().first())
+            .ok_or_else(|| CliError::Parse("Input has no columns".to_string()))
+    }
+
+    /// Keeps the rows whose address column holds an address inside one of `prefixes`.
+    fn apply_prefix_filter(
+        &self,
+        df: DataFrame,
+        prefixes: &[Ipv6Net],
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
+        if df.width() == 0 {
+            return Ok(df);
+        }
+        let utf8_series = Self::address_series(&df, ctx)?
+            .str()
+            .map_err(|e| CliError::Polars(format!("Failed to convert to string series: {}", e)))?;
+
+        let mask: BooleanChunked = utf8_series
+            .into_iter()
+            .map(|opt_str| {
+                opt_str
+                    .and_then(|s| s.parse::<std::net::Ipv6Addr>().ok())
+                    .is_some_and(|addr| PrefixAnalysis::contains_any(prefixes, &addr))
+            })
+            .collect();
+
+        df.filter(&mask)
+            .map_err(|e| CliError::Polars(format!("Failed to apply prefix filter: {}", e)))
+    }
+
+    /// Rewrites the address column in canonical form (`2001:0DB8::0001` becomes
+    /// `2001:db8::1`), leaving unparseable values as they are. Also returns how many
+    /// rows changed.
+    fn apply_normalize(
+        &self,
+        mut df: DataFrame,
+        ctx: &RunContext,
+    ) -> Result<(DataFrame, usize), CliError> {
+        if df.width() == 0 {
+            return Ok((df, 0));
+        }
+        let column = Self::address_series(&df, ctx)?;
+        let name = column.name().clone();
+        let utf8_series = column
+            .str()
+            .map_err(|e| CliError::Polars(format!("Failed to convert to string series: {}", e)))?;
+
+        let mut normalized = 0;
+        let canonical: StringChunked = utf8_series
+            .into_iter()
+            .map(|opt_str| {
+                opt_str.map(|s| match s.trim().parse::<IpAddr>() {
+                    Ok(addr) => {
+                        let canonical = addr.to_string();
+                        if canonical != s {
+                            normalized += 1;
+                        }
+                        canonical
+                    }
+                    Err(_) => s.to_string(),
+                })
+            })
+            .collect();
+
+        df.with_column(canonical.with_name(name))
+            .map_err(|e| CliError::Polars(format!("Failed to normalize addresses: {}", e)))?;
+        Ok((df, normalized))
+    }
+
+    fn apply_unique(&self, df: DataFrame, ctx: &RunContext) -> Result<DataFrame, CliError> {
         let total_rows = df.height();
 
-        let unique_pb = ProgressBar::new(total_rows as u64);
+        let unique_pb = ctx.progress_bar(total_rows as u64);
         unique_pb.set_style(
             ProgressStyle::default_spinner()
                 .template("[{elapsed_precise}] {msg} {spinner}")
@@ -712,7 +2346,7 @@ impl Commands {
 
         let result = df
             .unique::<Vec<String>, Vec<String>>(None, UniqueKeepStrategy::First, None)
-            .map_err(|e| format!("Failed to apply unique filter: {}", e))?;
+            .map_err(|e| CliError::Polars(format!("Failed to apply unique filter: {}", e)))?;
 
         let unique_count = result.height();
         unique_pb.finish_with_message(format!(
@@ -726,49 +2360,1457 @@ impl Commands {
     fn run_analyze(
         &self,
         file: &PathBuf,
-        field: &Option<String>,
+        input_format: InputFormat,
+        field: &[String],
         include: &Vec<AddressPredicate>,
         exclude: &Vec<AddressPredicate>,
+        prefix: &[Ipv6Net],
         unique: &bool,
+        limit: Option<usize>,
+        csv: &CsvOptions,
         analysis: &AnalyzeCommand,
-    ) -> Result<DataFrame, String> {
-        let df = crate::source::load_file(file, field);
-        let processed_df = self.apply_filter_and_unique(df, include, exclude, unique)?;
-
-        match analysis {
-            AnalyzeCommand::Dispersion => {
-                crate::analyze::analyze(processed_df, crate::analyze::AnalysisType::Dispersion)
-                    .map_err(|e| e.to_string())
-            }
-            AnalyzeCommand::Entropy { start_bit, end_bit } => {
-                if start_bit >= end_bit {
-                    return Err("start_bit must be less than end_bit".to_string());
+        ctx: &RunContext,
+    ) -> Result<DataFrame, CliError> {
+        // These read a scan result column rather than addresses
+        let mut analysis_type = analysis.analysis_type();
+        let scan_column = match analysis_type {
+            analyze::AnalysisType::ResponseTypes => Some("type"),
+            analyze::AnalysisType::RttSummary => Some("rtt_ms"),
+            analyze::AnalysisType::TtlHops => Some("ttl"),
+            _ => None,
+        };
+        if let Some(default_field) = scan_column {
+            let field = if field.is_empty() {
+                vec![default_field.to_string()]
+            } else {
+                field.to_vec()
+            };
+            let df = if crate::source::is_stdin(file) {
+                let stdin = std::io::stdin().lock();
+                let address_column = ctx.address_column();
+                crate::source::load_reader(
+                    stdin,
+                    InputFormat::Csv,
+                    &field,
+                    limit,
+                    address_column,
+                    csv,
+                )?
+            } else {
+                let mut lf = crate::source::open_csv_lazy(file, &field, csv)?;
+                if let Some(limit) = limit {
+                    lf = lf.limit(limit as IdxSize);
                 }
-                crate::analyze::analyze(
-                    processed_df,
-                    crate::analyze::AnalysisType::Entropy {
-                        start_bit: *start_bit,
-                        end_bit: *end_bit,
+                lf.collect().map_err(|e| {
+                    CliError::Polars(format!("Failed to read {} column: {}", field[0], e))
+                })?
+            };
+            return analyze::analyze(df, analysis_type)
+                .map_err(|e| CliError::Analysis(e.to_string()));
+        }
+
+        let df =
+            crate::source::load_input(file, input_format, field, limit, ctx.address_column(), csv)?;
+        let processed_df =
+            self.apply_filter_and_unique(df, include, exclude, prefix, unique, ctx)?;
+        // Analyses read the first column, which may be a numeric scan metric
+        let processed_df = match processed_df.width() {
+            0 => processed_df,
+            _ => DataFrame::new(vec![Self::address_series(&processed_df, ctx)?.clone()])
+                .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))?,
+        };
+
+        if let (
+            AnalyzeCommand::Subnets {
+                allocations: Some(file),
+                ..
+            },
+            analyze::AnalysisType::Allocations { allocations, .. },
+        ) = (analysis, &mut analysis_type)
+        {
+            *allocations = crate::source::load_allocations(file)?;
+        }
+        analyze::analyze(processed_df, analysis_type).map_err(|e| CliError::Analysis(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(scan_type: ScanType, target: Option<&str>) -> Commands {
+        Commands::Scan {
+            scan_type,
+            target: target.map(str::to_string),
+            input_file: None,
+            blocklist_file: None,
+            allowlist_file: None,
+            max_targets: None,
+            rate: 10000,
+            probes: 1,
+            max_runtime: None,
+            cooldown_time: 8,
+            seed: None,
+            source_ip: None,
+            interface: None,
+            probe_module: ProbeModule::IcmpEchoScan,
+            resume: None,
+            dry_run: false,
+            buffer_size: scan::icmp6::MAX_PACKET_SIZE,
+            identifier: None,
+            listen_window: 5,
+            payload_pattern: "zeros".to_string(),
+            payload_size: scan::icmp6::DEFAULT_PAYLOAD_SIZE,
+            exclude_predicate: Vec::new(),
+            extension_header: None,
+        }
+    }
+
+    #[test]
+    fn test_stream_generate_writes_one_line_per_address() {
+        let mut out = Vec::new();
+        let written = Commands::stream_generate(
+            1000,
+            GenerationMode::Sample,
+            &[],
+            &tga::demo_seeds(tga::DEMO_SEED_COUNT),
+            None,
+            RowWriter::new(&mut out, StreamFormat::JsonLines, &["address"]).unwrap(),
+            &RunContext::default(),
+        )
+        .unwrap();
+        assert_eq!(written, 1000);
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 1000);
+        for line in lines {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            let addr = value["address"].as_str().unwrap();
+            assert!(addr.parse::<std::net::Ipv6Addr>().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_stream_generate_writes_large_csv_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stream.csv");
+        let file = std::fs::File::create(&path).unwrap();
+        let written = Commands::stream_generate(
+            200_000,
+            GenerationMode::Sample,
+            &[],
+            &tga::demo_seeds(tga::DEMO_SEED_COUNT),
+            Some(7),
+            RowWriter::new(file, StreamFormat::Csv, &["address"]).unwrap(),
+            &RunContext::default(),
+        )
+        .unwrap();
+        assert_eq!(written, 200_000);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("address"));
+        assert_eq!(lines.count(), 200_000);
+    }
+
+    #[test]
+    fn test_appended_generate_runs_keep_both_sets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("append-gen.csv");
+        let ctx = RunContext::default();
+        for seed in [1, 2] {
+            let (file, write_header) =
+                crate::sink::open_append(&path, StreamFormat::Csv, &["address"]).unwrap();
+            let writer =
+                RowWriter::new_with_options(file, StreamFormat::Csv, &["address"], write_header)
+                    .unwrap();
+            Commands::stream_generate(
+                100,
+                GenerationMode::Sample,
+                &[],
+                &tga::demo_seeds(tga::DEMO_SEED_COUNT),
+                Some(seed),
+                writer,
+                &ctx,
+            )
+            .unwrap();
+        }
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 201);
+        assert_eq!(lines.iter().filter(|line| **line == "address").count(), 1);
+
+        let expected: Vec<String> = [1, 2]
+            .into_iter()
+            .flat_map(|seed| {
+                let mut addrs = Vec::new();
+                Commands::generate_addresses(
+                    100,
+                    GenerationMode::Sample,
+                    &[],
+                    &tga::demo_seeds(tga::DEMO_SEED_COUNT),
+                    Some(seed),
+                    &ctx,
+                    |addr| {
+                        addrs.push(addr.to_string());
+                        Ok(())
                     },
                 )
-                .map_err(|e| e.to_string())
-            }
-            AnalyzeCommand::Subnets {
-                max_subnets,
-                prefix_length,
-            } => crate::analyze::analyze(
-                processed_df,
-                crate::analyze::AnalysisType::Subnets {
-                    max_subnets: *max_subnets,
-                    prefix_length: *prefix_length,
-                },
+                .unwrap();
+                addrs
+            })
+            .collect();
+        assert_eq!(lines[1..], expected);
+    }
+
+    #[test]
+    fn test_unique_generation_beyond_capacity_fails_up_front() {
+        // A single seed leaves one value per segment, so one distinct address
+        let mut emitted = 0;
+        let err = Commands::generate_addresses(
+            2,
+            GenerationMode::Unique,
+            &[],
+            &tga::demo_seeds(1),
+            Some(1),
+            &RunContext::default(),
+            |_| {
+                emitted += 1;
+                Ok(())
+            })
+            .unwrap_err();
+        assert!(matches!(err, CliError::InvalidArgument(_)), "{}", err);
+        assert!(err.to_string().contains("can only produce 1"), "{}", err);
+        assert_eq!(emitted, 0);
+
+        // Repeats are fine when uniqueness is not asked for
+        Commands::generate_addresses(
+            2,
+            GenerationMode::Sample,
+            &[],
+            &tga::demo_seeds(1),
+            Some(1),
+            &RunContext::default(),
+            |_| Ok(()),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_enumeration_stops_when_the_space_is_exhausted() {
+        // A single seed leaves one value per segment, so one address to enumerate
+        let mut addresses = Vec::new();
+        let report = Commands::generate_addresses(
+            5,
+            GenerationMode::Enumerate,
+            &[],
+            &tga::demo_seeds(1),
+            None,
+            &RunContext::default(),
+            |addr| {
+                addresses.push(addr);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(report.generated, 1);
+        assert_eq!(addresses, [Ipv6Addr::from(tga::demo_seeds(1)[0])]);
+    }
+
+    #[test]
+    fn test_generation_report_counts_requested_addresses() {
+        let report = Commands::generate_addresses(
+            500,
+            GenerationMode::Unique,
+            &[],
+            &tga::demo_seeds(tga::DEMO_SEED_COUNT),
+            None,
+            &RunContext::default(),
+            |_| Ok(()),
+        )
+        .unwrap();
+        assert_eq!(report.generated, 500);
+        assert!(report.duplicate_fraction() < 1.0);
+        assert!(report.to_string().starts_with("Generated 500 addresses"));
+    }
+
+    #[test]
+    fn test_generate_uses_demo_seed_prefixes() {
+        let df = Commands::run_generate(
+            50,
+            GenerationMode::Sample,
+            &[],
+            &tga::demo_seeds(tga::DEMO_SEED_COUNT),
+            None,
+            &RunContext::default(),
+        )
+        .unwrap();
+        let seed_prefixes: std::collections::HashSet<[u8; 4]> = tga::demo_seed_addresses()
+            .iter()
+            .map(|s| [s[0], s[1], s[2], s[3]])
+            .collect();
+        for addr in df
+            .column("address")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+        {
+            let octets = addr.parse::<std::net::Ipv6Addr>().unwrap().octets();
+            assert!(seed_prefixes.contains(&[octets[0], octets[1], octets[2], octets[3]]));
+        }
+    }
+
+    #[test]
+    fn test_generate_trains_on_seed_hostnames() {
+        assert_eq!(
+            Commands::generation_seeds(5, &None).unwrap(),
+            tga::demo_seeds(5)
+        );
+
+        // Nothing to resolve, so this fails without touching the network
+        let dir = tempfile::tempdir().unwrap();
+        let hostnames = dir.path().join("seed-hostnames");
+        std::fs::write(&hostnames, "# no hosts yet\n").unwrap();
+        let command = Commands::Generate {
+            count: 5,
+            unique: false,
+            enumerate: false,
+            jsonl: false,
+            constrain: vec![],
+            seed_count: tga::DEMO_SEED_COUNT,
+            seed_hostnames: Some(hostnames.clone()),
+            seed: None,
+        };
+        let err = command.run().unwrap_err();
+        assert!(
+            err.to_string().contains("None of the 0 hostnames"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_default_generate_spans_several_slash64s() {
+        let generate = |seed| {
+            let df = Commands::run_generate(
+                200,
+                GenerationMode::Sample,
+                &[],
+                &tga::demo_seeds(tga::DEMO_SEED_COUNT),
+                seed,
+                &RunContext::default(),
             )
-            .map_err(|e| e.to_string()),
-            AnalyzeCommand::Counts => crate::analyze::analyze(
-                processed_df,
-                crate::analyze::AnalysisType::Counts,
+            .unwrap();
+            df.column("address")
+                .unwrap()
+                .str()
+                .unwrap()
+                .into_no_null_iter()
+                .map(|addr| addr.parse::<std::net::Ipv6Addr>().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let slash64s: HashSet<[u8; 8]> = generate(None)
+            .iter()
+            .map(|addr| addr.octets()[..8].try_into().unwrap())
+            .collect();
+        assert!(slash64s.len() > 1, "{:?}", slash64s);
+
+        assert_eq!(generate(Some(42)), generate(Some(42)));
+    }
+
+    #[test]
+    fn test_generate_constrain_rejects_matching_addresses() {
+        let constrain = [AddressPredicate::Multicast];
+        let mut generated = Vec::new();
+        Commands::generate_addresses(
+            2000,
+            GenerationMode::Sample,
+            &constrain,
+            &tga::demo_seeds(tga::DEMO_SEED_COUNT),
+            None,
+            &RunContext::default(),
+            |addr| {
+                generated.push(addr);
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(generated.len(), 2000);
+        assert!(generated.iter().all(|addr| !addr.is_multicast()));
+
+        // Every demo seed is a documentation address, so nothing can satisfy this
+        let constrain = [AddressPredicate::Documentation];
+        let err = Commands::run_generate(
+            1,
+            GenerationMode::Sample,
+            &constrain,
+            &tga::demo_seeds(tga::DEMO_SEED_COUNT),
+            None,
+            &RunContext::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, CliError::Tga(_)), "{:?}", err);
+        assert!(err.to_string().contains("0/1"), "{}", err);
+    }
+
+    fn analyze(analysis: AnalyzeCommand) -> Commands {
+        Commands::Analyze {
+            file: PathBuf::from("addresses.txt"),
+            input_format: InputFormat::Auto,
+            field: vec![],
+            include: vec![],
+            exclude: vec![],
+            prefix: vec![],
+            unique: false,
+            limit: None,
+            csv_delimiter: b',',
+            csv_quote: b'"',
+            analysis,
+        }
+    }
+
+    #[test]
+    fn test_limit_bounds_analyzed_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("limit.csv");
+        let mut contents = String::from("address\n");
+        for i in 0..1000u32 {
+            contents.push_str(&format!("2001:db8::{:x}\n", i));
+        }
+        std::fs::write(&path, contents).unwrap();
+
+        let mut command = analyze(AnalyzeCommand::Counts {
+            count_only: false,
+            predicate: None,
+        });
+        if let Commands::Analyze { file, limit, .. } = &mut command {
+            *file = path.clone();
+            *limit = Some(100);
+        }
+        let out = command.run().unwrap();
+
+        let counts: Vec<u64> = out
+            .column("count")
+            .unwrap()
+            .u64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert!(!counts.is_empty());
+        assert!(counts.iter().all(|count| *count <= 100));
+    }
+
+    #[test]
+    fn test_analyze_directory_concatenates_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        std::fs::write(dir.join("day1.csv"), "address\n2001:db8::1\n2001:db8::2\n").unwrap();
+        std::fs::write(
+            dir.join("day2.csv"),
+            "address\n2001:db8::3\n2001:db8::1\n::1\n",
+        )
+        .unwrap();
+
+        let mut command = analyze(AnalyzeCommand::Statistics { approximate: false });
+        if let Commands::Analyze { file, .. } = &mut command {
+            *file = dir.clone();
+        }
+        let out = command.run().unwrap();
+        assert_eq!(
+            out.column("total_count").unwrap().u64().unwrap().get(0),
+            Some(5)
+        );
+        assert_eq!(
+            out.column("unique_count").unwrap().u64().unwrap().get(0),
+            Some(4)
+        );
+
+        std::fs::write(dir.join("day3.csv"), "saddr\n2001:db8::4\n").unwrap();
+        let err = command.run().unwrap_err();
+        assert!(matches!(err, CliError::Parse(_)), "{:?}", err);
+        assert!(err.to_string().contains("share a schema"), "{}", err);
+    }
+
+    #[test]
+    fn test_prefix_filter_keeps_addresses_in_any_prefix() {
+        let df = DataFrame::new(vec![
+            Column::new(
+                "address".into(),
+                &["2001:db8::1", "2001:db8:1::1", "2001:db9::1", "garbage"],
+            ),
+            Column::new("rtt_ms".into(), &[1u64, 2, 3, 4]),
+        ])
+        .unwrap();
+        let prefixes: Vec<Ipv6Net> = vec![
+            "2001:db8::/32".parse().unwrap(),
+            "2001:db8:1::/48".parse().unwrap(),
+        ];
+
+        let out = analyze(AnalyzeCommand::Dispersion)
+            .apply_prefix_filter(df, &prefixes, &RunContext::default())
+            .unwrap();
+        assert_eq!(out.height(), 2);
+        assert_eq!(out.get_column_names_str(), ["address", "rtt_ms"]);
+    }
+
+    #[test]
+    fn test_predicate_filter_keeps_other_columns_aligned() {
+        let df = DataFrame::new(vec![
+            Column::new(
+                "address".into(),
+                &["fe80::1", "2001:db8::1", "fe80::2", "bogus"],
+            ),
+            Column::new("rtt_ms".into(), &[10u64, 20, 30, 40]),
+        ])
+        .unwrap();
+
+        let out = analyze(AnalyzeCommand::Dispersion)
+            .apply_filter(
+                df,
+                &AddressPredicate::LinkLocal,
+                true,
+                &RunContext::default(),
+            )
+            .unwrap();
+
+        assert_eq!(out.get_column_names_str(), ["address", "rtt_ms"]);
+        let addresses: Vec<&str> = out
+            .column("address")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        let rtts: Vec<u64> = out
+            .column("rtt_ms")
+            .unwrap()
+            .u64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(addresses, ["fe80::1", "fe80::2"]);
+        assert_eq!(rtts, [10, 30]);
+    }
+
+    #[test]
+    fn test_filter_reads_configured_address_column() {
+        let df = DataFrame::new(vec![
+            Column::new("rtt_ms".into(), &[10u64, 20, 30]),
+            Column::new("saddr".into(), &["fe80::1", "2001:db8::1", "fe80::2"]),
+        ])
+        .unwrap();
+        let ctx = RunContext {
+            address_column: Some("saddr".to_string()),
+            ..Default::default()
+        };
+
+        let out = analyze(AnalyzeCommand::Dispersion)
+            .apply_filter_and_unique(
+                df,
+                &vec![AddressPredicate::LinkLocal],
+                &vec![],
+                &[],
+                &false,
+                &ctx,
             )
-            .map_err(|e| e.to_string()),
+            .unwrap();
+
+        assert_eq!(out.get_column_names_str(), ["rtt_ms", "saddr"]);
+        let addresses: Vec<&str> = out
+            .column("saddr")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(addresses, ["fe80::1", "fe80::2"]);
+
+        let generated = Commands::run_generate(
+            3,
+            GenerationMode::Sample,
+            &[],
+            &tga::demo_seeds(tga::DEMO_SEED_COUNT),
+            None,
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(generated.get_column_names_str(), ["saddr"]);
+    }
+
+    #[test]
+    fn test_missing_named_address_column_is_an_error() {
+        let df = DataFrame::new(vec![
+            Column::new("rtt_ms".into(), &[10u64, 20]),
+            Column::new("saddr".into(), &["fe80::1", "2001:db8::1"]),
+        ])
+        .unwrap();
+        let ctx = RunContext {
+            address_column: Some("daddr".to_string()),
+            ..Default::default()
+        };
+
+        let err = analyze(AnalyzeCommand::Dispersion)
+            .apply_filter_and_unique(
+                df.clone(),
+                &vec![AddressPredicate::LinkLocal],
+                &vec![],
+                &[],
+                &false,
+                &ctx,
+            )
+            .unwrap_err();
+        assert!(matches!(err, CliError::InvalidArgument(_)), "{:?}", err);
+        assert!(err.to_string().contains("'daddr'"), "{}", err);
+
+        // Without a named column the first string column is still used
+        let out = analyze(AnalyzeCommand::Dispersion)
+            .apply_filter_and_unique(
+                df,
+                &vec![AddressPredicate::LinkLocal],
+                &vec![],
+                &[],
+                &false,
+                &RunContext::default(),
+            )
+            .unwrap();
+        assert_eq!(out.height(), 1);
+    }
+
+    #[test]
+    fn test_unique_collapses_differently_spelled_addresses() {
+        let df = DataFrame::new(vec![Column::new(
+            "address".into(),
+            &[
+                "2001:db8::1",
+                "2001:0db8:0000:0000:0000:0000:0000:0001",
+                "2001:DB8::1",
+                "fe80::1",
+            ],
+        )])
+        .unwrap();
+        let command = analyze(AnalyzeCommand::Dispersion);
+
+        let (_, normalized) = command
+            .apply_normalize(df.clone(), &RunContext::default())
+            .unwrap();
+        assert_eq!(normalized, 2);
+
+        let out = command
+            .apply_filter_and_unique(df, &vec![], &vec![], &[], &true, &RunContext::default())
+            .unwrap();
+        assert_eq!(out.height(), 2);
+    }
+
+    #[test]
+    fn test_probe_results_to_dataframe_covers_every_status() {
+        let results = vec![
+            (
+                "2001:db8::1".parse().unwrap(),
+                probe::ProbeResult::Reachable {
+                    rtt_ms: 12,
+                    details: None,
+                },
+            ),
+            (
+                "2001:db8::2".parse().unwrap(),
+                probe::ProbeResult::Unreachable {
+                    reason: "admin prohibited".to_string(),
+                },
+            ),
+            (
+                "192.0.2.1".parse().unwrap(),
+                probe::ProbeResult::Timeout { timeout_ms: 1000 },
+            ),
+            (
+                "2001:db8::3".parse().unwrap(),
+                probe::ProbeResult::Error {
+                    error: "no route".to_string(),
+                },
+            ),
+        ];
+
+        let df = probe_results_to_dataframe(&results, DEFAULT_ADDRESS_COLUMN).unwrap();
+        assert_eq!(
+            df.get_column_names_str(),
+            ["address", "status", "rtt_ms", "detail"]
+        );
+        let statuses: Vec<&str> = df
+            .column("status")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(statuses, ["reachable", "unreachable", "timeout", "error"]);
+        let rtts: Vec<Option<u64>> = df
+            .column("rtt_ms")
+            .unwrap()
+            .u64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(rtts, [Some(12), None, None, None]);
+        let details: Vec<Option<&str>> = df
+            .column("detail")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            details,
+            [None, Some("admin prohibited"), None, Some("no route")]
+        );
+
+        let json = serde_json::to_string(&results[2].1).unwrap();
+        assert_eq!(json, r#"{"status":"timeout","timeout_ms":1000}"#);
+        assert_eq!(
+            serde_json::from_str::<probe::ProbeResult>(&json).unwrap(),
+            results[2].1
+        );
+    }
+
+    #[test]
+    fn test_sort_orders_addresses_numerically() {
+        let df = DataFrame::new(vec![
+            Column::new(
+                "address".into(),
+                &["::10", "bogus", "::2", "2001:db8::1", "::1"],
+            ),
+            Column::new("rtt_ms".into(), &[10u64, 0, 2, 3, 1]),
+        ])
+        .unwrap();
+
+        let sorted = sort_by_address(&df, "address").unwrap();
+        let addresses: Vec<&str> = sorted
+            .column("address")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(addresses, ["::1", "::2", "::10", "2001:db8::1", "bogus"]);
+        let rtts: Vec<u64> = sorted
+            .column("rtt_ms")
+            .unwrap()
+            .u64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(rtts, [1, 2, 10, 3, 0]);
+    }
+
+    #[test]
+    fn test_dry_run_lists_targets_without_scanning() {
+        // Scanning for real would need a raw socket and panic without privileges
+        let dry_run = |max_targets: Option<u64>, blocklist_file: Option<PathBuf>| {
+            let mut command = scan(ScanType::Icmpv6, Some("2001:db8::/120"));
+            if let Commands::Scan {
+                dry_run,
+                max_targets: max,
+                blocklist_file: blocklist,
+                ..
+            } = &mut command
+            {
+                *dry_run = true;
+                *max = max_targets;
+                *blocklist = blocklist_file;
+            }
+            command.run().unwrap()
+        };
+
+        let df = dry_run(None, None);
+        assert_eq!(df.height(), 256);
+        assert_eq!(df.get_column_names_str(), ["address"]);
+
+        assert_eq!(dry_run(Some(10), None).height(), 10);
+
+        let dir = tempfile::tempdir().unwrap();
+        let blocklist = dir.path().join("blocklist");
+        std::fs::write(
+            &blocklist,
+            "# first 16 hosts\n2001:db8::/124\n2001:db8::ff\n",
+        )
+        .unwrap();
+        let df = dry_run(None, Some(blocklist.clone()));
+        assert_eq!(df.height(), 256 - 16 - 1);
+    }
+
+    #[test]
+    fn test_scan_never_probes_blocklisted_hosts() {
+        // Pre-cancelled so no socket is opened; the progress total counts what would be sent
+        let dir = tempfile::tempdir().unwrap();
+        let blocklist = dir.path().join("scan-blocklist");
+        std::fs::write(&blocklist, "2001:db8::2\n").unwrap();
+        let mut command = scan(ScanType::Icmpv6, Some("2001:db8::/126"));
+        if let Commands::Scan { blocklist_file, .. } = &mut command {
+            *blocklist_file = Some(blocklist.clone());
+        }
+        let totals = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&totals);
+        let ctx = RunContext {
+            cancel: Arc::new(AtomicBool::new(true)),
+            on_progress: Some(Box::new(move |progress: Progress| {
+                recorded.lock().unwrap().push(progress.total);
+            })),
+            ..Default::default()
+        };
+
+        let df = command.run_with_context(&ctx);
+        let targets = Commands::scan_targets(
+            &Some("2001:db8::/126".to_string()),
+            &None,
+            &None,
+            &Some(blocklist.clone()),
+            None,
+            &[],
+        );
+
+        assert_eq!(df.unwrap().height(), 0);
+        assert_eq!(*totals.lock().unwrap(), [3]);
+        assert!(!targets.unwrap().contains(&"2001:db8::2".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_scan_skips_a_wholly_excluded_target() {
+        let documentation = Commands::predicate_fns(&[AddressPredicate::Documentation]).unwrap();
+        let targets = Commands::scan_targets(
+            &Some("2001:db8::/32".to_string()),
+            &None,
+            &None,
+            &None,
+            Some(10),
+            &documentation,
+        )
+        .unwrap();
+        assert!(targets.is_empty(), "{:?}", targets);
+
+        // A predicate matching none of the network leaves it to be walked
+        let targets = Commands::scan_targets(
+            &Some("2001:db8::/32".to_string()),
+            &None,
+            &None,
+            &None,
+            Some(10),
+            &Commands::predicate_fns(&[AddressPredicate::Multicast]).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(targets.len(), 10);
+    }
+
+    #[test]
+    fn test_scan_runs_every_target_shape() {
+        // Pre-cancelled so nothing is sent; returns the progress totals each scan reported
+        let run = |command: Commands| {
+            let totals = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let recorded = Arc::clone(&totals);
+            let ctx = RunContext {
+                cancel: Arc::new(AtomicBool::new(true)),
+                on_progress: Some(Box::new(move |progress: Progress| {
+                    recorded.lock().unwrap().push(progress.total);
+                })),
+                ..Default::default()
+            };
+            let df = command.run_with_context(&ctx).unwrap();
+            assert_eq!(df.height(), 0);
+            let totals = totals.lock().unwrap().clone();
+            totals
+        };
+
+        assert!(run(scan(ScanType::LinkLocal, None)).is_empty());
+        assert_eq!(run(scan(ScanType::Icmpv6, Some("2001:db8::1"))), [1]);
+        assert_eq!(run(scan(ScanType::Icmpv4, Some("192.0.2.1"))), [1]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("scan-input");
+        std::fs::write(&input, "2001:db8::1\n2001:db8:1::/127\n").unwrap();
+        let mut command = scan(ScanType::Icmpv6, None);
+        if let Commands::Scan { input_file, .. } = &mut command {
+            *input_file = Some(input.clone());
+        }
+        let totals = run(command);
+        assert_eq!(totals, [3]);
+    }
+
+    #[test]
+    fn test_scan_rejects_hostname_targets() {
+        let err = scan(ScanType::Icmpv6, Some("www.example.test"))
+            .validate()
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("hostnames are not supported"),
+            "{}",
+            err
+        );
+        assert!(Target::parse("www.example.test").is_err());
+    }
+
+    #[test]
+    fn test_dry_run_skips_excluded_predicate_targets() {
+        // ::/126 holds the unspecified and loopback addresses, then ::2 and ::3
+        let mut command = scan(ScanType::Icmpv6, Some("::/126"));
+        if let Commands::Scan {
+            dry_run,
+            exclude_predicate,
+            ..
+        } = &mut command
+        {
+            *dry_run = true;
+            *exclude_predicate = vec![AddressPredicate::Unspecified, AddressPredicate::Loopback];
+        }
+        command.validate().unwrap();
+        let df = command.run().unwrap();
+        let targets: Vec<&str> = df
+            .column("address")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(targets, ["::2", "::3"]);
+
+        let mut command = scan(ScanType::Icmpv4, Some("192.0.2.0/30"));
+        if let Commands::Scan {
+            exclude_predicate, ..
+        } = &mut command
+        {
+            *exclude_predicate = vec![AddressPredicate::Multicast];
+        }
+        assert!(command.validate().is_err());
+    }
+
+    #[test]
+    fn test_quiet_mode_hides_progress_bars() {
+        let ctx = RunContext {
+            quiet: true,
+            ..Default::default()
+        };
+        assert!(ctx.progress_bar(10).is_hidden());
+
+        let df = DataFrame::new(vec![Column::new(
+            "address".into(),
+            &["fe80::1", "fe80::1", "2001:db8::1"],
+        )])
+        .unwrap();
+        let out = analyze(AnalyzeCommand::Dispersion)
+            .apply_unique(df, &ctx)
+            .unwrap();
+        // Deduplication does not keep row order
+        let mut addresses: Vec<&str> = out
+            .column("address")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        addresses.sort();
+        assert_eq!(addresses, ["2001:db8::1", "fe80::1"]);
+    }
+
+    #[test]
+    fn test_filter_exports_link_local_addresses() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("filter-in.csv");
+        let export = dir.path().join("filter-out.csv");
+        std::fs::write(&input, "address\nfe80::1\n2001:db8::1\nfe80::2\nff02::1\n").unwrap();
+
+        let command = Commands::Filter {
+            file: input.clone(),
+            input_format: InputFormat::Auto,
+            field: vec![],
+            include: vec![AddressPredicate::LinkLocal],
+            exclude: vec![],
+            prefix: vec![],
+            unique: false,
+            export: export.clone(),
+        };
+        let summary = command.run().unwrap();
+        assert_eq!(
+            summary.column("rows").unwrap().u64().unwrap().get(0),
+            Some(2)
+        );
+
+        let exported = CsvReadOptions::default()
+            .with_has_header(true)
+            .try_into_reader_with_file_path(Some(export.clone()))
+            .unwrap()
+            .finish()
+            .unwrap();
+
+        let addresses: Vec<&str> = exported
+            .column("address")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(addresses, ["fe80::1", "fe80::2"]);
+    }
+
+    #[test]
+    fn test_list_predicates_includes_every_predicate() {
+        let df = Commands::ListPredicates.run().unwrap();
+        let listed: Vec<&str> = df
+            .column("name")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        for (name, _, _) in get_all_predicates() {
+            assert!(
+                listed.contains(&name),
+                "{} missing from list-predicates",
+                name
+            );
         }
+        assert!(
+            df.column("description")
+                .unwrap()
+                .str()
+                .unwrap()
+                .into_no_null_iter()
+                .all(|d| !d.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_join_keeps_rows_by_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let left = dir.path().join("join-left.csv");
+        let right = dir.path().join("join-right.csv");
+        std::fs::write(
+            &left,
+            "address,rtt_ms\n2001:db8::1,10\n2001:db8::2,20\n2001:db8::3,30\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &right,
+            "saddr,rtt_ms\n2001:db8::2,25\n2001:db8::3,35\n2001:db8::4,45\n",
+        )
+        .unwrap();
+
+        let join = |how| {
+            Commands::Join {
+                left: left.clone(),
+                right: right.clone(),
+                how,
+            }
+            .run()
+            .unwrap()
+        };
+        let inner = join(JoinKind::Inner);
+        let left_only = join(JoinKind::Left);
+        let outer = join(JoinKind::Outer);
+
+        assert_eq!(inner.height(), 2);
+        assert_eq!(
+            inner.get_column_names_str(),
+            ["address", "rtt_ms", "rtt_ms_right"]
+        );
+        assert_eq!(left_only.height(), 3);
+        assert_eq!(outer.height(), 4);
+        assert_eq!(outer.column("address").unwrap().null_count(), 1);
+    }
+
+    fn set_operation(in_both: bool) -> Vec<String> {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("set-a.txt");
+        let b = dir.path().join("set-b.txt");
+        std::fs::write(&a, "2001:db8::1\n2001:db8::2\n2001:db8::3\n2001:db8::2\n").unwrap();
+        std::fs::write(&b, "2001:0DB8::0002\n2001:db8:0:0::3\n2001:db8::4\n").unwrap();
+
+        let command = match in_both {
+            true => Commands::Intersect {
+                a: a.clone(),
+                b: b.clone(),
+            },
+            false => Commands::Diff {
+                a: a.clone(),
+                b: b.clone(),
+            },
+        };
+        let df = command.run().unwrap();
+
+        df.column("address")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_ignores_textual_differences() {
+        assert_eq!(set_operation(false), ["2001:db8::1"]);
+    }
+
+    #[test]
+    fn test_intersect_ignores_textual_differences() {
+        assert_eq!(set_operation(true), ["2001:db8::2", "2001:db8::3"]);
+    }
+
+    #[test]
+    fn test_sample_with_seed_is_reproducible() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sample.txt");
+        let contents: String = (0..1000u32)
+            .map(|i| format!("2001:db8::{:x}\n", i))
+            .collect();
+        std::fs::write(&path, contents).unwrap();
+
+        let sample = |seed| {
+            Commands::Sample {
+                file: path.clone(),
+                count: Some(50),
+                fraction: None,
+                seed: Some(seed),
+            }
+            .run()
+            .unwrap()
+        };
+        let first = sample(7);
+        let again = sample(7);
+        let other = sample(8);
+
+        assert_eq!(first.height(), 50);
+        assert!(first.equals(&again));
+        assert!(!first.equals(&other));
+        assert_eq!(first.column("address").unwrap().n_unique().unwrap(), 50);
+    }
+
+    #[test]
+    fn test_validate_rejects_out_of_range_fraction() {
+        let command = Commands::Sample {
+            file: PathBuf::from("targets.txt"),
+            count: None,
+            fraction: Some(1.5),
+            seed: None,
+        };
+        let err = command.validate().unwrap_err();
+        assert!(matches!(err, CliError::InvalidArgument(_)), "{:?}", err);
+        assert!(err.to_string().contains("between 0 and 1"), "{}", err);
+    }
+
+    #[test]
+    fn test_plugin_runs_registered_subcommand() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plugin.txt");
+        std::fs::write(&path, "::\n::1\n").unwrap();
+        let ctx = RunContext {
+            address_column: Some("addr".to_string()),
+            ..Default::default()
+        };
+
+        let plugin = |args: &[&str]| {
+            Commands::Plugin {
+                file: path.clone(),
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+            }
+            .run_with_context(&ctx)
+        };
+        let df = plugin(&["entropy", "--tail-bytes", "2"]);
+        let unknown = plugin(&["no-such-plugin"]);
+
+        let df = df.unwrap();
+        let entropies: Vec<Option<f64>> = df
+            .column("entropy")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(entropies, [Some(0.0), Some(1.0)]);
+        assert!(
+            matches!(unknown, Err(CliError::InvalidArgument(_))),
+            "{:?}",
+            unknown.err()
+        );
+    }
+
+    #[test]
+    fn test_info_lists_builtin_tgas() {
+        let df = Commands::Info.run().unwrap();
+        let value = |key: &str| {
+            let keys = df.column("key").unwrap().str().unwrap();
+            let index = keys.into_no_null_iter().position(|k| k == key).unwrap();
+            df.column("value")
+                .unwrap()
+                .str()
+                .unwrap()
+                .get(index)
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(value("version"), env!("CARGO_PKG_VERSION"));
+        let tgas = value("tgas");
+        assert!(tgas.contains("entropy_ip"), "{}", tgas);
+        assert!(tgas.contains("random_ip"), "{}", tgas);
+        assert_eq!(
+            value("predicate_count"),
+            get_all_predicates().len().to_string()
+        );
+    }
+
+    #[test]
+    fn test_describe_analysis_lists_dispersion_columns() {
+        let df = Commands::DescribeAnalysis {
+            analysis: AnalyzeCommand::Dispersion,
+        }
+        .run()
+        .unwrap();
+        let columns: Vec<&str> = df
+            .column("column")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                "min_distance",
+                "max_distance",
+                "avg_distance",
+                "total_pairs"
+            ]
+        );
+        assert_eq!(
+            df.column("dtype").unwrap().str().unwrap().get(0),
+            Some("u32")
+        );
+    }
+
+    #[test]
+    fn test_target_display() {
+        assert_eq!(
+            Target::parse("2001:db8::1").unwrap().to_string(),
+            "2001:db8::1"
+        );
+        assert_eq!(
+            Target::parse("192.0.2.0/24").unwrap().to_string(),
+            "192.0.2.0/24"
+        );
+
+        let resolved = Target::Hostname(
+            "www.example.test".to_string(),
+            vec!["2001:db8::1".parse().unwrap(), "192.0.2.1".parse().unwrap()],
+        );
+        assert_eq!(
+            resolved.to_string(),
+            "www.example.test (2001:db8::1, 192.0.2.1)"
+        );
+        let unresolved = Target::Hostname("www.example.test".to_string(), vec![]);
+        assert_eq!(unresolved.to_string(), "www.example.test (unresolved)");
+    }
+
+    #[test]
+    fn test_target_with_host_bits_is_normalized() {
+        let net: IpNet = "2001:db8::5/64".parse().unwrap();
+        assert!(Target::has_host_bits(&net));
+        assert!(!Target::has_host_bits(&"2001:db8::/64".parse().unwrap()));
+        assert_eq!(
+            Target::parse("2001:db8::5/64").unwrap(),
+            Target::Network("2001:db8::/64".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_discover_accepts_its_old_wire_form() {
+        for json in ["\"Discover\"", r#"{"Discover":{}}"#] {
+            let command = Commands::from_json(json).unwrap();
+            assert!(
+                matches!(command, Commands::Discover { listen_window: 5 }),
+                "{}",
+                json
+            );
+        }
+
+        let json = serde_json::to_string(&Commands::Discover { listen_window: 9 }).unwrap();
+        assert!(matches!(
+            Commands::from_json(&json).unwrap(),
+            Commands::Discover { listen_window: 9 }
+        ));
+    }
+
+    #[test]
+    fn test_scan_enums_round_trip_through_serde() {
+        for module in ProbeModule::value_variants() {
+            let json = serde_json::to_string(module).unwrap();
+            assert_eq!(&serde_json::from_str::<ProbeModule>(&json).unwrap(), module);
+        }
+        for scan_type in ScanType::value_variants() {
+            let json = serde_json::to_string(scan_type).unwrap();
+            assert_eq!(&serde_json::from_str::<ScanType>(&json).unwrap(), scan_type);
+        }
+        assert_eq!(ProbeModule::TcpSynScan.to_string(), "tcp_syn_scan");
+        assert_eq!(ScanType::LinkLocal.to_string(), "link_local");
+
+        let target = Target::parse("2001:db8::/32").unwrap();
+        let json = serde_json::to_string(&target).unwrap();
+        assert_eq!(serde_json::from_str::<Target>(&json).unwrap(), target);
+    }
+
+    #[test]
+    fn test_validate_accepts_matching_scan_targets() {
+        assert!(
+            scan(ScanType::Icmpv4, Some("192.0.2.0/24"))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            scan(ScanType::Icmpv6, Some("2001:db8::/64"))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            scan(ScanType::Icmpv6, Some("2001:db8::1"))
+                .validate()
+                .is_ok()
+        );
+        assert!(scan(ScanType::LinkLocal, None).validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_icmpv4_with_ipv6_target() {
+        let err = scan(ScanType::Icmpv4, Some("2001:db8::/64"))
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("icmpv6"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_rejects_icmpv6_with_ipv4_target() {
+        let err = scan(ScanType::Icmpv6, Some("192.0.2.1"))
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("icmpv4"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_rejects_link_local_with_target() {
+        let err = scan(ScanType::LinkLocal, Some("fe80::1"))
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("do not take a target"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_rejects_missing_target() {
+        let err = scan(ScanType::Icmpv6, None).validate().unwrap_err();
+        assert!(err.to_string().contains("--input-file"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_checks_extension_header() {
+        let with_header = |scan_type: ScanType, target: &str, header: &str| {
+            let mut command = scan(scan_type, Some(target));
+            if let Commands::Scan {
+                extension_header, ..
+            } = &mut command
+            {
+                *extension_header = Some(header.to_string());
+            }
+            command.validate()
+        };
+
+        assert!(with_header(ScanType::Icmpv6, "2001:db8::1", "hop-by-hop").is_ok());
+        let err = with_header(ScanType::Icmpv6, "2001:db8::1", "routing").unwrap_err();
+        assert!(err.to_string().contains("extension header"), "{}", err);
+        let err = with_header(ScanType::Icmpv4, "192.0.2.1", "hop-by-hop").unwrap_err();
+        assert!(err.to_string().contains("icmpv6 scans only"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_payload_pattern() {
+        let mut command = scan(ScanType::Icmpv6, Some("2001:db8::1"));
+        if let Commands::Scan {
+            payload_pattern, ..
+        } = &mut command
+        {
+            *payload_pattern = "xyz".to_string();
+        }
+        let err = command.validate().unwrap_err();
+        assert!(err.to_string().contains("payload pattern"), "{}", err);
+    }
+
+    #[test]
+    fn test_errors_carry_their_category() {
+        let mut command = analyze(AnalyzeCommand::Statistics { approximate: false });
+        if let Commands::Analyze { file, .. } = &mut command {
+            *file = PathBuf::from("/nonexistent/rmap-addresses.txt");
+        }
+        assert!(matches!(command.run(), Err(CliError::Io(_))));
+
+        let targets = Commands::scan_targets(
+            &Some("not-a-target".to_string()),
+            &None,
+            &None,
+            &None,
+            None,
+            &[],
+        );
+        assert!(matches!(targets, Err(CliError::Parse(_))));
+
+        let err = scan(ScanType::Icmpv4, Some("2001:db8::1"))
+            .validate()
+            .unwrap_err();
+        assert_eq!(err.kind(), "invalid_argument");
+    }
+
+    #[test]
+    fn test_validate_rejects_inverted_entropy_range() {
+        let err = analyze(AnalyzeCommand::Entropy {
+            start_bit: 64,
+            end_bit: 32,
+            granularity: EntropyGranularity::Bit,
+        })
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("must be less than"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_rejects_unaligned_byte_entropy_range() {
+        let entropy = |start_bit, end_bit| {
+            analyze(AnalyzeCommand::Entropy {
+                start_bit,
+                end_bit,
+                granularity: EntropyGranularity::Byte,
+            })
+            .validate()
+        };
+        assert!(entropy(64, 128).is_ok());
+        let err = entropy(60, 128).unwrap_err();
+        assert!(err.to_string().contains("byte-aligned"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_rejects_out_of_range_prefix_length() {
+        let err = analyze(AnalyzeCommand::Subnets {
+            max_subnets: 10,
+            prefix_length: 0,
+            allocations: None,
+        })
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("between 1 and 128"), "{}", err);
+
+        assert!(
+            analyze(AnalyzeCommand::Subnets {
+                max_subnets: 10,
+                prefix_length: 129,
+                allocations: None,
+            })
+            .validate()
+            .is_err()
+        );
     }
 }
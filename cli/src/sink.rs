@@ -1,43 +1,287 @@
+use crate::error::CliError;
 use comfy_table::{Attribute, Cell, CellAlignment, ContentArrangement, Table};
-use polars::{frame::DataFrame, prelude::AnyValue};
+use polars::prelude::{AnyValue, CsvWriter, DataFrame, ParquetWriter, SerWriter};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Writes `df` to `path`, as Parquet if the extension is `.parquet` and as CSV otherwise.
+pub fn write_dataframe(df: &mut DataFrame, path: &Path) -> Result<(), CliError> {
+    let file = File::create(path)
+        .map_err(|e| CliError::Io(format!("Failed to create {}: {}", path.display(), e)))?;
+    if is_parquet(path) {
+        ParquetWriter::new(file)
+            .finish(df)
+            .map(|_| ())
+            .map_err(|e| CliError::Io(format!("Failed to write {}: {}", path.display(), e)))
+    } else {
+        CsvWriter::new(file)
+            .include_header(true)
+            .finish(df)
+            .map_err(|e| CliError::Io(format!("Failed to write {}: {}", path.display(), e)))
+    }
+}
+
+/// Appends `df` to the CSV file at `path`, writing a header only if the file is new
+/// or empty. An existing file must have the same columns.
+pub fn append_dataframe(df: &mut DataFrame, path: &Path) -> Result<(), CliError> {
+    if is_parquet(path) {
+        return Err(CliError::InvalidArgument(format!(
+            "Cannot append to {}: Parquet files can only be overwritten",
+            path.display()
+        )));
+    }
+    let columns: Vec<String> = df
+        .get_column_names_str()
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    let columns: Vec<&str> = columns.iter().map(String::as_str).collect();
+    let (file, write_header) = open_append(path, StreamFormat::Csv, &columns)?;
+    CsvWriter::new(file)
+        .include_header(write_header)
+        .finish(df)
+        .map_err(|e| CliError::Io(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// Opens `path` for appending rows with `columns` in `format`, creating it if needed.
+/// Returns the file and whether it still needs a header, i.e. whether it was empty.
+/// Rows already in the file must have the same columns.
+pub fn open_append(
+    path: &Path,
+    format: StreamFormat,
+    columns: &[&str],
+) -> Result<(File, bool), CliError> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .read(true)
+        .open(path)
+        .map_err(|e| CliError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+
+    let mut first_line = String::new();
+    BufReader::new(&file)
+        .read_line(&mut first_line)
+        .map_err(|e| CliError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    let first_line = first_line.trim_end_matches(['\r', '\n']);
+    if first_line.is_empty() {
+        return Ok((file, true));
+    }
+
+    let mut existing: Vec<String> = match format {
+        StreamFormat::Csv => first_line
+            .split(',')
+            .map(|c| c.trim_matches('"').to_string())
+            .collect(),
+        StreamFormat::JsonLines => match serde_json::from_str(first_line) {
+            Ok(serde_json::Value::Object(row)) => row.keys().cloned().collect(),
+            _ => Vec::new(),
+        },
+    };
+    let mut expected = columns.to_vec();
+    if format == StreamFormat::JsonLines {
+        // JSON objects carry no column order
+        existing.sort();
+        expected.sort();
+    }
+    if existing != expected {
+        return Err(CliError::InvalidArgument(format!(
+            "Cannot append to {}: it has columns [{}] but the new rows have [{}]",
+            path.display(),
+            existing.join(", "),
+            columns.join(", ")
+        )));
+    }
+    Ok((file, false))
+}
+
+fn is_parquet(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("parquet"))
+}
+
+/// Encodings [`RowWriter`] can write one row at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// One JSON object per line, keyed by column name
+    JsonLines,
+    /// A header line followed by one comma-separated line per row
+    Csv,
+}
+
+/// Writes rows as soon as they are produced, so output of any length is written in
+/// bounded memory instead of being collected into a `DataFrame` first.
+pub struct RowWriter<W: Write> {
+    writer: BufWriter<W>,
+    format: StreamFormat,
+    columns: Vec<String>,
+    rows: usize,
+}
+
+impl<W: Write> RowWriter<W> {
+    /// Starts a stream of rows with the given columns; CSV streams write their header here.
+    pub fn new(writer: W, format: StreamFormat, columns: &[&str]) -> Result<Self, CliError> {
+        Self::new_with_options(writer, format, columns, true)
+    }
+
+    /// Like [`RowWriter::new`], but a CSV header is only written if `write_header` is
+    /// set, e.g. not when appending to a file that already has one.
+    pub fn new_with_options(
+        writer: W,
+        format: StreamFormat,
+        columns: &[&str],
+        write_header: bool,
+    ) -> Result<Self, CliError> {
+        let mut row_writer = RowWriter {
+            writer: BufWriter::new(writer),
+            format,
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            rows: 0,
+        };
+        if format == StreamFormat::Csv && write_header {
+            let header: Vec<String> = columns.iter().map(|c| csv_field(c)).collect();
+            row_writer.write_line(&header.join(","))?;
+        }
+        Ok(row_writer)
+    }
+
+    /// Writes one row; `values` are in the order of the columns given to [`RowWriter::new`].
+    pub fn write_row(&mut self, values: &[&str]) -> Result<(), CliError> {
+        let line = match self.format {
+            StreamFormat::JsonLines => {
+                let object: serde_json::Map<String, serde_json::Value> = self
+                    .columns
+                    .iter()
+                    .zip(values)
+                    .map(|(column, value)| (column.clone(), (*value).into()))
+                    .collect();
+                serde_json::Value::Object(object).to_string()
+            }
+            StreamFormat::Csv => {
+                let fields: Vec<String> = values.iter().map(|v| csv_field(v)).collect();
+                fields.join(",")
+            }
+        };
+        self.write_line(&line)?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Flushes buffered rows and returns how many were written, not counting a header.
+    pub fn finish(mut self) -> Result<usize, CliError> {
+        self.writer
+            .flush()
+            .map_err(|e| CliError::Io(format!("Failed to flush output: {}", e)))?;
+        Ok(self.rows)
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), CliError> {
+        writeln!(self.writer, "{}", line)
+            .map_err(|e| CliError::Io(format!("Failed to write row: {}", e)))
+    }
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// How results are rendered for the terminal; files written with `write_dataframe`
+/// are never affected.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrintOptions {
+    /// Keep only the first and last rows of longer frames
+    pub max_rows: Option<usize>,
+    /// Decimal places for float cells; `None` prints them as-is
+    pub precision: Option<usize>,
+    /// Leave out bold headers and any other ANSI styling
+    pub no_color: bool,
+}
+
+/// Whether styling is turned off, by `--no-color` or a non-empty `NO_COLOR` variable.
+pub fn color_disabled(no_color_flag: bool) -> bool {
+    no_color_flag || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
+
+pub fn print_dataframe(df: &DataFrame, opts: &PrintOptions) {
+    print!("{}", format_dataframe(df, opts));
+}
+
+/// Renders `df` for the terminal: one value per line for a single column, a table
+/// otherwise. With `max_rows`, only the first and last rows are kept, around a line
+/// saying how many were omitted.
+pub fn format_dataframe(df: &DataFrame, opts: &PrintOptions) -> String {
+    format_into(Table::new(), df, opts)
+}
+
+/// [`format_dataframe`], with any multi-column output rendered into `table`.
+fn format_into(mut table: Table, df: &DataFrame, opts: &PrintOptions) -> String {
+    let (head, omitted) = match opts.max_rows {
+        Some(max) if df.height() > max => (max.div_ceil(2), df.height() - max),
+        _ => (df.height(), 0),
+    };
+    // `None` marks where the omitted rows would have been
+    let rows: Vec<Option<usize>> = (0..head)
+        .map(Some)
+        .chain((omitted > 0).then_some(None))
+        .chain((head + omitted..df.height()).map(Some))
+        .collect();
+    let elision = format!("... {} rows omitted ...", omitted);
 
-pub fn print_dataframe(df: &DataFrame) {
     if df.get_column_names().len() == 1 {
-        for i in 0..df.height() {
-            let row = df.get_row(i).unwrap();
-            match &row.0[0] {
-                AnyValue::String(s) => println!("{}", s),
-                _ => println!("{}", row.0[0]),
+        let mut out = String::new();
+        for row in rows {
+            match row {
+                Some(i) => match &df.get_row(i).unwrap().0[0] {
+                    AnyValue::String(s) => out.push_str(s),
+                    value => out.push_str(&value.to_string()),
+                },
+                None => out.push_str(&elision),
             }
+            out.push('\n');
         }
 
-        return;
+        return out;
     }
 
-    let mut table = Table::new();
     table.set_content_arrangement(ContentArrangement::Dynamic);
     table.load_preset("     ──            ");
+    if opts.no_color {
+        table.force_no_tty();
+    }
 
     let headers: Vec<Cell> = df
         .get_column_names()
         .iter()
-        .map(|s| Cell::new(s).add_attribute(Attribute::Bold))
+        .map(|s| match opts.no_color {
+            true => Cell::new(s),
+            false => Cell::new(s).add_attribute(Attribute::Bold),
+        })
         .collect();
     table.set_header(headers);
 
-    for i in 0..df.height() {
-        let row = df.get_row(i).unwrap();
-        let row_data: Vec<Cell> = row.0.iter().map(|val| format_cell(val)).collect();
+    for row in rows {
+        let row_data: Vec<Cell> = match row {
+            Some(i) => {
+                let row = df.get_row(i).unwrap();
+                row.0.iter().map(|val| format_cell(val, opts)).collect()
+            }
+            None => std::iter::once(Cell::new(&elision))
+                .chain((1..df.width()).map(|_| Cell::new("...")))
+                .collect(),
+        };
 
         table.add_row(row_data);
     }
 
-    println!("\n");
-    println!("{}", table);
-    println!("\n");
+    format!("\n\n{}\n\n\n", table)
 }
 
-fn format_cell(val: &polars::prelude::AnyValue) -> Cell {
+fn format_cell(val: &AnyValue, opts: &PrintOptions) -> Cell {
     match val {
         AnyValue::Int64(_)
         | AnyValue::Int32(_)
@@ -46,10 +290,178 @@ fn format_cell(val: &polars::prelude::AnyValue) -> Cell {
         | AnyValue::UInt64(_)
         | AnyValue::UInt32(_)
         | AnyValue::UInt16(_)
-        | AnyValue::UInt8(_)
-        | AnyValue::Float64(_)
-        | AnyValue::Float32(_) => Cell::new(val.to_string()).set_alignment(CellAlignment::Right),
+        | AnyValue::UInt8(_) => {
+            Cell::new(group_thousands(&val.to_string())).set_alignment(CellAlignment::Right)
+        }
+        AnyValue::Float64(_) | AnyValue::Float32(_) => {
+            let text = match (opts.precision, val.extract::<f64>()) {
+                (Some(precision), Some(f)) => format!("{:.*}", precision, f),
+                _ => val.to_string(),
+            };
+            Cell::new(text).set_alignment(CellAlignment::Right)
+        }
         AnyValue::String(s) => Cell::new(s.to_string()),
         _ => Cell::new(val.to_string()),
     }
 }
+
+/// Inserts a comma between each group of three digits, e.g. `-1234567` -> `-1,234,567`.
+fn group_thousands(digits: &str) -> String {
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", digits),
+    };
+    let mut out = String::from(sign);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::{Column, DataFrame};
+
+    #[test]
+    fn test_max_rows_elides_the_middle_of_large_frames() {
+        let ids: Vec<String> = (0..1000).map(|i| format!("row-{}", i)).collect();
+        let values: Vec<u64> = (0..1000).collect();
+        let df = DataFrame::new(vec![
+            Column::new("id".into(), ids),
+            Column::new("value".into(), values),
+        ])
+        .unwrap();
+
+        let opts = PrintOptions {
+            max_rows: Some(10),
+            ..Default::default()
+        };
+        let out = format_dataframe(&df, &opts);
+        let data_rows = out.lines().filter(|line| line.contains("row-")).count();
+        assert_eq!(data_rows, 10);
+        assert!(
+            out.contains("row-0 ") && out.contains("row-999 "),
+            "{}",
+            out
+        );
+        assert!(out.contains("990 rows omitted"), "{}", out);
+
+        let full = format_dataframe(&df, &PrintOptions::default());
+        assert_eq!(
+            full.lines().filter(|line| line.contains("row-")).count(),
+            1000
+        );
+    }
+
+    #[test]
+    fn test_counts_are_grouped_in_tables_but_not_in_csv() {
+        let mut df = DataFrame::new(vec![
+            Column::new("prefix".into(), &["2001:db8::/32"]),
+            Column::new("count".into(), &[1_234_567u64]),
+            Column::new("ratio".into(), &[0.123456f64]),
+        ])
+        .unwrap();
+
+        let opts = PrintOptions {
+            precision: Some(2),
+            ..Default::default()
+        };
+        let table = format_dataframe(&df, &opts);
+        assert!(table.contains("1,234,567"), "{}", table);
+        assert!(
+            table.contains("0.12") && !table.contains("0.123"),
+            "{}",
+            table
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sink.csv");
+        write_dataframe(&mut df, &path).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            csv.contains("1234567") && !csv.contains("1,234,567"),
+            "{}",
+            csv
+        );
+        assert!(csv.contains("0.123456"), "{}", csv);
+    }
+
+    #[test]
+    fn test_streamed_csv_has_a_header_and_one_line_per_row() {
+        let mut out = Vec::new();
+        let mut writer = RowWriter::new(&mut out, StreamFormat::Csv, &["address", "note"]).unwrap();
+        for i in 0..100_000 {
+            writer
+                .write_row(&[&format!("2001:db8::{:x}", i), "a"])
+                .unwrap();
+        }
+        writer.write_row(&["2001:db8::1", "x,\"y\""]).unwrap();
+        assert_eq!(writer.finish().unwrap(), 100_001);
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 100_002);
+        assert_eq!(lines[0], "address,note");
+        assert_eq!(lines[1], "2001:db8::0,a");
+        assert_eq!(lines[100_001], "2001:db8::1,\"x,\"\"y\"\"\"");
+    }
+
+    #[test]
+    fn test_appending_rejects_mismatched_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("append.csv");
+        let mut first =
+            DataFrame::new(vec![Column::new("address".into(), &["2001:db8::1"])]).unwrap();
+        let mut second =
+            DataFrame::new(vec![Column::new("address".into(), &["2001:db8::2"])]).unwrap();
+        let mut other =
+            DataFrame::new(vec![Column::new("saddr".into(), &["2001:db8::3"])]).unwrap();
+
+        append_dataframe(&mut first, &path).unwrap();
+        append_dataframe(&mut second, &path).unwrap();
+        let err = append_dataframe(&mut other, &path).unwrap_err();
+        let csv = std::fs::read_to_string(&path).unwrap();
+
+        assert_eq!(csv, "address\n2001:db8::1\n2001:db8::2\n");
+        assert!(matches!(err, CliError::InvalidArgument(_)), "{:?}", err);
+        assert!(err.to_string().contains("[address]"), "{}", err);
+    }
+
+    #[test]
+    fn test_no_color_prints_no_escape_sequences() {
+        let df = DataFrame::new(vec![
+            Column::new("address".into(), &["2001:db8::1", "2001:db8::2"]),
+            Column::new("count".into(), &[1u64, 2]),
+        ])
+        .unwrap();
+
+        // Style even though the test output is not a terminal
+        let format = |no_color| {
+            let mut table = Table::new();
+            table.enforce_styling();
+            let opts = PrintOptions {
+                no_color,
+                ..Default::default()
+            };
+            format_into(table, &df, &opts)
+        };
+
+        let styled = format(false);
+        assert!(styled.contains('\x1b'), "{:?}", styled);
+        let plain = format(true);
+        assert!(plain.contains("2001:db8::1"), "{}", plain);
+        assert!(!plain.contains('\x1b'), "{:?}", plain);
+    }
+
+    #[test]
+    fn test_group_thousands() {
+        assert_eq!(group_thousands("0"), "0");
+        assert_eq!(group_thousands("999"), "999");
+        assert_eq!(group_thousands("1000"), "1,000");
+        assert_eq!(group_thousands("-1234567"), "-1,234,567");
+    }
+}
@@ -1,53 +1,422 @@
+use hickory_resolver::TokioAsyncResolver;
+use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+use ipnet::{IpNet, Ipv6Net};
 use polars::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::net::Ipv6Addr;
-use std::path::PathBuf;
+use std::future::Future;
+use std::io::{BufRead, BufReader, Cursor, Read};
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use tracing::warn;
 
-pub fn open_csv_lazy(file: &PathBuf, field: &Option<String>) -> Result<LazyFrame, String> {
+use crate::error::CliError;
+use crate::runner::{DEFAULT_ADDRESS_COLUMN, InputFormat};
+
+/// Field separator and quote character used to read CSV input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub separator: u8,
+    pub quote_char: u8,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            separator: b',',
+            quote_char: b'"',
+        }
+    }
+}
+
+/// Parses a single-byte CSV delimiter or quote character, accepting `\t` or `tab`
+/// for a tab.
+pub fn parse_csv_char(input: &str) -> Result<u8, String> {
+    match input {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ => match input.as_bytes() {
+            [byte] if byte.is_ascii() => Ok(*byte),
+            _ => Err(format!(
+                "Invalid CSV character '{}': expected a single ASCII character such as ';' or \\t",
+                input
+            )),
+        },
+    }
+}
+
+/// File argument that reads standard input, as in `cat addrs.txt | rmap analyze - counts`.
+pub const STDIN_PATH: &str = "-";
+
+pub fn is_stdin(file: &Path) -> bool {
+    file.as_os_str() == STDIN_PATH
+}
+
+/// Opens `file` for reading line by line, or standard input for [`STDIN_PATH`].
+fn open_lines(file: &Path) -> Result<Box<dyn BufRead>, CliError> {
+    if is_stdin(file) {
+        return Ok(Box::new(std::io::stdin().lock()));
+    }
+    let reader =
+        File::open(file).map_err(|e| CliError::Io(format!("Failed to open input file: {}", e)))?;
+    Ok(Box::new(BufReader::new(reader)))
+}
+
+/// Opens a CSV lazily. A file whose first field is already an address (such as
+/// headerless zmap output) is read without a header, naming its first column `address`.
+pub fn open_csv_lazy(
+    file: &PathBuf,
+    fields: &[String],
+    csv: &CsvOptions,
+) -> Result<LazyFrame, CliError> {
+    let has_header = !starts_with_address(file, csv.separator)?;
     LazyCsvReader::new(file)
+        .with_separator(csv.separator)
+        .with_quote_char(Some(csv.quote_char))
         .with_infer_schema_length(Some(100))
-        .with_has_header(true)
+        .with_has_header(has_header)
         .with_chunk_size(10000)
         .finish()
-        .map_err(|e| format!("Failed to parse CSV file: {}", e))
-        .map(|lf| {
-            match field {
-                Some(field) => lf.select([col(field)]),
-                None => lf,
+        .map_err(|e| CliError::Parse(format!("Failed to parse CSV file: {}", e)))
+        .and_then(|lf| {
+            let lf = if has_header {
+                lf
+            } else {
+                lf.rename(["column_1"], [DEFAULT_ADDRESS_COLUMN], true)
+            };
+            select_fields(lf, fields)
+        })
+        .map(|lf| lf.with_new_streaming(true))
+}
+
+/// Selects `fields` from `lf` in the order given, or every column when none are named.
+/// Naming a column the input does not have is an error listing the ones it does.
+fn select_fields(mut lf: LazyFrame, fields: &[String]) -> Result<LazyFrame, CliError> {
+    if fields.is_empty() {
+        return Ok(lf);
+    }
+    let schema = lf
+        .collect_schema()
+        .map_err(|e| CliError::Parse(format!("Failed to read CSV header: {}", e)))?;
+    if let Some(missing) = fields.iter().find(|f| !schema.contains(f.as_str())) {
+        let available: Vec<&str> = schema.iter_names().map(|name| name.as_str()).collect();
+        return Err(CliError::Parse(format!(
+            "Column '{}' not found; available: {}",
+            missing,
+            available.join(", ")
+        )));
+    }
+    Ok(lf.select(fields.iter().map(|f| col(f.as_str())).collect::<Vec<_>>()))
+}
+
+/// Whether the first `separator`-delimited field of the first line parses as an IP address.
+fn starts_with_address(file: &PathBuf, separator: u8) -> Result<bool, CliError> {
+    let reader =
+        File::open(file).map_err(|e| CliError::Io(format!("Failed to open input file: {}", e)))?;
+    let mut first_line = String::new();
+    BufReader::new(reader)
+        .read_line(&mut first_line)
+        .map_err(|e| CliError::Io(format!("Failed to read input file: {}", e)))?;
+    Ok(first_field_is_address(&first_line, separator))
+}
+
+fn first_field_is_address(line: &str, separator: u8) -> bool {
+    let first_field = line.split(char::from(separator)).next().unwrap_or_default();
+    first_field.trim().parse::<IpAddr>().is_ok()
+}
+
+/// Loads the requested `fields` in the order given, or every column when none are named.
+/// Metrics such as `rtt_ms` and `ttl` keep their inferred numeric types, while `saddr`
+/// and `address` are always strings. With a `limit`, only the first `limit` rows are read.
+pub fn load_file(
+    file: &PathBuf,
+    fields: &[String],
+    limit: Option<usize>,
+    csv: &CsvOptions,
+) -> Result<DataFrame, CliError> {
+    let lf = open_csv_lazy(file, fields, csv)?;
+    collect_limited(lf, limit)
+        .map_err(|e| CliError::Parse(format!("Failed to read {}: {}", file.display(), e)))
+}
+
+/// Collects the first `limit` rows of `lf`, with `saddr` and `address` cast to strings.
+fn collect_limited(mut lf: LazyFrame, limit: Option<usize>) -> PolarsResult<DataFrame> {
+    if let Some(limit) = limit {
+        lf = lf.limit(limit as IdxSize);
+    }
+
+    let schema = lf.collect_schema()?;
+    let address_casts = schema
+        .iter_names()
+        .filter(|name| ["saddr", DEFAULT_ADDRESS_COLUMN].contains(&name.as_str()))
+        .map(|name| col(name.as_str()).cast(DataType::String))
+        .collect::<Vec<_>>();
+
+    lf.with_columns(address_casts).collect()
+}
+
+/// Like [`load_input`], but reads piped input from `reader`: standard input for a
+/// [`STDIN_PATH`] argument. IP lists are parsed line by line as they arrive, while CSV
+/// input is buffered in memory first.
+pub fn load_reader<R: BufRead>(
+    mut reader: R,
+    format: InputFormat,
+    fields: &[String],
+    limit: Option<usize>,
+    address_column: &str,
+    csv: &CsvOptions,
+) -> Result<DataFrame, CliError> {
+    let format = match format {
+        InputFormat::Auto => {
+            // Sniff from what is already buffered, without consuming it
+            let peeked = reader
+                .fill_buf()
+                .map_err(|e| CliError::Io(format!("Failed to read input: {}", e)))?;
+            match analyze::identify_format(peeked) {
+                Ok(analyze::Format::IpList) => InputFormat::IpList,
+                _ => InputFormat::Csv,
             }
-            .with_new_streaming(true)
+        }
+        format => format,
+    };
+    if format == InputFormat::IpList {
+        return read_ip_list(reader, limit, address_column);
+    }
+
+    let mut buf = Vec::new();
+    reader
+        .read_to_end(&mut buf)
+        .map_err(|e| CliError::Io(format!("Failed to read input: {}", e)))?;
+    let first_line = buf.split(|b| *b == b'\n').next().unwrap_or_default();
+    let has_header = !first_field_is_address(&String::from_utf8_lossy(first_line), csv.separator);
+    let df = CsvReadOptions::default()
+        .with_has_header(has_header)
+        .with_infer_schema_length(Some(100))
+        .map_parse_options(|opts| {
+            opts.with_separator(csv.separator)
+                .with_quote_char(Some(csv.quote_char))
         })
+        .into_reader_with_file_handle(Cursor::new(buf))
+        .finish()
+        .map_err(|e| CliError::Parse(format!("Failed to parse CSV input: {}", e)))?;
+
+    let mut lf = df.lazy();
+    if !has_header {
+        lf = lf.rename(["column_1"], [DEFAULT_ADDRESS_COLUMN], true);
+    }
+    let lf = select_fields(lf, fields)?;
+    let df = collect_limited(lf, limit)
+        .map_err(|e| CliError::Polars(format!("Failed to collect DataFrame: {}", e)))?;
+    match format {
+        InputFormat::ScanResult if fields.is_empty() => saddr_first(df),
+        _ => Ok(df),
+    }
 }
 
-pub fn load_file(file: &PathBuf, field: &Option<String>) -> DataFrame {
-    let mut lf = open_csv_lazy(file, field).unwrap();
-    let schema = lf.collect_schema().unwrap();
+/// Loads `file` as `format`, sniffing it with [`analyze::identify_format`] when `Auto`.
+/// IP lists become a single `address_column`; everything else is read as CSV, with
+/// the `saddr` column of scan results moved to the front unless `fields` are named.
+/// A directory loads every file in it, concatenated in name order.
+pub fn load_input(
+    file: &PathBuf,
+    format: InputFormat,
+    fields: &[String],
+    limit: Option<usize>,
+    address_column: &str,
+    csv: &CsvOptions,
+) -> Result<DataFrame, CliError> {
+    if is_stdin(file) {
+        let stdin = std::io::stdin().lock();
+        return load_reader(stdin, format, fields, limit, address_column, csv);
+    }
+    if file.is_dir() {
+        return load_directory(file, format, fields, limit, address_column, csv);
+    }
 
-    let mut names = Vec::new();
-    for (name, dtype) in schema.iter() {
-        if dtype == &DataType::String {
-            names.push(name.to_string());
+    let format = match format {
+        InputFormat::Auto => {
+            let reader = File::open(file)
+                .map_err(|e| CliError::Io(format!("Failed to open input file: {}", e)))?;
+            match analyze::identify_format(BufReader::new(reader)) {
+                Ok(analyze::Format::IpList) => InputFormat::IpList,
+                _ => InputFormat::Csv,
+            }
         }
+        format => format,
+    };
+
+    match format {
+        InputFormat::IpList => load_ip_list(file, limit, address_column),
+        InputFormat::ScanResult if fields.is_empty() => {
+            saddr_first(load_file(file, fields, limit, csv)?)
+        }
+        _ => load_file(file, fields, limit, csv),
     }
+}
 
-    let expr = names
-        .iter()
-        .map(|name| col(name.to_string()))
-        .collect::<Vec<_>>();
+/// Moves the `saddr` column of scan results to the front, where analyses read addresses.
+fn saddr_first(df: DataFrame) -> Result<DataFrame, CliError> {
+    let saddr = df
+        .column("saddr")
+        .map_err(|_| CliError::Parse("Scan result input has no saddr column".to_string()))?
+        .clone();
+    let mut columns = vec![saddr];
+    columns.extend(
+        df.get_columns()
+            .iter()
+            .filter(|c| c.name().as_str() != "saddr")
+            .cloned(),
+    );
+    DataFrame::new(columns)
+        .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
+}
+
+/// Stacks every non-hidden file in `dir`, which must all load with the same columns.
+fn load_directory(
+    dir: &PathBuf,
+    format: InputFormat,
+    fields: &[String],
+    limit: Option<usize>,
+    address_column: &str,
+    csv: &CsvOptions,
+) -> Result<DataFrame, CliError> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| CliError::Io(format!("Failed to read directory {}: {}", dir.display(), e)))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| !name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    files.sort();
+
+    let mut combined: Option<(PathBuf, DataFrame)> = None;
+    for file in files {
+        let df = load_input(&file, format, fields, limit, address_column, csv)?;
+        match &mut combined {
+            None => combined = Some((file, df)),
+            Some((first, stacked)) => {
+                if df.schema() != stacked.schema() {
+                    return Err(CliError::Parse(format!(
+                        "{} has columns {:?} but {} has {:?}; every file in a directory must share a schema",
+                        file.display(),
+                        df.schema(),
+                        first.display(),
+                        stacked.schema()
+                    )));
+                }
+                stacked.vstack_mut(&df).map_err(|e| {
+                    CliError::Polars(format!("Failed to concatenate {}: {}", file.display(), e))
+                })?;
+            }
+        }
+    }
+
+    let (_, mut df) = combined
+        .ok_or_else(|| CliError::Io(format!("No input files found in {}", dir.display())))?;
+    df.align_chunks_par();
+    Ok(match limit {
+        Some(limit) => df.head(Some(limit)),
+        None => df,
+    })
+}
+
+/// Reads one address per line into `address_column`. A first line that is not an
+/// address is taken to be a header and skipped; any later one is an error.
+fn load_ip_list(
+    file: &PathBuf,
+    limit: Option<usize>,
+    address_column: &str,
+) -> Result<DataFrame, CliError> {
+    read_ip_list(open_lines(file)?, limit, address_column)
+}
+
+fn read_ip_list<R: BufRead>(
+    reader: R,
+    limit: Option<usize>,
+    address_column: &str,
+) -> Result<DataFrame, CliError> {
+    let mut addresses = Vec::new();
+    for (i, addr) in analyze::IpListIterator::new(reader).enumerate() {
+        if limit.is_some_and(|limit| addresses.len() >= limit) {
+            break;
+        }
+        match addr {
+            Ok(addr) => addresses.push(addr.to_string()),
+            Err(_) if i == 0 => continue,
+            Err(e) => return Err(CliError::Parse(format!("Failed to read IP list: {}", e))),
+        }
+    }
+    DataFrame::new(vec![Series::new(address_column.into(), addresses).into()])
+        .map_err(|e| CliError::Polars(format!("Failed to create DataFrame: {}", e)))
+}
 
-    lf.select(expr).collect().unwrap()
+/// How many addresses `sample_ip_list` keeps.
+#[derive(Debug, Clone, Copy)]
+pub enum SampleSize {
+    /// Exactly this many (or every address, if the file has fewer)
+    Count(usize),
+    /// Each address independently with this probability
+    Fraction(f64),
 }
 
-pub fn load_ipv6_addresses_from_file(file: &PathBuf) -> Result<Vec<[u8; 16]>, String> {
-    let file = File::open(file).map_err(|e| format!("Failed to open input file: {}", e))?;
+/// Draws a uniform random sample from an IP list in one streaming pass, holding only
+/// the sample in memory (reservoir sampling for [`SampleSize::Count`]). The sample
+/// keeps file order, and the same `seed` always draws the same addresses.
+pub fn sample_ip_list(
+    file: &PathBuf,
+    size: SampleSize,
+    seed: Option<u64>,
+) -> Result<Vec<IpAddr>, CliError> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let reader = open_lines(file)?;
+
+    // Pairs of (position in file, address), so the sample can be put back in order
+    let mut sample: Vec<(usize, IpAddr)> = Vec::new();
+    let mut seen = 0;
+    for (i, addr) in analyze::IpListIterator::new(reader).enumerate() {
+        let addr = match addr {
+            Ok(addr) => addr,
+            Err(_) if i == 0 => continue,
+            Err(e) => return Err(CliError::Parse(format!("Failed to read IP list: {}", e))),
+        };
+        match size {
+            SampleSize::Count(n) if sample.len() < n => sample.push((seen, addr)),
+            SampleSize::Count(n) => {
+                let j = rng.gen_range(0..=seen);
+                if j < n {
+                    sample[j] = (seen, addr);
+                }
+            }
+            SampleSize::Fraction(p) => {
+                if rng.gen_bool(p) {
+                    sample.push((seen, addr));
+                }
+            }
+        }
+        seen += 1;
+    }
+
+    sample.sort_unstable_by_key(|(position, _)| *position);
+    Ok(sample.into_iter().map(|(_, addr)| addr).collect())
+}
+
+pub fn load_ipv6_addresses_from_file(file: &PathBuf) -> Result<Vec<[u8; 16]>, CliError> {
+    let file =
+        File::open(file).map_err(|e| CliError::Io(format!("Failed to open input file: {}", e)))?;
 
     let reader = BufReader::new(file);
     let mut addresses = Vec::new();
 
     for (line_num, line) in reader.lines().enumerate() {
-        let line = line.map_err(|e| format!("Failed to read line {}: {}", line_num + 1, e))?;
+        let line =
+            line.map_err(|e| CliError::Io(format!("Failed to read line {}: {}", line_num + 1, e)))?;
         let line = line.trim();
 
         if line.is_empty() || line.starts_with('#') {
@@ -55,25 +424,182 @@ pub fn load_ipv6_addresses_from_file(file: &PathBuf) -> Result<Vec<[u8; 16]>, St
         }
 
         let ip = Ipv6Addr::from_str(line).map_err(|e| {
-            format!(
+            CliError::Parse(format!(
                 "Failed to parse IPv6 address on line {}: {}",
                 line_num + 1,
                 e
-            )
+            ))
         })?;
 
         addresses.push(ip.octets());
     }
 
     if addresses.is_empty() {
-        return Err("No valid IPv6 addresses found in input file".to_string());
+        return Err(CliError::Parse(
+            "No valid IPv6 addresses found in input file".to_string(),
+        ));
     }
 
     Ok(addresses)
 }
 
-pub fn load_dataframe(file: &PathBuf) -> Result<DataFrame, String> {
-    let mut lf = open_csv_lazy(file, &None)?;
+/// Loads IPv6 allocation prefixes, either one CIDR per line or RIR delegation
+/// records (`registry|cc|ipv6|2001:db8::|32|date|status`). Non-IPv6 records are skipped.
+pub fn load_allocations(file: &PathBuf) -> Result<Vec<Ipv6Net>, CliError> {
+    let file = File::open(file)
+        .map_err(|e| CliError::Io(format!("Failed to open allocations file: {}", e)))?;
+
+    let mut allocations = Vec::new();
+    for (line_num, line) in BufReader::new(file).lines().enumerate() {
+        let line =
+            line.map_err(|e| CliError::Io(format!("Failed to read line {}: {}", line_num + 1, e)))?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let cidr = if line.contains('|') {
+            let fields: Vec<&str> = line.split('|').collect();
+            if fields.len() < 5 || fields[2] != "ipv6" || fields[3] == "*" {
+                continue;
+            }
+            format!("{}/{}", fields[3], fields[4])
+        } else {
+            line.to_string()
+        };
+
+        let net = Ipv6Net::from_str(&cidr).map_err(|e| {
+            CliError::Parse(format!(
+                "Failed to parse allocation '{}' on line {}: {}",
+                cidr,
+                line_num + 1,
+                e
+            ))
+        })?;
+        allocations.push(net);
+    }
+
+    if allocations.is_empty() {
+        return Err(CliError::Parse(
+            "No IPv6 allocations found in allocations file".to_string(),
+        ));
+    }
+
+    Ok(allocations)
+}
+
+/// Loads one network per line, as a CIDR or a bare address (a single-host network).
+/// Used for scan target, allowlist and blocklist files.
+pub fn load_networks(file: &PathBuf) -> Result<Vec<IpNet>, CliError> {
+    let reader = File::open(file)
+        .map_err(|e| CliError::Io(format!("Failed to open {}: {}", file.display(), e)))?;
+
+    let mut networks = Vec::new();
+    for (line_num, line) in BufReader::new(reader).lines().enumerate() {
+        let line =
+            line.map_err(|e| CliError::Io(format!("Failed to read line {}: {}", line_num + 1, e)))?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let net = match line.parse::<IpAddr>() {
+            Ok(addr) => IpNet::from(addr),
+            Err(_) => IpNet::from_str(line).map_err(|e| {
+                CliError::Parse(format!(
+                    "Failed to parse network '{}' on line {} of {}: {}",
+                    line,
+                    line_num + 1,
+                    file.display(),
+                    e
+                ))
+            })?,
+        };
+        networks.push(net);
+    }
+
+    Ok(networks)
+}
+
+/// Reads one hostname per line and resolves each to its AAAA records, returning the
+/// deduplicated addresses as training seeds. Hosts that fail to resolve are skipped
+/// with a warning.
+pub fn load_seeds_from_hostnames(file: &PathBuf) -> Result<Vec<[u8; 16]>, CliError> {
+    let reader = File::open(file)
+        .map_err(|e| CliError::Io(format!("Failed to open {}: {}", file.display(), e)))?;
+    let mut hostnames = Vec::new();
+    for (line_num, line) in BufReader::new(reader).lines().enumerate() {
+        let line =
+            line.map_err(|e| CliError::Io(format!("Failed to read line {}: {}", line_num + 1, e)))?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            hostnames.push(line.to_string());
+        }
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| CliError::Io(format!("Failed to start resolver runtime: {}", e)))?;
+    let seeds = runtime.block_on(async {
+        let resolver =
+            TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default());
+        resolve_seeds(&hostnames, |hostname| {
+            let resolver = resolver.clone();
+            async move {
+                let lookup = resolver
+                    .ipv6_lookup(hostname)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(lookup.iter().map(|aaaa| aaaa.0).collect())
+            }
+        })
+        .await
+    });
+
+    if seeds.is_empty() {
+        return Err(CliError::Io(format!(
+            "None of the {} hostnames in {} resolved to an IPv6 address",
+            hostnames.len(),
+            file.display()
+        )));
+    }
+    Ok(seeds)
+}
+
+/// Resolves `hostnames` concurrently with `resolve`, keeping the first occurrence of
+/// each address.
+pub async fn resolve_seeds<F, Fut>(hostnames: &[String], resolve: F) -> Vec<[u8; 16]>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Vec<Ipv6Addr>, String>>,
+{
+    let lookups = hostnames.iter().map(|hostname| {
+        let lookup = resolve(hostname.clone());
+        async move { (hostname, lookup.await) }
+    });
+
+    let mut seen = HashSet::new();
+    let mut seeds = Vec::new();
+    for (hostname, result) in futures::future::join_all(lookups).await {
+        match result {
+            Ok(addrs) => {
+                for addr in addrs {
+                    if seen.insert(addr) {
+                        seeds.push(addr.octets());
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to resolve {}: {}", hostname, e),
+        }
+    }
+    seeds
+}
+
+pub fn load_dataframe(file: &PathBuf) -> Result<DataFrame, CliError> {
+    let mut lf = open_csv_lazy(file, &[], &CsvOptions::default())?;
     let schema = lf.collect_schema().unwrap();
 
     let mut names = Vec::new();
@@ -88,5 +614,259 @@ pub fn load_dataframe(file: &PathBuf) -> Result<DataFrame, String> {
         .map(|name| col(name.to_string()))
         .collect::<Vec<_>>();
 
-    lf.select(expr).collect().map_err(|e| format!("Failed to collect DataFrame: {}", e))
+    lf.select(expr)
+        .collect()
+        .map_err(|e| CliError::Polars(format!("Failed to collect DataFrame: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_file_selects_multiple_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fields.csv");
+        std::fs::write(
+            &path,
+            "saddr,rtt,type\n2001:db8::1,12,129\n2001:db8::2,30,1\n",
+        )
+        .unwrap();
+
+        let df = load_file(
+            &path,
+            &["saddr".to_string(), "rtt".to_string()],
+            None,
+            &CsvOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(df.get_column_names_str(), ["saddr", "rtt"]);
+        assert_eq!(df.height(), 2);
+    }
+
+    #[test]
+    fn test_load_file_rejects_misspelled_field() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bad-field.csv");
+        std::fs::write(&path, "saddr,rtt_ms\n2001:db8::1,12\n").unwrap();
+
+        let csv = CsvOptions::default();
+        let fields = ["rttt".to_string()];
+        let err = load_file(&path, &fields, None, &csv).unwrap_err();
+        let piped = load_reader(
+            "saddr,rtt_ms\n2001:db8::1,12\n".as_bytes(),
+            InputFormat::Csv,
+            &fields,
+            None,
+            "address",
+            &csv,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, CliError::Parse(_)), "{:?}", err);
+        assert!(
+            err.to_string()
+                .contains("'rttt' not found; available: saddr, rtt_ms"),
+            "{}",
+            err
+        );
+        assert!(matches!(piped, CliError::Parse(_)), "{:?}", piped);
+    }
+
+    #[test]
+    fn test_load_file_keeps_first_row_of_headerless_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("headerless.csv");
+        std::fs::write(&path, "2001:db8::1,129\n2001:db8::2,1\n2001:db8::3,129\n").unwrap();
+
+        let df = load_file(&path, &[], None, &CsvOptions::default()).unwrap();
+
+        assert_eq!(df.get_column_names_str(), ["address", "column_2"]);
+        let addresses: Vec<&str> = df
+            .column("address")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(addresses, ["2001:db8::1", "2001:db8::2", "2001:db8::3"]);
+    }
+
+    #[test]
+    fn test_scan_results_keep_numeric_metrics() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metrics.csv");
+        std::fs::write(
+            &path,
+            "classification,saddr,rtt_ms,ttl\n\
+             echoreply,2001:db8::1,12.5,54\n\
+             echoreply,2001:db8::2,30.25,250\n",
+        )
+        .unwrap();
+
+        let csv = CsvOptions::default();
+        let df = load_input(&path, InputFormat::ScanResult, &[], None, "address", &csv).unwrap();
+
+        assert_eq!(
+            df.get_column_names_str(),
+            ["saddr", "classification", "rtt_ms", "ttl"]
+        );
+        assert_eq!(df.column("saddr").unwrap().dtype(), &DataType::String);
+        assert!(df.column("rtt_ms").unwrap().dtype().is_float());
+        assert!(df.column("ttl").unwrap().dtype().is_integer());
+    }
+
+    #[test]
+    fn test_semicolon_delimited_csv_with_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("semicolon.csv");
+        std::fs::write(
+            &path,
+            "saddr;rtt_ms;note\n\
+             2001:db8::1;12;\"a;b\"\n\
+             2001:db8::2;30;c\n",
+        )
+        .unwrap();
+
+        let csv = CsvOptions {
+            separator: parse_csv_char(";").unwrap(),
+            ..CsvOptions::default()
+        };
+        let df = load_input(&path, InputFormat::Csv, &[], None, "address", &csv).unwrap();
+
+        assert_eq!(df.get_column_names_str(), ["saddr", "rtt_ms", "note"]);
+        let notes: Vec<&str> = df
+            .column("note")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(notes, ["a;b", "c"]);
+    }
+
+    #[test]
+    fn test_load_reader_reads_piped_ip_lists_and_csv() {
+        let piped = Cursor::new("2001:db8::1\n2001:db8::2\n2001:db8::3\n");
+        let csv = CsvOptions::default();
+        let df = load_reader(piped, InputFormat::Auto, &[], Some(2), "address", &csv).unwrap();
+        let addresses: Vec<&str> = df
+            .column("address")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(addresses, ["2001:db8::1", "2001:db8::2"]);
+
+        let piped = Cursor::new("ttl,saddr\n64,2001:db8::1\n128,2001:db8::2\n");
+        let df = load_reader(piped, InputFormat::ScanResult, &[], None, "address", &csv).unwrap();
+        assert_eq!(df.get_column_names_str(), ["saddr", "ttl"]);
+        assert_eq!(
+            df.column("saddr").unwrap().str().unwrap().get(1),
+            Some("2001:db8::2")
+        );
+        assert!(is_stdin(Path::new("-")) && !is_stdin(Path::new("./-")));
+    }
+
+    #[test]
+    fn test_parse_csv_char() {
+        assert_eq!(parse_csv_char(";"), Ok(b';'));
+        assert_eq!(parse_csv_char("\\t"), Ok(b'\t'));
+        assert_eq!(parse_csv_char("\t"), Ok(b'\t'));
+        assert!(parse_csv_char(";;").is_err());
+        assert!(parse_csv_char("").is_err());
+    }
+
+    #[test]
+    fn test_forced_ip_list_overrides_misdetected_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ip-list.csv");
+        std::fs::write(
+            &path,
+            "hosts, seen from vantage A\n2001:db8::1\n2001:db8::2\n",
+        )
+        .unwrap();
+
+        let sniffed = analyze::identify_format(BufReader::new(File::open(&path).unwrap())).unwrap();
+        assert_eq!(sniffed, analyze::Format::ScanResult);
+
+        let csv = CsvOptions::default();
+        let df = load_input(&path, InputFormat::IpList, &[], None, "address", &csv).unwrap();
+
+        assert_eq!(df.get_column_names_str(), ["address"]);
+        let addresses: Vec<&str> = df
+            .column("address")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(addresses, ["2001:db8::1", "2001:db8::2"]);
+    }
+
+    #[test]
+    fn test_load_allocations_reads_cidrs_and_delegation_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("allocations.txt");
+        std::fs::write(
+            &path,
+            "2|apnic|20240101|3|19850701|20240101|+1000\n\
+             apnic|*|ipv6|*|2|summary\n\
+             apnic|JP|ipv4|192.0.2.0|256|20000101|allocated\n\
+             apnic|JP|ipv6|2001:db8::|32|19990813|allocated\n\
+             # manual entries\n\
+             2001:db8:1000::/36\n",
+        )
+        .unwrap();
+
+        let allocations = load_allocations(&path).unwrap();
+
+        assert_eq!(
+            allocations,
+            vec![
+                "2001:db8::/32".parse::<Ipv6Net>().unwrap(),
+                "2001:db8:1000::/36".parse::<Ipv6Net>().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_seeds_dedupes_and_skips_failures() {
+        let records: std::collections::HashMap<&str, Vec<Ipv6Addr>> = [
+            ("www.example.test", vec!["2001:db8::1".parse().unwrap()]),
+            (
+                "mail.example.test",
+                vec![
+                    "2001:db8::2".parse().unwrap(),
+                    "2001:db8::1".parse().unwrap(),
+                ],
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let hostnames: Vec<String> = [
+            "www.example.test",
+            "missing.example.test",
+            "mail.example.test",
+        ]
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+
+        let seeds = futures::executor::block_on(resolve_seeds(&hostnames, |hostname| {
+            let result = records
+                .get(hostname.as_str())
+                .cloned()
+                .ok_or_else(|| format!("no AAAA record for {}", hostname));
+            async move { result }
+        }));
+
+        let expected: Vec<[u8; 16]> = ["2001:db8::1", "2001:db8::2"]
+            .iter()
+            .map(|a| a.parse::<Ipv6Addr>().unwrap().octets())
+            .collect();
+        assert_eq!(seeds, expected);
+    }
 }
@@ -9,7 +9,7 @@ pub fn get_available_tga_names() -> Vec<&'static str> {
 }
 
 pub fn get_all_available_tga_names() -> Vec<&'static str> {
-    let mut names: Vec<&'static str> = vec!["entropy_ip", "random_ip"];
+    let mut names: Vec<&'static str> = vec!["entropy_ip", "low_byte", "random_ip", "random_prefix"];
     
     match tga::get_available_python_tga_infos() {
         Ok(python_tgas) => {
@@ -32,6 +32,15 @@ pub fn train_tga_by_name(name: &str, input_file: &PathBuf) -> Result<Box<dyn TGA
     tga::TgaRegistry::train_tga(name, addresses)
 }
 
+pub fn train_tga_from_hostnames(
+    name: &str,
+    hostnames_file: &PathBuf,
+) -> Result<Box<dyn TGA + Send + Sync>, String> {
+    let addresses = source::load_seeds_from_hostnames(hostnames_file)?;
+
+    tga::TgaRegistry::train_tga(name, addresses)
+}
+
 pub fn generate_tga(model_file: &PathBuf, count: usize, unique: bool) -> Result<(), String> {
     let model_data = std::fs::read(model_file)
         .map_err(|e| format!("Failed to read model file: {}", e))?;
@@ -41,7 +50,7 @@ pub fn generate_tga(model_file: &PathBuf, count: usize, unique: bool) -> Result<
     println!("Generating {} addresses{} using {}", count, if unique { " (unique)" } else { "" }, trained_model.name());
     
     if unique {
-        let addresses = trained_model.generate_unique(count);
+        let addresses = trained_model.generate_unique(count)?;
         for addr_bytes in addresses {
             let ip = Ipv6Addr::from(addr_bytes);
             println!("{}", ip);
@@ -0,0 +1,65 @@
+/// Parses a count with an optional `k`/`K` (10^3), `M` (10^6) or `G` (10^9) suffix,
+/// e.g. `500`, `10k`, `1.5M`.
+pub fn parse_count(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let (number, multiplier) = match input.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&input[..i], 1_000u64),
+        Some((i, 'M')) => (&input[..i], 1_000_000),
+        Some((i, 'G')) => (&input[..i], 1_000_000_000),
+        Some(_) => (input, 1),
+        None => return Err("Expected a count like 500, 10k, 1.5M or 2G".to_string()),
+    };
+
+    let invalid = || {
+        format!(
+            "Invalid count '{}': expected a number with an optional k, M or G suffix (e.g. 500, 10k, 1.5M)",
+            input
+        )
+    };
+
+    if let Ok(value) = number.parse::<u64>() {
+        return value.checked_mul(multiplier).ok_or_else(invalid);
+    }
+
+    let value = number.parse::<f64>().map_err(|_| invalid())?;
+    let scaled = value * multiplier as f64;
+    if !scaled.is_finite() || scaled < 0.0 || scaled.fract() != 0.0 || scaled > u64::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(scaled as u64)
+}
+
+pub fn parse_rate(input: &str) -> Result<u32, String> {
+    let count = parse_count(input)?;
+    u32::try_from(count)
+        .map_err(|_| format!("Rate '{}' exceeds the maximum of {}", input, u32::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_count_suffixes() {
+        assert_eq!(parse_count("500"), Ok(500));
+        assert_eq!(parse_count("10k"), Ok(10_000));
+        assert_eq!(parse_count("10K"), Ok(10_000));
+        assert_eq!(parse_count("1.5M"), Ok(1_500_000));
+        assert_eq!(parse_count("2G"), Ok(2_000_000_000));
+    }
+
+    #[test]
+    fn test_parse_count_rejects_malformed_values() {
+        assert!(parse_count("abc").is_err());
+        assert!(parse_count("").is_err());
+        assert!(parse_count("k").is_err());
+        assert!(parse_count("-5").is_err());
+        assert!(parse_count("1.0005k").is_err());
+    }
+
+    #[test]
+    fn test_parse_rate_bounds() {
+        assert_eq!(parse_rate("10k"), Ok(10_000));
+        assert!(parse_rate("5G").is_err());
+    }
+}
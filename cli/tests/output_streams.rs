@@ -0,0 +1,50 @@
+use std::net::Ipv6Addr;
+use std::process::Command;
+
+#[test]
+fn test_stdout_holds_only_result_rows() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rmap"))
+        .args(["generate", "--count", "5"])
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rows: Vec<&str> = stdout.lines().collect();
+    assert_eq!(rows.len(), 5, "unexpected stdout:\n{}", stdout);
+    for row in rows {
+        assert!(
+            row.parse::<Ipv6Addr>().is_ok(),
+            "non-address line on stdout: {}",
+            row
+        );
+    }
+
+    // The generation summary is still reported, just not on stdout
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Generated 5 addresses"), "{}", stderr);
+}
+
+#[test]
+fn test_log_file_receives_status_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("rmap.log");
+    let output = Command::new(env!("CARGO_BIN_EXE_rmap"))
+        .arg("--log")
+        .arg(&log)
+        .args(["generate", "--count", "5"])
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    let logged = std::fs::read_to_string(&log).unwrap();
+    assert!(output.status.success());
+
+    assert!(logged.contains("Generated 5 addresses"), "{}", logged);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("Generated 5 addresses"), "{}", stderr);
+}
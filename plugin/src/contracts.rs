@@ -76,36 +76,119 @@ pub trait MyField {
     const FIELD_NAME: &'static str;
     const FIELD_TYPE: &'static DataType;
 
-    fn from_any_value(any_value: AnyValue) -> Self;
-    fn to_any_value(&self) -> AnyValue;
+    /// Returns `None` for nulls and values that don't parse as `Self`.
+    fn from_any_value(any_value: AnyValue) -> Option<Self>
+    where
+        Self: Sized;
+    fn to_any_value(&self) -> AnyValue<'static>;
 }
 
 impl MyField for Ipv6Addr {
     const FIELD_NAME: &'static str = "ipv6";
     const FIELD_TYPE: &'static DataType = &DataType::String;
 
-    fn from_any_value(any_value: AnyValue) -> Self {
-        todo!()
+    fn from_any_value(any_value: AnyValue) -> Option<Self> {
+        match any_value {
+            AnyValue::String(s) => s.trim().parse().ok(),
+            AnyValue::StringOwned(s) => s.trim().parse().ok(),
+            _ => None,
+        }
     }
 
-    fn to_any_value(&self) -> AnyValue {
-        todo!()
+    fn to_any_value(&self) -> AnyValue<'static> {
+        AnyValue::StringOwned(self.to_string().into())
     }
 }
 
-pub trait AbsorbField<T: MyField> {
-    type Config;
+impl MyField for u8 {
+    const FIELD_NAME: &'static str = "u8";
+    const FIELD_TYPE: &'static DataType = &DataType::UInt8;
+
+    fn from_any_value(any_value: AnyValue) -> Option<Self> {
+        match any_value {
+            AnyValue::Null => None,
+            AnyValue::String(s) => s.trim().parse().ok(),
+            AnyValue::StringOwned(s) => s.trim().parse().ok(),
+            other => other.extract::<u8>(),
+        }
+    }
+
+    fn to_any_value(&self) -> AnyValue<'static> {
+        AnyValue::UInt8(*self)
+    }
+}
 
+/// Folds a stream of fields into a result. Options belong in the implementor's
+/// constructor, which keeps the trait object safe: `Box<dyn AbsorbField<T>>` works.
+pub trait AbsorbField<T: MyField> {
     fn absorb(&mut self, item: T);
     fn finalize(&mut self) -> DataFrame;
 
     fn absorb_series(&mut self, series: &Series) -> DataFrame {
         for item in series.iter() {
-            let item = item.cast(&T::FIELD_TYPE);
-            let item = T::from_any_value(item);
-            self.absorb(item);
+            let item = item.cast(T::FIELD_TYPE);
+            if let Some(item) = T::from_any_value(item) {
+                self.absorb(item);
+            }
         }
 
         self.finalize()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Collect(Vec<Ipv6Addr>);
+
+    impl AbsorbField<Ipv6Addr> for Collect {
+        fn absorb(&mut self, item: Ipv6Addr) {
+            self.0.push(item);
+        }
+
+        fn finalize(&mut self) -> DataFrame {
+            let addresses: Vec<String> = self.0.iter().map(|a| a.to_string()).collect();
+            DataFrame::new(vec![Column::new("address".into(), addresses)]).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_ipv6_any_value_round_trip() {
+        let addr: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let value = addr.to_any_value();
+        assert_eq!(Ipv6Addr::from_any_value(value), Some(addr));
+        assert_eq!(
+            Ipv6Addr::from_any_value(AnyValue::String("not an ip")),
+            None
+        );
+        assert_eq!(Ipv6Addr::from_any_value(AnyValue::Null), None);
+    }
+
+    #[test]
+    fn test_absorb_series_skips_invalid_values() {
+        let series = Series::new("address".into(), &["2001:db8::1", "bogus", "::1"]);
+        let mut collector = Collect(Vec::new());
+        let df = collector.absorb_series(&series);
+        assert_eq!(df.height(), 2);
+        assert_eq!(
+            collector.0,
+            vec![
+                "2001:db8::1".parse::<Ipv6Addr>().unwrap(),
+                "::1".parse::<Ipv6Addr>().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_absorb_field_works_as_a_trait_object() {
+        let mut boxed: Box<dyn AbsorbField<Ipv6Addr>> = Box::new(Collect(Vec::new()));
+        boxed.absorb("2001:db8::1".parse().unwrap());
+        boxed.absorb("2001:db8::2".parse().unwrap());
+        let df = boxed.finalize();
+        assert_eq!(
+            df.column("address").unwrap().str().unwrap().get(1),
+            Some("2001:db8::2")
+        );
+    }
+}
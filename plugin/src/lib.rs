@@ -1,21 +1,64 @@
-use clap::{ArgMatches, Command, CommandFactory};
+use clap::{ArgMatches, Command, FromArgMatches};
 use polars::prelude::*;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
 
 pub mod contracts;
+pub mod schemas;
+
+pub use schemas::FieldSpec;
+
+// Re-exported so `register_plugin!` expands in crates that don't depend on these directly
+#[doc(hidden)]
+pub use clap as __clap;
+#[doc(hidden)]
+pub use inventory as __inventory;
+#[doc(hidden)]
+pub use polars as __polars;
 
 pub type Result<T> = std::result::Result<T, PolarsError>;
 
+/// A command-line plugin; `Config` is parsed from the plugin's subcommand arguments.
+// Plugins are only driven through `DynPlugin`, which boxes the future, so it need not be `Send`
+#[allow(async_fn_in_trait)]
 pub trait Plugin<I, O>: Send + Sync + 'static {
     type Config: clap::Parser + Sized + Send + Sync + Default + 'static;
 
     async fn run(&self, cfg: Self::Config, input: I) -> Result<O>;
 }
 
+/// Object-safe form of a `Plugin<DataFrame, DataFrame>`, as stored in the registry.
+pub trait DynPlugin: Send + Sync {
+    /// Parses the plugin's config from its subcommand `matches` and runs it on `input`.
+    fn run_with_matches<'a>(
+        &'a self,
+        matches: &ArgMatches,
+        input: DataFrame,
+    ) -> Pin<Box<dyn Future<Output = Result<DataFrame>> + 'a>>;
+}
+
+impl<P: Plugin<DataFrame, DataFrame>> DynPlugin for P {
+    fn run_with_matches<'a>(
+        &'a self,
+        matches: &ArgMatches,
+        input: DataFrame,
+    ) -> Pin<Box<dyn Future<Output = Result<DataFrame>> + 'a>> {
+        let cfg = P::Config::from_arg_matches(matches);
+        Box::pin(async move {
+            let cfg = cfg.map_err(|e| PolarsError::InvalidOperation(e.to_string().into()))?;
+            self.run(cfg, input).await
+        })
+    }
+}
+
 pub struct PluginRegistration {
     pub name: &'static str,
     pub about: &'static str,
     pub parser: fn() -> Command,
-    pub factory: fn() -> &'static str,
+    pub factory: fn() -> Box<dyn DynPlugin>,
 }
 
 inventory::collect!(PluginRegistration);
@@ -35,43 +78,110 @@ pub fn attach_all_subcommands(app: Command) -> Command {
     iter().fold(app, |app, reg| app.subcommand((reg.parser)()))
 }
 
-fn temp_create_plugin() -> &'static str {
-    "temp"
-}
-
+/// Runs the registered plugin named by `matches`' subcommand on `df`, or returns
+/// `None` when the subcommand is not a plugin.
 pub async fn dispatch(matches: &ArgMatches, df: DataFrame) -> Result<Option<DataFrame>> {
     if let Some((sub, sub_m)) = matches.subcommand() {
         if let Some(reg) = lookup(sub) {
             let plugin = (reg.factory)();
-            todo!()
-
+            return plugin.run_with_matches(sub_m, df).await.map(Some);
         }
     }
     Ok(None)
 }
 
-#[macro_export]
-macro_rules! register_plugin {
-    ($ty:ty, $cfg:ty) => {
-        static PLUGIN_INSTANCE: $ty = <$ty>::default();
+struct ThreadWaker(Thread);
 
-        fn __factory() -> &'static dyn Plugin<DataFrame, DataFrame, ArgMatches> {
-            &PLUGIN_INSTANCE
-        }
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
 
-        fn __parser() -> Command {
-            <$cfg as clap::CommandFactory>::command()
-                .name(stringify!($ty))
-                .about("Plugin configuration")
+/// Drives a plugin future to completion on the current thread, without an async runtime.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
         }
+        thread::park();
+    }
+}
 
-        inventory::submit! {
-            $crate::PluginRegistration {
-                name:    stringify!($ty),
-                about:   "Plugin configuration",
-                parser:  __parser,
-                factory: __factory,
+/// Registers `$ty` (a `Default` `Plugin<DataFrame, DataFrame>`) as the subcommand `$name`.
+#[macro_export]
+macro_rules! register_plugin {
+    ($ty:ty, $name:expr, $about:expr) => {
+        const _: () = {
+            fn __factory() -> ::std::boxed::Box<dyn $crate::DynPlugin> {
+                ::std::boxed::Box::new(<$ty as ::std::default::Default>::default())
             }
-        }
+
+            fn __parser() -> $crate::__clap::Command {
+                <<$ty as $crate::Plugin<
+                    $crate::__polars::prelude::DataFrame,
+                    $crate::__polars::prelude::DataFrame,
+                >>::Config as $crate::__clap::CommandFactory>::command()
+                .name($name)
+                .about($about)
+            }
+
+            $crate::__inventory::submit! {
+                $crate::PluginRegistration {
+                    name: $name,
+                    about: $about,
+                    parser: __parser,
+                    factory: __factory,
+                }
+            }
+        };
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// Keeps the first `--rows` rows.
+    #[derive(Default)]
+    struct Head;
+
+    #[derive(Parser, Default)]
+    struct HeadCfg {
+        #[arg(long, default_value_t = 1)]
+        rows: usize,
+    }
+
+    impl Plugin<DataFrame, DataFrame> for Head {
+        type Config = HeadCfg;
+
+        async fn run(&self, cfg: HeadCfg, input: DataFrame) -> Result<DataFrame> {
+            Ok(input.head(Some(cfg.rows)))
+        }
+    }
+
+    register_plugin!(Head, "head", "Keep the first rows");
+
+    #[test]
+    fn test_registered_plugin_is_dispatched() {
+        let app = attach_all_subcommands(Command::new("rmap"));
+        let df =
+            DataFrame::new(vec![Column::new("address".into(), &["::1", "::2", "::3"])]).unwrap();
+
+        let matches = app
+            .try_get_matches_from(["rmap", "head", "--rows", "2"])
+            .unwrap();
+        let out = block_on(dispatch(&matches, df.clone())).unwrap().unwrap();
+        assert_eq!(out.height(), 2);
+
+        let matches = Command::new("rmap")
+            .subcommand(Command::new("other"))
+            .try_get_matches_from(["rmap", "other"])
+            .unwrap();
+        assert!(block_on(dispatch(&matches, df)).unwrap().is_none());
+    }
+}
@@ -1,4 +1,18 @@
-use arrow_array::{FixedSizeBinaryArray, Float64Array};
-use arrow_schema::DataType;
-use arrow_struct::ArrowStruct;
-use crate::{FieldSpec};
\ No newline at end of file
+use polars::prelude::DataType;
+
+/// A column a plugin reads or writes.
+pub struct FieldSpec {
+    pub name: &'static str,
+    pub dtype: DataType,
+    pub nullable: bool,
+}
+
+impl FieldSpec {
+    pub const fn new(name: &'static str, dtype: DataType, nullable: bool) -> Self {
+        Self {
+            name,
+            dtype,
+            nullable,
+        }
+    }
+}
@@ -1,10 +1,11 @@
-use pnet::packet::Packet;
 use pnet::packet::icmp::{self, IcmpPacket, IcmpTypes, echo_request::MutableEchoRequestPacket};
 use pnet::packet::icmpv6::{
     self, Icmpv6Code, Icmpv6Packet, Icmpv6Types,
     echo_request::MutableEchoRequestPacket as MutableIcmpv6EchoRequestPacket,
 };
-use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv6::MutableIpv6Packet;
+use pnet::packet::{MutablePacket, Packet};
 use pnet::transport::{TransportChannelType, TransportProtocol};
 use std::net::IpAddr;
 use std::time::Duration;
@@ -14,19 +15,114 @@ use std::time::Instant;
 
 use crate::Probe;
 
+/// An IPv6 extension header placed between the IPv6 header and the ICMPv6 message.
+/// Both kinds are emitted as a single 8-byte header holding only padding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionHeader {
+    HopByHop,
+    DestinationOptions,
+}
+
+impl ExtensionHeader {
+    pub const LEN: usize = 8;
+
+    fn protocol(self) -> IpNextHeaderProtocol {
+        match self {
+            ExtensionHeader::HopByHop => IpNextHeaderProtocols::Hopopt,
+            ExtensionHeader::DestinationOptions => IpNextHeaderProtocols::Ipv6Opts,
+        }
+    }
+
+    /// Writes the header with `next_header` as the protocol that follows it.
+    fn write(self, buffer: &mut [u8], next_header: IpNextHeaderProtocol) {
+        buffer[0] = next_header.0;
+        // Length in 8-octet units beyond the first 8
+        buffer[1] = 0;
+        // PadN option covering the remaining 4 bytes
+        buffer[2] = 1;
+        buffer[3] = 4;
+        buffer[4..Self::LEN].fill(0);
+    }
+}
+
+impl std::str::FromStr for ExtensionHeader {
+    type Err = String;
+
+    /// Parses `hop-by-hop` or `destination-options`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hop-by-hop" => Ok(ExtensionHeader::HopByHop),
+            "destination-options" => Ok(ExtensionHeader::DestinationOptions),
+            _ => Err(format!(
+                "Invalid extension header '{}'; expected hop-by-hop or destination-options",
+                s
+            )),
+        }
+    }
+}
+
+/// Writes an IPv6 header, followed by `extension_header` if given, for an ICMPv6
+/// message of `icmp_len` bytes from `source` to `target`. Returns how many bytes were
+/// written, which is where the ICMPv6 message goes.
+pub fn write_ipv6_header(
+    buffer: &mut [u8],
+    source: Ipv6Addr,
+    target: Ipv6Addr,
+    extension_header: Option<ExtensionHeader>,
+    icmp_len: usize,
+) -> Result<usize, String> {
+    let ext_len = extension_header.map_or(0, |_| ExtensionHeader::LEN);
+    let header_len = IPV6_HEADER_LEN + ext_len;
+    if ext_len + icmp_len > u16::MAX as usize {
+        return Err(format!(
+            "ICMPv6 message of {} bytes does not fit in an IPv6 packet",
+            icmp_len
+        ));
+    }
+    if buffer.len() < header_len + icmp_len {
+        return Err(format!(
+            "Buffer of {} bytes is too small for a {} byte probe",
+            buffer.len(),
+            header_len + icmp_len
+        ));
+    }
+
+    let (ip_header, rest) = buffer.split_at_mut(IPV6_HEADER_LEN);
+    let mut ip = MutableIpv6Packet::new(ip_header).unwrap();
+    ip.set_version(6);
+    ip.set_payload_length((ext_len + icmp_len) as u16);
+    ip.set_hop_limit(64);
+    ip.set_source(source);
+    ip.set_destination(target);
+    match extension_header {
+        Some(header) => {
+            ip.set_next_header(header.protocol());
+            header.write(&mut rest[..ext_len], IpNextHeaderProtocols::Icmpv6);
+        }
+        None => ip.set_next_header(IpNextHeaderProtocols::Icmpv6),
+    }
+    Ok(header_len)
+}
+
+const IPV6_HEADER_LEN: usize = 40;
+const ICMP_ECHO_HEADER_LEN: usize = 8;
+
 #[derive(Debug, Clone)]
 pub struct IcmpProbe {
     timeout_ms: u64,
     identifier: u16,
     payload_size: usize,
+    extension_header: Option<ExtensionHeader>,
 }
 
 impl Default for IcmpProbe {
     fn default() -> Self {
         Self {
             timeout_ms: 5000,
-            identifier: 0x1337,
+            // Random so probes from concurrent scans can be told apart
+            identifier: rand::random(),
             payload_size: 48,
+            extension_header: None,
         }
     }
 }
@@ -48,8 +144,72 @@ impl IcmpProbe {
             timeout_ms,
             identifier,
             payload_size,
+            ..Default::default()
         }
     }
+
+    /// Prepends `header` to ICMPv6 probes built with [`IcmpProbe::build_ipv6`] or
+    /// [`Probe::update`].
+    pub fn with_extension_header(mut self, header: ExtensionHeader) -> Self {
+        self.extension_header = Some(header);
+        self
+    }
+
+    fn fill_icmpv6_echo_request(
+        &self,
+        mut packet: MutableIcmpv6EchoRequestPacket<'_>,
+        source: Ipv6Addr,
+        target: Ipv6Addr,
+    ) {
+        packet.set_icmpv6_type(Icmpv6Types::EchoRequest);
+        packet.set_identifier(self.identifier);
+        packet.set_sequence_number(0);
+        packet.set_payload(&vec![0; self.payload_size]);
+
+        // The checksum covers the pseudo-header and the ICMPv6 message only, never
+        // any extension headers in front of it
+        let data = packet.packet();
+        let icmp = icmpv6::Icmpv6Packet::new(data).unwrap();
+        let checksum = icmpv6::checksum(&icmp, &source, &target);
+        packet.set_checksum(checksum);
+    }
+
+    pub fn identifier(&self) -> u16 {
+        self.identifier
+    }
+
+    pub fn payload_size(&self) -> usize {
+        self.payload_size
+    }
+
+    pub fn extension_header(&self) -> Option<ExtensionHeader> {
+        self.extension_header
+    }
+
+    /// Length of the IPv6 packet [`IcmpProbe::build_ipv6`] produces, and so the
+    /// smallest buffer that holds it or a reply echoing its payload.
+    pub fn ipv6_packet_len(&self) -> usize {
+        let ext_len = self.extension_header.map_or(0, |_| ExtensionHeader::LEN);
+        IPV6_HEADER_LEN + ext_len + ICMP_ECHO_HEADER_LEN + self.payload_size
+    }
+
+    /// Builds a complete IPv6 packet (header, optional extension header, ICMPv6 echo
+    /// request) into `buffer` for sending on a layer 3 channel. Returns its length.
+    pub fn build_ipv6(
+        &self,
+        buffer: &mut [u8],
+        source: Ipv6Addr,
+        target: Ipv6Addr,
+    ) -> Result<usize, String> {
+        let icmp_len = ICMP_ECHO_HEADER_LEN + self.payload_size;
+        let header_len =
+            write_ipv6_header(buffer, source, target, self.extension_header, icmp_len)?;
+        let total_len = header_len + icmp_len;
+
+        let icmp = MutableIcmpv6EchoRequestPacket::new(&mut buffer[header_len..total_len]).unwrap();
+        self.fill_icmpv6_echo_request(icmp, source, target);
+        Ok(total_len)
+    }
 }
 
 impl Probe<Ipv4Addr> for IcmpProbe {
@@ -82,30 +242,115 @@ impl Probe<Ipv4Addr> for IcmpProbe {
     }
 }
 
+/// Sends whole IPv6 packets, so any extension header goes out with the probe.
 impl Probe<Ipv6Addr> for IcmpProbe {
     const NAME: &'static str = "ICMPv6";
     const DESCRIPTION: &'static str = "ICMPv6 Echo Request probe for IPv6 hosts";
+    // IPPROTO_RAW: the kernel sends our IPv6 header as is (IPV6_HDRINCL)
     const CHANNEL_TYPE: TransportChannelType =
-        TransportChannelType::Layer4(TransportProtocol::Ipv6(IpNextHeaderProtocols::Icmpv6));
+        TransportChannelType::Layer4(TransportProtocol::Ipv6(IpNextHeaderProtocols::Reserved));
 
-    type Packet<'p> = MutableIcmpv6EchoRequestPacket<'p>;
+    type Packet<'p> = MutableIpv6Packet<'p>;
 
     fn init<'p>(buffer: &'p mut [u8]) -> Self::Packet<'p> {
         Self::Packet::new(buffer).unwrap()
     }
 
-    fn update<'p>(&'p self, mut packet: Self::Packet<'p>, source: Ipv6Addr, target: Ipv6Addr) -> Result<(), String> {
-        packet.set_identifier(self.identifier);
-        packet.set_sequence_number(0);
+    fn update<'p>(
+        &'p self,
+        mut packet: Self::Packet<'p>,
+        source: Ipv6Addr,
+        target: Ipv6Addr,
+    ) -> Result<(), String> {
+        self.build_ipv6(packet.packet_mut(), source, target)
+            .map(|_| ())
+    }
+}
 
-        let payload: [u8; 5] = [0; 5];
-        packet.set_payload(&payload);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet::packet::ipv6::Ipv6Packet;
 
-        let data = packet.packet();
-        let icmp = icmpv6::Icmpv6Packet::new(data).unwrap();
-        let checksum = icmpv6::checksum(&icmp, &source, &target);
-        packet.set_checksum(checksum);
+    #[test]
+    fn test_hop_by_hop_header_precedes_icmpv6() {
+        let source: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let target: Ipv6Addr = "2001:db8::2".parse().unwrap();
+        let probe = IcmpProbe::new().with_extension_header(ExtensionHeader::HopByHop);
 
-        Ok(())
+        let mut buffer = [0u8; 128];
+        let len = probe.build_ipv6(&mut buffer, source, target).unwrap();
+        let icmp_len = ICMP_ECHO_HEADER_LEN + probe.payload_size();
+        assert_eq!(len, IPV6_HEADER_LEN + ExtensionHeader::LEN + icmp_len);
+        assert_eq!(len, probe.ipv6_packet_len());
+
+        let ip = Ipv6Packet::new(&buffer[..len]).unwrap();
+        assert_eq!(ip.get_version(), 6);
+        assert_eq!(ip.get_next_header(), IpNextHeaderProtocols::Hopopt);
+        assert_eq!(
+            ip.get_payload_length() as usize,
+            ExtensionHeader::LEN + icmp_len
+        );
+        assert_eq!(ip.get_source(), source);
+        assert_eq!(ip.get_destination(), target);
+
+        let ext = &buffer[IPV6_HEADER_LEN..IPV6_HEADER_LEN + ExtensionHeader::LEN];
+        assert_eq!(ext, [IpNextHeaderProtocols::Icmpv6.0, 0, 1, 4, 0, 0, 0, 0]);
+
+        let icmp_bytes = &buffer[IPV6_HEADER_LEN + ExtensionHeader::LEN..len];
+        let icmp = Icmpv6Packet::new(icmp_bytes).unwrap();
+        assert_eq!(icmp.get_icmpv6_type(), Icmpv6Types::EchoRequest);
+        assert_eq!(
+            icmp.get_checksum(),
+            icmpv6::checksum(&icmp, &source, &target)
+        );
+    }
+
+    #[test]
+    fn test_update_sends_destination_options_header() {
+        let source: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let target: Ipv6Addr = "2001:db8::2".parse().unwrap();
+        let probe = IcmpProbe::new().with_extension_header(ExtensionHeader::DestinationOptions);
+
+        let mut buffer = [0u8; 128];
+        let packet = <IcmpProbe as Probe<Ipv6Addr>>::init(&mut buffer);
+        <IcmpProbe as Probe<Ipv6Addr>>::update(&probe, packet, source, target).unwrap();
+
+        let ip = Ipv6Packet::new(&buffer[..probe.ipv6_packet_len()]).unwrap();
+        assert_eq!(ip.get_next_header(), IpNextHeaderProtocols::Ipv6Opts);
+        assert_eq!(ip.get_destination(), target);
+        assert_eq!(ip.payload()[0], IpNextHeaderProtocols::Icmpv6.0);
+        let icmp = Icmpv6Packet::new(&ip.payload()[ExtensionHeader::LEN..]).unwrap();
+        assert_eq!(icmp.get_icmpv6_type(), Icmpv6Types::EchoRequest);
+        assert_eq!(
+            icmp.get_checksum(),
+            icmpv6::checksum(&icmp, &source, &target)
+        );
+
+        assert_eq!(
+            "hop-by-hop".parse::<ExtensionHeader>(),
+            Ok(ExtensionHeader::HopByHop)
+        );
+        assert!("routing".parse::<ExtensionHeader>().is_err());
+    }
+
+    #[test]
+    fn test_probe_without_extension_header() {
+        let source: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let target: Ipv6Addr = "2001:db8::2".parse().unwrap();
+
+        let mut buffer = [0u8; 128];
+        let len = IcmpProbe::new()
+            .build_ipv6(&mut buffer, source, target)
+            .unwrap();
+        let ip = Ipv6Packet::new(&buffer[..len]).unwrap();
+        assert_eq!(ip.get_next_header(), IpNextHeaderProtocols::Icmpv6);
+        assert_eq!(
+            len,
+            IPV6_HEADER_LEN + ICMP_ECHO_HEADER_LEN + IcmpProbe::new().payload_size()
+        );
+
+        let err = IcmpProbe::new().build_ipv6(&mut buffer[..20], source, target);
+        assert!(err.is_err());
     }
 }
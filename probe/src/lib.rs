@@ -4,6 +4,7 @@ use pnet::transport::{
     TransportChannelType, TransportProtocol, TransportReceiver, TransportSender, icmp_packet_iter,
     icmpv6_packet_iter,
 };
+use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use std::time::Duration;
 
@@ -11,9 +12,10 @@ mod icmp;
 mod tcp;
 mod udp;
 
-pub use icmp::IcmpProbe;
+pub use icmp::{ExtensionHeader, IcmpProbe, write_ipv6_header};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
 pub enum ProbeResult {
     Reachable {
         rtt_ms: u64,
@@ -30,6 +32,36 @@ pub enum ProbeResult {
     },
 }
 
+impl ProbeResult {
+    /// Lowercase name of the variant, as used in result tables.
+    pub fn status(&self) -> &'static str {
+        match self {
+            ProbeResult::Reachable { .. } => "reachable",
+            ProbeResult::Unreachable { .. } => "unreachable",
+            ProbeResult::Timeout { .. } => "timeout",
+            ProbeResult::Error { .. } => "error",
+        }
+    }
+
+    /// Round-trip time, for reachable targets only.
+    pub fn rtt_ms(&self) -> Option<u64> {
+        match self {
+            ProbeResult::Reachable { rtt_ms, .. } => Some(*rtt_ms),
+            _ => None,
+        }
+    }
+
+    /// Free-form explanation attached to the result, if any.
+    pub fn detail(&self) -> Option<&str> {
+        match self {
+            ProbeResult::Reachable { details, .. } => details.as_deref(),
+            ProbeResult::Unreachable { reason } => Some(reason),
+            ProbeResult::Timeout { .. } => None,
+            ProbeResult::Error { error } => Some(error),
+        }
+    }
+}
+
 pub trait Probe<T: Clone + Copy + Into<IpAddr>>: Default {
     const NAME: &'static str;
     const DESCRIPTION: &'static str;
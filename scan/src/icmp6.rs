@@ -1,9 +1,10 @@
 use pnet::packet::Packet;
 use pnet::packet::icmp::echo_request::MutableEchoRequestPacket;
-use pnet::packet::icmp::{self, IcmpTypes, MutableIcmpPacket};
+use pnet::packet::icmp::{self, IcmpPacket, IcmpTypes, MutableIcmpPacket};
 use pnet::packet::icmpv6::echo_request::MutableEchoRequestPacket as MutableIcmpv6EchoRequestPacket;
-use pnet::packet::icmpv6::{self, Icmpv6Types, MutableIcmpv6Packet};
+use pnet::packet::icmpv6::{self, Icmpv6Packet, Icmpv6Types, MutableIcmpv6Packet};
 use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv6::Ipv6Packet;
 use pnet::transport::{
     self, TransportChannelType, TransportProtocol, TransportReceiver, TransportSender,
     icmp_packet_iter, icmpv6_packet_iter,
@@ -11,8 +12,13 @@ use pnet::transport::{
 
 use metrics::{counter, gauge, histogram};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
-use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+
+use crate::state::ScanState;
 use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
 #[derive(Debug)]
 pub struct ProbeResult {
@@ -20,46 +26,108 @@ pub struct ProbeResult {
     pub rtt: Duration,
 }
 
-pub fn icmp4_scan(network: ipnet::Ipv4Net) -> Vec<ProbeResult> {
-    println!("Starting ICMPv4 scan of network: {}", network);
+impl From<&ProbeResult> for probe::ProbeResult {
+    fn from(result: &ProbeResult) -> Self {
+        probe::ProbeResult::Reachable {
+            rtt_ms: result.rtt.as_millis() as u64,
+            details: None,
+        }
+    }
+}
 
-    counter!("rmap_icmp4_scans_total", 1);
-    gauge!("rmap_active_icmp4_scans", 1.0);
+pub fn icmp4_scan(network: ipnet::Ipv4Net) -> Result<Vec<ProbeResult>, String> {
+    icmp4_scan_with_progress(network, &|_| {})
+}
 
-    let (mut ts, mut tr) = transport::transport_channel(
-        4096,
+/// Like [`icmp4_scan`], calling `on_progress` as probes go out and replies come in.
+pub fn icmp4_scan_with_progress(
+    network: ipnet::Ipv4Net,
+    on_progress: &dyn Fn(ScanProgress),
+) -> Result<Vec<ProbeResult>, String> {
+    info!("Starting ICMPv4 scan of network: {}", network);
+    let hosts: Vec<Ipv4Addr> = network.hosts().collect();
+    icmp4_scan_resumable(&hosts, on_progress, None, &EchoScanOptions::default())
+}
+
+/// Probes each of `hosts`, in order, skipping those already recorded in `state` and
+/// checkpointing progress to it as probes go out. See [`EchoScanOptions`] for the
+/// remaining settings.
+pub fn icmp4_scan_resumable(
+    hosts: &[Ipv4Addr],
+    on_progress: &dyn Fn(ScanProgress),
+    state: Option<&mut ScanState>,
+    options: &EchoScanOptions,
+) -> Result<Vec<ProbeResult>, String> {
+    let identifier = options.identifier;
+    let host_count = hosts.len();
+    info!(
+        "Starting ICMPv4 scan of {} targets (identifier {:#06x})",
+        host_count, identifier
+    );
+    if options.is_cancelled() {
+        info!("Scan cancelled before any probes were sent");
+        on_progress(ScanProgress {
+            sent: 0,
+            total: host_count,
+            replies: 0,
+        });
+        return Ok(Vec::new());
+    }
+
+    validate_buffer_size(options.buffer_size, options.payload.reply_len())?;
+    let (mut ts, mut tr) = open_transport_channel(
         TransportChannelType::Layer4(TransportProtocol::Ipv4(IpNextHeaderProtocols::Icmp)),
-    )
-    .expect("Failed to create transport channel");
+        options.buffer_size,
+    )?;
+
+    counter!("rmap_icmp4_scans_total", 1);
+    gauge!("rmap_active_icmp4_scans", 1.0);
 
     let (tx, rx) = std::sync::mpsc::channel();
+    let mut replies = Replies::new(rx);
 
     let receiver_thread = std::thread::spawn(move || {
-        icmp4_receiver_thread(&mut tr, tx);
+        icmp4_receiver_thread(&mut tr, tx, identifier);
     });
 
-    let source_ip = network.addr();
-    let hosts: Vec<Ipv4Addr> = network.hosts().collect();
-    let host_count = hosts.len();
-    println!("Sending {} ICMPv4 Echo Requests...", host_count);
+    info!("Sending up to {} ICMPv4 Echo Requests...", host_count);
 
     counter!("rmap_icmp4_hosts_total", host_count as u64);
 
-    for (i, host) in hosts.into_iter().enumerate() {
-        send_icmpv4_echo_request(&mut ts, source_ip, host);
-        std::thread::sleep(Duration::from_millis(20));
-
-        if (i + 1) % 50 == 0 {
-            println!("Sent {}/{} requests", i + 1, host_count);
-        }
-    }
+    let outcome = send_probes(
+        hosts,
+        state,
+        &options.cancel,
+        |_| false,
+        |host| {
+            send_icmpv4_echo_request(&mut ts, host, identifier, &options.payload);
+            std::thread::sleep(Duration::from_millis(20));
+        },
+        |reached| {
+            on_progress(ScanProgress {
+                sent: reached,
+                total: host_count,
+                replies: replies.collect(),
+            })
+        },
+    );
 
-    println!("All packets sent. Waiting for remaining responses...");
+    on_progress(ScanProgress {
+        sent: outcome.reached,
+        total: host_count,
+        replies: replies.collect(),
+    });
+    info!("All packets sent. Waiting for remaining responses...");
     drop(ts);
 
     receiver_thread.join().unwrap();
 
-    let results: Vec<ProbeResult> = rx.try_iter().collect();
+    on_progress(ScanProgress {
+        sent: outcome.reached,
+        total: host_count,
+        replies: replies.collect(),
+    });
+    let results = replies.results;
 
     counter!("rmap_icmp4_responses_total", results.len() as u64);
     if host_count > 0 {
@@ -68,122 +136,531 @@ pub fn icmp4_scan(network: ipnet::Ipv4Net) -> Vec<ProbeResult> {
     }
     gauge!("rmap_active_icmp4_scans", 0.0);
 
-    println!(
+    info!(
         "ICMPv4 scan complete. Found {} responsive hosts.",
         results.len()
     );
-    results
+    Ok(results)
+}
+
+/// Settings shared by [`icmp4_scan_resumable`] and [`icmp6_scan_resumable`].
+#[derive(Debug, Clone)]
+pub struct EchoScanOptions {
+    /// Receive buffer size in bytes; see [`validate_buffer_size`]
+    pub buffer_size: usize,
+    /// Echo identifier marking this scan's probes; replies without it are ignored
+    pub identifier: u16,
+    pub payload: EchoPayload,
+    /// IPv6 targets matching any of these predicates are never probed
+    pub exclude: Vec<fn(Ipv6Addr) -> bool>,
+    /// No further probes are sent once this is set
+    pub cancel: Arc<AtomicBool>,
+    /// Sent between the IPv6 header and each ICMPv6 echo request
+    pub extension_header: Option<probe::ExtensionHeader>,
+}
+
+impl Default for EchoScanOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: MAX_PACKET_SIZE,
+            identifier: random_identifier(),
+            payload: EchoPayload::default(),
+            exclude: Vec::new(),
+            cancel: Arc::default(),
+            extension_header: None,
+        }
+    }
+}
+
+impl EchoScanOptions {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+/// How far a scan has got, as passed to its `on_progress` callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanProgress {
+    /// Targets handled so far, whether probed or skipped
+    pub sent: usize,
+    pub total: usize,
+    /// Replies received so far
+    pub replies: usize,
+}
+
+/// Replies gathered from the receiver thread while probes are still going out.
+struct Replies {
+    rx: Receiver<ProbeResult>,
+    results: Vec<ProbeResult>,
+}
+
+impl Replies {
+    fn new(rx: Receiver<ProbeResult>) -> Self {
+        Self {
+            rx,
+            results: Vec::new(),
+        }
+    }
+
+    /// Takes every reply that has arrived so far, returning the running count.
+    fn collect(&mut self) -> usize {
+        self.results.extend(self.rx.try_iter());
+        self.results.len()
+    }
+}
+
+/// How far [`send_probes`] got through its targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SendOutcome {
+    /// Index of the first target not yet handled
+    reached: usize,
+    /// Targets handled without a probe because they were excluded
+    skipped: usize,
+}
+
+/// Calls `send` for each of `targets` from `state`'s checkpoint on, skipping those
+/// `skip` matches, and records progress in `state`, reporting it to `on_progress`
+/// every 50 targets. Stops early once `cancel` is set.
+fn send_probes<A: Copy>(
+    targets: &[A],
+    mut state: Option<&mut ScanState>,
+    cancel: &AtomicBool,
+    skip: impl Fn(A) -> bool,
+    mut send: impl FnMut(A),
+    mut on_progress: impl FnMut(usize),
+) -> SendOutcome {
+    let start = state.as_ref().map_or(0, |s| s.next_index() as usize);
+    if start > 0 {
+        info!("Resuming scan at target {}/{}", start, targets.len());
+    }
+
+    let mut outcome = SendOutcome {
+        reached: start.min(targets.len()),
+        skipped: 0,
+    };
+    for (i, target) in targets.iter().copied().enumerate().skip(start) {
+        if cancel.load(Ordering::Relaxed) {
+            info!("Scan cancelled after {}/{} targets", i, targets.len());
+            break;
+        }
+        // Skipped targets still count towards progress, so resumed scans line up
+        if skip(target) {
+            outcome.skipped += 1;
+        } else {
+            send(target);
+        }
+        outcome.reached = i + 1;
+
+        if let Some(state) = state.as_deref_mut() {
+            if let Err(e) = state.record(i as u64 + 1) {
+                warn!("{}", e);
+            }
+        }
+
+        if (i + 1) % 50 == 0 {
+            info!("Sent {}/{} requests", i + 1, targets.len());
+            on_progress(i + 1);
+        }
+    }
+
+    if let Some(state) = state.as_deref_mut() {
+        if let Err(e) = state.save() {
+            warn!("{}", e);
+        }
+    }
+    outcome
+}
+
+/// Largest IPv6 packet without a jumbo payload option: the fixed header plus the
+/// largest payload its 16-bit length field can describe.
+pub const MAX_PACKET_SIZE: usize = 40 + u16::MAX as usize;
+
+/// Default size of our echo request payloads: a send timestamp plus padding.
+pub const DEFAULT_PAYLOAD_SIZE: usize = 48;
+
+/// Bytes at the start of every payload holding the send timestamp.
+const TIMESTAMP_LEN: usize = 4;
+
+/// Largest echo request payload that fits a single IPv4 packet.
+pub const MAX_PAYLOAD_SIZE: usize = u16::MAX as usize - 20 - 8;
+
+/// What fills an echo request's payload after its send timestamp.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PayloadPattern {
+    #[default]
+    Zeros,
+    Ones,
+    /// 0x00, 0x01, ... wrapping after 0xff
+    Incrementing,
+    /// These bytes, repeated to fill the payload
+    Bytes(Vec<u8>),
+}
+
+impl PayloadPattern {
+    fn fill(&self, buf: &mut [u8]) {
+        match self {
+            PayloadPattern::Zeros => buf.fill(0),
+            PayloadPattern::Ones => buf.fill(0xff),
+            PayloadPattern::Incrementing => {
+                for (i, byte) in buf.iter_mut().enumerate() {
+                    *byte = i as u8;
+                }
+            }
+            PayloadPattern::Bytes(bytes) => {
+                for (byte, value) in buf.iter_mut().zip(bytes.iter().cycle()) {
+                    *byte = *value;
+                }
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for PayloadPattern {
+    type Err = String;
+
+    /// Parses `zeros`, `ones`, `incrementing`, or a hex string such as `deadbeef`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zeros" => return Ok(PayloadPattern::Zeros),
+            "ones" => return Ok(PayloadPattern::Ones),
+            "incrementing" => return Ok(PayloadPattern::Incrementing),
+            _ => {}
+        }
+
+        let hex = s.strip_prefix("0x").unwrap_or(s);
+        if hex.is_empty() || hex.len() % 2 != 0 || !hex.is_ascii() {
+            return Err(format!(
+                "Invalid payload pattern '{}'; expected zeros, ones, incrementing, or an even-length hex string",
+                s
+            ));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map(PayloadPattern::Bytes)
+            .map_err(|e| format!("Invalid hex in payload pattern '{}': {}", s, e))
+    }
+}
+
+/// The payload our echo requests carry: a send timestamp, then `pattern` up to `size` bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EchoPayload {
+    pattern: PayloadPattern,
+    size: usize,
+}
+
+impl Default for EchoPayload {
+    fn default() -> Self {
+        Self {
+            pattern: PayloadPattern::Zeros,
+            size: DEFAULT_PAYLOAD_SIZE,
+        }
+    }
+}
+
+impl EchoPayload {
+    /// `size` must leave room for the timestamp and fit an IPv4 packet.
+    pub fn new(pattern: PayloadPattern, size: usize) -> Result<Self, String> {
+        if !(TIMESTAMP_LEN..=MAX_PAYLOAD_SIZE).contains(&size) {
+            return Err(format!(
+                "Payload size {} is out of range; expected {} to {} bytes",
+                size, TIMESTAMP_LEN, MAX_PAYLOAD_SIZE
+            ));
+        }
+        Ok(Self { pattern, size })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Largest echo reply to probes carrying this payload: a maximal (60 byte) IPv4
+    /// header, which raw IPv4 sockets deliver, plus the ICMP header and echoed payload.
+    pub fn reply_len(&self) -> usize {
+        60 + 8 + self.size
+    }
+
+    fn bytes(&self, sent_ms: u32) -> Vec<u8> {
+        let mut payload = vec![0u8; self.size];
+        payload[..TIMESTAMP_LEN].copy_from_slice(&sent_ms.to_be_bytes());
+        self.pattern.fill(&mut payload[TIMESTAMP_LEN..]);
+        payload
+    }
+}
+
+/// A random echo identifier for one scan. Replies are matched on it, so scans
+/// running side by side on a host must not share one.
+pub fn random_identifier() -> u16 {
+    rand::random()
+}
+
+/// Checks a receive buffer of `buffer_size` bytes can hold a `packet_len` byte reply
+/// whole, so it is never truncated.
+pub fn validate_buffer_size(buffer_size: usize, packet_len: usize) -> Result<(), String> {
+    if buffer_size < packet_len {
+        return Err(format!(
+            "Transport buffer of {} bytes is smaller than the {} byte packets it must hold",
+            buffer_size, packet_len
+        ));
+    }
+    Ok(())
+}
+
+/// Opens a raw transport channel with a `buffer_size` byte receive buffer, explaining
+/// the usual cause when the OS refuses.
+pub fn open_transport_channel(
+    channel_type: TransportChannelType,
+    buffer_size: usize,
+) -> Result<(TransportSender, TransportReceiver), String> {
+    transport::transport_channel(buffer_size, channel_type).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::PermissionDenied {
+            format!(
+                "Raw socket creation failed ({}); run as root or grant CAP_NET_RAW (e.g. `sudo setcap cap_net_raw+ep <binary>`)",
+                e
+            )
+        } else {
+            format!("Failed to create transport channel: {}", e)
+        }
+    })
+}
+
+/// One read from a receiver's packet iterator, reduced to what the scan cares about.
+enum ReceiveEvent {
+    Reply(ProbeResult),
+    /// A packet that isn't one of our echo replies
+    Ignored,
+    TimedOut,
+    Closed,
+}
+
+/// Logs `event` and forwards replies to `tx`. Returns false once the receiver should stop.
+///
+/// Status goes through `tracing` rather than stdout so it never mixes with result output.
+fn handle_receive_event(event: ReceiveEvent, tx: &Sender<ProbeResult>) -> bool {
+    match event {
+        ReceiveEvent::Reply(result) => {
+            debug!(
+                "Received Echo Reply from {} (RTT: {:?})",
+                result.addr, result.rtt
+            );
+            tx.send(result).is_ok()
+        }
+        ReceiveEvent::Ignored => true,
+        ReceiveEvent::TimedOut => {
+            info!("Receiver timed out. Scan complete.");
+            false
+        }
+        ReceiveEvent::Closed => {
+            info!("Receiver channel closed. Exiting.");
+            false
+        }
+    }
+}
+
+/// Round-trip time from the send timestamp our probes carry in their payload.
+fn rtt_from_payload(payload: &[u8]) -> Option<Duration> {
+    let sent_time = u32::from_be_bytes(payload.get(0..4)?.try_into().ok()?);
+    let now = Instant::now().elapsed().as_millis() as u32;
+    Some(Duration::from_millis(now.saturating_sub(sent_time) as u64))
+}
+
+/// Round-trip time of `packet` if it is an echo reply to a probe sent with `identifier`.
+fn icmp4_reply_rtt(packet: &IcmpPacket, identifier: u16) -> Option<Duration> {
+    (packet.get_icmp_type() == IcmpTypes::EchoReply)
+        .then(|| icmp::echo_reply::EchoReplyPacket::new(packet.packet()))
+        .flatten()
+        .filter(|echo_reply| echo_reply.get_identifier() == identifier)
+        .and_then(|echo_reply| rtt_from_payload(echo_reply.payload()))
+}
+
+/// `packet` as an echo reply to a probe sent with `identifier`, or `None` for anything else.
+pub(crate) fn icmpv6_echo_reply<'p>(
+    packet: &'p Icmpv6Packet,
+    identifier: u16,
+) -> Option<icmpv6::echo_reply::EchoReplyPacket<'p>> {
+    (packet.get_icmpv6_type() == Icmpv6Types::EchoReply)
+        .then(|| icmpv6::echo_reply::EchoReplyPacket::new(packet.packet()))
+        .flatten()
+        .filter(|echo_reply| echo_reply.get_identifier() == identifier)
+}
+
+fn icmpv6_reply_rtt(packet: &Icmpv6Packet, identifier: u16) -> Option<Duration> {
+    icmpv6_echo_reply(packet, identifier)
+        .and_then(|echo_reply| rtt_from_payload(echo_reply.payload()))
 }
 
-fn icmp4_receiver_thread(tr: &mut TransportReceiver, tx: Sender<ProbeResult>) {
+fn icmp4_receiver_thread(tr: &mut TransportReceiver, tx: Sender<ProbeResult>, identifier: u16) {
     let mut iter = icmp_packet_iter(tr);
     loop {
-        match iter.next_with_timeout(Duration::from_secs(2)) {
+        let event = match iter.next_with_timeout(Duration::from_secs(2)) {
             Ok(Some((packet, addr))) => {
-                if packet.get_icmp_type() == IcmpTypes::EchoReply {
-                    if let Some(echo_reply) =
-                        icmp::echo_reply::EchoReplyPacket::new(packet.packet())
-                    {
-                        if echo_reply.get_identifier() == 0x1337 {
-                            if !echo_reply.payload().is_empty() {
-                                let sent_time = u32::from_be_bytes(
-                                    echo_reply.payload()[0..4].try_into().unwrap(),
-                                );
-                                let now = Instant::now().elapsed().as_millis() as u32;
-                                let rtt_ms = now.saturating_sub(sent_time);
-                                let rtt = Duration::from_millis(rtt_ms as u64);
-
-                                println!(
-                                    "Received ICMPv4 Echo Reply from {} (RTT: {:?})",
-                                    addr, rtt
-                                );
-
-                                let result = ProbeResult { addr, rtt };
-                                if tx.send(result).is_err() {
-                                    break;
-                                }
-                            }
-                        }
-                    }
+                let rtt = icmp4_reply_rtt(&packet, identifier);
+                match rtt {
+                    Some(rtt) => ReceiveEvent::Reply(ProbeResult { addr, rtt }),
+                    None => ReceiveEvent::Ignored,
                 }
             }
-            Ok(None) => {
-                println!("Receiver timed out. Scan complete.");
-                break;
-            }
-            Err(_) => {
-                println!("Receiver channel closed. Exiting.");
-                break;
-            }
+            Ok(None) => ReceiveEvent::TimedOut,
+            Err(_) => ReceiveEvent::Closed,
+        };
+        if !handle_receive_event(event, &tx) {
+            break;
         }
     }
 }
 
-fn send_icmpv4_echo_request(sender: &mut TransportSender, _source_ip: Ipv4Addr, dest_ip: Ipv4Addr) {
-    const PAYLOAD_SIZE: usize = 48;
-    let mut buffer = [0u8; 8 + PAYLOAD_SIZE];
+fn send_icmpv4_echo_request(
+    sender: &mut TransportSender,
+    dest_ip: Ipv4Addr,
+    identifier: u16,
+    payload: &EchoPayload,
+) {
+    let now = Instant::now().elapsed().as_millis() as u32;
+    let buffer = build_icmpv4_echo_request(identifier, &payload.bytes(now));
+    let icmp_packet = IcmpPacket::new(&buffer).unwrap();
+
+    if sender.send_to(icmp_packet, dest_ip.into()).is_err() {
+        warn!("Error sending ICMPv4 packet to {}", dest_ip);
+    }
+}
+
+/// An ICMPv4 echo request carrying `payload`, checksum included.
+fn build_icmpv4_echo_request(identifier: u16, payload: &[u8]) -> Vec<u8> {
+    let mut buffer = vec![0u8; 8 + payload.len()];
     let mut icmp_packet = MutableEchoRequestPacket::new(&mut buffer).unwrap();
 
     icmp_packet.set_icmp_type(IcmpTypes::EchoRequest);
-    icmp_packet.set_identifier(0x1337);
+    icmp_packet.set_identifier(identifier);
     icmp_packet.set_sequence_number(0);
+    icmp_packet.set_payload(payload);
 
-    let mut payload = [0u8; PAYLOAD_SIZE];
-    let now = Instant::now().elapsed().as_millis() as u32;
-    payload[0..4].copy_from_slice(&now.to_be_bytes());
-    icmp_packet.set_payload(&payload);
-
-    let mut csum_buffer = [0u8; 8 + PAYLOAD_SIZE];
-    csum_buffer.copy_from_slice(&icmp_packet.packet());
-    let csum_packet = icmp::MutableIcmpPacket::new(&mut csum_buffer).unwrap();
-    let checksum = icmp::checksum(&csum_packet.to_immutable());
+    let checksum = icmp::checksum(&IcmpPacket::new(icmp_packet.packet()).unwrap());
     icmp_packet.set_checksum(checksum);
+    buffer
+}
 
-    if sender.send_to(icmp_packet, dest_ip.into()).is_err() {
-        eprintln!("Error sending ICMPv4 packet to {}", dest_ip);
-    }
+pub fn icmp6_scan(network: ipnet::Ipv6Net) -> Result<Vec<ProbeResult>, String> {
+    icmp6_scan_with_progress(network, &|_| {})
 }
 
-pub fn icmp6_scan(network: ipnet::Ipv6Net) -> Vec<ProbeResult> {
-    println!("Starting ICMPv6 scan of network: {}", network);
+/// Like [`icmp6_scan`], calling `on_progress` as probes go out and replies come in.
+pub fn icmp6_scan_with_progress(
+    network: ipnet::Ipv6Net,
+    on_progress: &dyn Fn(ScanProgress),
+) -> Result<Vec<ProbeResult>, String> {
+    info!("Starting ICMPv6 scan of network: {}", network);
+    let hosts: Vec<Ipv6Addr> = network.hosts().collect();
+    icmp6_scan_resumable(&hosts, on_progress, None, &EchoScanOptions::default())
+}
+
+/// Probes each of `hosts`, in order, skipping those already recorded in `state` and
+/// checkpointing progress to it as probes go out. See [`EchoScanOptions`] for the
+/// remaining settings.
+pub fn icmp6_scan_resumable(
+    hosts: &[Ipv6Addr],
+    on_progress: &dyn Fn(ScanProgress),
+    state: Option<&mut ScanState>,
+    options: &EchoScanOptions,
+) -> Result<Vec<ProbeResult>, String> {
+    let identifier = options.identifier;
+    let host_count = hosts.len();
+    info!(
+        "Starting ICMPv6 scan of {} targets (identifier {:#06x})",
+        host_count, identifier
+    );
+    if options.is_cancelled() {
+        info!("Scan cancelled before any probes were sent");
+        on_progress(ScanProgress {
+            sent: 0,
+            total: host_count,
+            replies: 0,
+        });
+        return Ok(Vec::new());
+    }
+
+    validate_buffer_size(options.buffer_size, options.payload.reply_len())?;
+    let (mut ts, mut tr) = open_transport_channel(
+        TransportChannelType::Layer4(TransportProtocol::Ipv6(IpNextHeaderProtocols::Icmpv6)),
+        options.buffer_size,
+    )?;
 
     counter!("rmap_icmp6_scans_total", 1);
     gauge!("rmap_active_icmp6_scans", 1.0);
 
-    let (mut ts, mut tr) = transport::transport_channel(
-        4096,
-        TransportChannelType::Layer4(TransportProtocol::Ipv6(IpNextHeaderProtocols::Icmpv6)),
-    )
-    .expect("Failed to create transport channel");
+    // Extension headers need whole IPv6 packets, sent on their own header-included socket
+    let mut packet_sender = match options.extension_header {
+        Some(header) => {
+            let (sender, _) = open_transport_channel(
+                TransportChannelType::Layer4(TransportProtocol::Ipv6(
+                    IpNextHeaderProtocols::Reserved,
+                )),
+                options.buffer_size,
+            )?;
+            Some((sender, header))
+        }
+        None => None,
+    };
 
     let (tx, rx) = std::sync::mpsc::channel();
+    let mut replies = Replies::new(rx);
 
     let receiver_thread = std::thread::spawn(move || {
-        icmpv6_receiver_thread(&mut tr, tx);
+        icmpv6_receiver_thread(&mut tr, tx, identifier);
     });
 
-    let source_ip = network.addr();
-    let hosts: Vec<Ipv6Addr> = network.hosts().collect();
-    let host_count = hosts.len();
-    println!("Sending {} ICMPv6 Echo Requests...", host_count);
+    // The kernel computes the checksum of ICMPv6 sent on raw sockets (RFC 3542), so
+    // the pseudo-header source only feeds the value it overwrites
+    let source_ip = Ipv6Addr::UNSPECIFIED;
+    info!("Sending up to {} ICMPv6 Echo Requests...", host_count);
 
     counter!("rmap_icmp6_hosts_total", host_count as u64);
 
-    for (i, host) in hosts.into_iter().enumerate() {
-        send_icmpv6_echo_request(&mut ts, source_ip, host);
-        std::thread::sleep(Duration::from_millis(20));
+    let outcome = send_probes(
+        hosts,
+        state,
+        &options.cancel,
+        |host| is_excluded(host, &options.exclude),
+        |host| {
+            match packet_sender.as_mut() {
+                Some((sender, header)) => {
+                    send_icmpv6_echo_packet(sender, host, identifier, &options.payload, *header)
+                }
+                None => {
+                    send_icmpv6_echo_request(&mut ts, source_ip, host, identifier, &options.payload)
+                }
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        },
+        |reached| {
+            on_progress(ScanProgress {
+                sent: reached,
+                total: host_count,
+                replies: replies.collect(),
+            })
+        },
+    );
 
-        if (i + 1) % 50 == 0 {
-            println!("Sent {}/{} requests", i + 1, host_count);
-        }
+    on_progress(ScanProgress {
+        sent: outcome.reached,
+        total: host_count,
+        replies: replies.collect(),
+    });
+    if outcome.skipped > 0 {
+        info!("Skipped {} excluded targets", outcome.skipped);
+        counter!("rmap_icmp6_excluded_total", outcome.skipped as u64);
     }
-
-    println!("All packets sent. Waiting for remaining responses...");
+    info!("All packets sent. Waiting for remaining responses...");
     drop(ts);
 
     receiver_thread.join().unwrap();
 
-    let results: Vec<ProbeResult> = rx.try_iter().collect();
+    on_progress(ScanProgress {
+        sent: outcome.reached,
+        total: host_count,
+        replies: replies.collect(),
+    });
+    let results = replies.results;
 
     counter!("rmap_icmp6_responses_total", results.len() as u64);
     if host_count > 0 {
@@ -192,73 +669,461 @@ pub fn icmp6_scan(network: ipnet::Ipv6Net) -> Vec<ProbeResult> {
     }
     gauge!("rmap_active_icmp6_scans", 0.0);
 
-    println!(
+    info!(
         "ICMPv6 scan complete. Found {} responsive hosts.",
         results.len()
     );
-    results
+    Ok(results)
+}
+
+/// Whether `host` matches any `exclude` predicate, so it must not be probed.
+pub fn is_excluded(host: Ipv6Addr, exclude: &[fn(Ipv6Addr) -> bool]) -> bool {
+    exclude.iter().any(|matches| matches(host))
 }
 
-fn icmpv6_receiver_thread(tr: &mut TransportReceiver, tx: Sender<ProbeResult>) {
+fn icmpv6_receiver_thread(tr: &mut TransportReceiver, tx: Sender<ProbeResult>, identifier: u16) {
     let mut iter = icmpv6_packet_iter(tr);
     loop {
-        match iter.next_with_timeout(Duration::from_secs(2)) {
+        let event = match iter.next_with_timeout(Duration::from_secs(2)) {
             Ok(Some((packet, addr))) => {
-                if let Some(echo_reply) = icmpv6::echo_reply::EchoReplyPacket::new(packet.packet())
-                {
-                    if echo_reply.get_identifier() == 0x1337 {
-                        if !echo_reply.payload().is_empty() {
-                            let sent_time =
-                                u32::from_be_bytes(echo_reply.payload()[0..4].try_into().unwrap());
-                            let now = Instant::now().elapsed().as_millis() as u32;
-                            let rtt_ms = now.saturating_sub(sent_time);
-                            let rtt = Duration::from_millis(rtt_ms as u64);
-
-                            println!("Received ICMPv6 Echo Reply from {} (RTT: {:?})", addr, rtt);
-
-                            let result = ProbeResult {
-                                addr: addr.into(),
-                                rtt,
-                            };
-                            if tx.send(result).is_err() {
-                                break;
-                            }
-                        }
-                    }
+                let rtt = icmpv6_reply_rtt(&packet, identifier);
+                match rtt {
+                    Some(rtt) => ReceiveEvent::Reply(ProbeResult { addr, rtt }),
+                    None => ReceiveEvent::Ignored,
                 }
             }
-            Ok(None) => {
-                println!("Receiver timed out. Scan complete.");
-                break;
-            }
-            Err(_) => {
-                println!("Receiver channel closed. Exiting.");
-                break;
-            }
+            Ok(None) => ReceiveEvent::TimedOut,
+            Err(_) => ReceiveEvent::Closed,
+        };
+        if !handle_receive_event(event, &tx) {
+            break;
         }
     }
 }
 
-fn send_icmpv6_echo_request(sender: &mut TransportSender, source_ip: Ipv6Addr, dest_ip: Ipv6Addr) {
-    const PAYLOAD_SIZE: usize = 48;
-    let mut buffer = [0u8; 8 + PAYLOAD_SIZE];
-    let mut icmp_packet = MutableIcmpv6EchoRequestPacket::new(&mut buffer).unwrap();
+fn send_icmpv6_echo_request(
+    sender: &mut TransportSender,
+    source_ip: Ipv6Addr,
+    dest_ip: Ipv6Addr,
+    identifier: u16,
+    payload: &EchoPayload,
+) {
+    let now = Instant::now().elapsed().as_millis() as u32;
+    let buffer = build_icmpv6_echo_request(source_ip, dest_ip, identifier, &payload.bytes(now));
+    let icmp_packet = Icmpv6Packet::new(&buffer).unwrap();
 
-    icmp_packet.set_identifier(0x1337);
-    icmp_packet.set_sequence_number(0);
+    if sender.send_to(icmp_packet, dest_ip.into()).is_err() {
+        warn!("Error sending ICMPv6 packet to {}", dest_ip);
+    }
+}
 
-    let mut payload = [0u8; PAYLOAD_SIZE];
+/// Sends an echo request to `dest_ip` as a whole IPv6 packet with `header` in front
+/// of the ICMPv6 message, from the address the kernel would route it from.
+fn send_icmpv6_echo_packet(
+    sender: &mut TransportSender,
+    dest_ip: Ipv6Addr,
+    identifier: u16,
+    payload: &EchoPayload,
+    header: probe::ExtensionHeader,
+) {
     let now = Instant::now().elapsed().as_millis() as u32;
-    payload[0..4].copy_from_slice(&now.to_be_bytes());
-    icmp_packet.set_payload(&payload);
+    let packet = route_source(dest_ip).and_then(|source_ip| {
+        build_icmpv6_echo_packet(source_ip, dest_ip, identifier, &payload.bytes(now), header)
+    });
+    let packet = match packet {
+        Ok(packet) => packet,
+        Err(e) => {
+            warn!("Cannot build ICMPv6 packet to {}: {}", dest_ip, e);
+            return;
+        }
+    };
+
+    if sender
+        .send_to(Ipv6Packet::new(&packet).unwrap(), dest_ip.into())
+        .is_err()
+    {
+        warn!("Error sending ICMPv6 packet to {}", dest_ip);
+    }
+}
 
-    let mut csum_buffer = [0u8; 8 + PAYLOAD_SIZE];
-    csum_buffer.copy_from_slice(&icmp_packet.packet());
-    let csum_packet = icmpv6::MutableIcmpv6Packet::new(&mut csum_buffer).unwrap();
-    let checksum = icmpv6::checksum(&csum_packet.to_immutable(), &source_ip, &dest_ip);
+/// The address the kernel sends from to reach `dest`, found by connecting a UDP socket,
+/// which sends nothing. Header-included packets must name their source themselves.
+fn route_source(dest: Ipv6Addr) -> Result<Ipv6Addr, String> {
+    let socket = std::net::UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))
+        .map_err(|e| format!("Failed to open a routing socket: {}", e))?;
+    socket
+        .connect((dest, 9))
+        .map_err(|e| format!("No route to {}: {}", dest, e))?;
+    match socket.local_addr().map_err(|e| e.to_string())?.ip() {
+        IpAddr::V6(source) => Ok(source),
+        IpAddr::V4(source) => Err(format!("Route to {} leaves from IPv4 {}", dest, source)),
+    }
+}
+
+/// An IPv6 packet from `source_ip` to `dest_ip` holding `header` and then an ICMPv6
+/// echo request carrying `payload`. The checksum is ours to fill in, since the kernel
+/// sends header-included packets untouched.
+fn build_icmpv6_echo_packet(
+    source_ip: Ipv6Addr,
+    dest_ip: Ipv6Addr,
+    identifier: u16,
+    payload: &[u8],
+    header: probe::ExtensionHeader,
+) -> Result<Vec<u8>, String> {
+    let icmp = build_icmpv6_echo_request(source_ip, dest_ip, identifier, payload);
+    let mut buffer = vec![0u8; 40 + probe::ExtensionHeader::LEN + icmp.len()];
+    let header_len =
+        probe::write_ipv6_header(&mut buffer, source_ip, dest_ip, Some(header), icmp.len())?;
+    buffer[header_len..].copy_from_slice(&icmp);
+    Ok(buffer)
+}
+
+/// An ICMPv6 echo request from `source_ip` to `dest_ip` carrying `payload`, checksum
+/// included.
+fn build_icmpv6_echo_request(
+    source_ip: Ipv6Addr,
+    dest_ip: Ipv6Addr,
+    identifier: u16,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut buffer = vec![0u8; 8 + payload.len()];
+    let mut icmp_packet = MutableIcmpv6EchoRequestPacket::new(&mut buffer).unwrap();
+
+    icmp_packet.set_icmpv6_type(Icmpv6Types::EchoRequest);
+    icmp_packet.set_identifier(identifier);
+    icmp_packet.set_sequence_number(0);
+    icmp_packet.set_payload(payload);
+
+    let checksum = icmpv6::checksum(
+        &Icmpv6Packet::new(icmp_packet.packet()).unwrap(),
+        &source_ip,
+        &dest_ip,
+    );
     icmp_packet.set_checksum(checksum);
+    buffer
+}
 
-    if sender.send_to(icmp_packet, dest_ip.into()).is_err() {
-        eprintln!("Error sending ICMPv6 packet to {}", dest_ip);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    const CHILD_ENV: &str = "RMAP_SCAN_STDOUT_CHILD";
+
+    /// Feeds a mocked sequence of receiver events through the real handling code with
+    /// a debug-level subscriber on stderr, then prints only the collected results.
+    fn run_mocked_scan() {
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_writer(std::io::stderr)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let (tx, rx) = std::sync::mpsc::channel();
+            let events = [
+                ReceiveEvent::Reply(ProbeResult {
+                    addr: "2001:db8::1".parse().unwrap(),
+                    rtt: Duration::from_millis(3),
+                }),
+                ReceiveEvent::Ignored,
+                ReceiveEvent::Reply(ProbeResult {
+                    addr: "2001:db8::2".parse().unwrap(),
+                    rtt: Duration::from_millis(5),
+                }),
+                ReceiveEvent::TimedOut,
+            ];
+            for event in events {
+                if !handle_receive_event(event, &tx) {
+                    break;
+                }
+            }
+            drop(tx);
+            for result in rx {
+                println!("{}", result.addr);
+            }
+        });
+    }
+
+    #[test]
+    fn test_missing_privileges_is_an_error_not_a_panic() {
+        let channel_type =
+            TransportChannelType::Layer4(TransportProtocol::Ipv6(IpNextHeaderProtocols::Icmpv6));
+        // Only meaningful without CAP_NET_RAW; privileged runs have nothing to check
+        let Err(err) = open_transport_channel(channel_type, MAX_PACKET_SIZE) else {
+            return;
+        };
+        assert!(
+            err.contains("CAP_NET_RAW") || err.contains("Failed to create transport channel"),
+            "{}",
+            err
+        );
+
+        let err = icmp6_scan("::1/128".parse().unwrap()).unwrap_err();
+        assert!(!err.is_empty());
+    }
+
+    #[test]
+    fn test_large_payload_reply_fits_default_buffer() {
+        let source: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let target: Ipv6Addr = "2001:db8::2".parse().unwrap();
+        let probe = probe::IcmpProbe::with_settings(5000, random_identifier(), 8000)
+            .with_extension_header(probe::ExtensionHeader::HopByHop);
+        validate_buffer_size(MAX_PACKET_SIZE, probe.ipv6_packet_len()).unwrap();
+
+        // A reply echoes the request's payload, so read one back through a buffer of
+        // the default size and check nothing was cut off
+        let mut buffer = vec![0u8; MAX_PACKET_SIZE];
+        let len = probe.build_ipv6(&mut buffer, source, target).unwrap();
+        let packet = pnet::packet::ipv6::Ipv6Packet::new(&buffer[..len]).unwrap();
+        assert_eq!(packet.get_payload_length() as usize, len - 40);
+        assert_eq!(packet.payload().len(), 8 + 8 + 8000);
+
+        // The old fixed 4096 byte buffer would have truncated it
+        assert!(validate_buffer_size(4096, probe.ipv6_packet_len()).is_err());
+        assert!(validate_buffer_size(4096, EchoPayload::default().reply_len()).is_ok());
+    }
+
+    #[test]
+    fn test_extension_header_goes_out_with_echo_request() {
+        let source: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let target: Ipv6Addr = "2001:db8::2".parse().unwrap();
+        let payload = EchoPayload::default().bytes(0);
+
+        let packet = build_icmpv6_echo_packet(
+            source,
+            target,
+            0x1234,
+            &payload,
+            probe::ExtensionHeader::HopByHop,
+        )
+        .unwrap();
+        let ip = Ipv6Packet::new(&packet).unwrap();
+        assert_eq!(ip.get_next_header(), IpNextHeaderProtocols::Hopopt);
+        assert_eq!(ip.get_source(), source);
+        assert_eq!(ip.get_destination(), target);
+        assert_eq!(ip.get_payload_length() as usize, packet.len() - 40);
+
+        let ext = &packet[40..48];
+        assert_eq!(ext[0], IpNextHeaderProtocols::Icmpv6.0);
+        let icmp = Icmpv6Packet::new(&packet[48..]).unwrap();
+        assert_eq!(icmp.get_icmpv6_type(), Icmpv6Types::EchoRequest);
+        assert_eq!(
+            icmp.get_checksum(),
+            icmpv6::checksum(&icmp, &source, &target)
+        );
+        assert_eq!(&icmp.payload()[4..], payload.as_slice());
+    }
+
+    #[test]
+    fn test_custom_payload_pattern_survives_packet_building() {
+        let source: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let target: Ipv6Addr = "2001:db8::2".parse().unwrap();
+        let pattern: PayloadPattern = "0xdeadbeef".parse().unwrap();
+        assert_eq!(pattern, PayloadPattern::Bytes(vec![0xde, 0xad, 0xbe, 0xef]));
+        let payload = EchoPayload::new(pattern, 14).unwrap();
+
+        let packet = build_icmpv6_echo_request(source, target, 0x1234, &payload.bytes(7));
+        assert_eq!(packet.len(), 8 + 14);
+        assert_eq!(&packet[8..12], &7u32.to_be_bytes());
+        assert_eq!(
+            &packet[12..],
+            &[0xde, 0xad, 0xbe, 0xef, 0xde, 0xad, 0xbe, 0xef, 0xde, 0xad]
+        );
+        let parsed = Icmpv6Packet::new(&packet).unwrap();
+        assert_eq!(
+            parsed.get_checksum(),
+            icmpv6::checksum(&parsed, &source, &target)
+        );
+
+        let incrementing = EchoPayload::new(PayloadPattern::Incrementing, 8).unwrap();
+        let packet = build_icmpv4_echo_request(0x1234, &incrementing.bytes(0));
+        assert_eq!(&packet[12..], &[0, 1, 2, 3]);
+        let parsed = IcmpPacket::new(&packet).unwrap();
+        assert_eq!(parsed.get_checksum(), icmp::checksum(&parsed));
+
+        assert!("abc".parse::<PayloadPattern>().is_err());
+        assert!("zz".parse::<PayloadPattern>().is_err());
+        assert!(EchoPayload::new(PayloadPattern::Ones, 3).is_err());
+    }
+
+    #[test]
+    fn test_replies_are_counted_while_sending() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut replies = Replies::new(rx);
+        let reply = |addr: &str| ProbeResult {
+            addr: addr.parse().unwrap(),
+            rtt: Duration::from_millis(5),
+        };
+
+        let targets: Vec<u32> = (0..100).collect();
+        let mut progress = Vec::new();
+        send_probes(
+            &targets,
+            None,
+            &AtomicBool::new(false),
+            |_| false,
+            |t| {
+                if t < 60 {
+                    tx.send(reply(&format!("2001:db8::{:x}", t))).unwrap();
+                }
+            },
+            |sent| {
+                progress.push(ScanProgress {
+                    sent,
+                    total: targets.len(),
+                    replies: replies.collect(),
+                })
+            },
+        );
+        assert_eq!(
+            progress.iter().map(|p| p.replies).collect::<Vec<_>>(),
+            [50, 60]
+        );
+
+        tx.send(reply("2001:db8::ffff")).unwrap();
+        assert_eq!(replies.collect(), 61);
+        assert_eq!(
+            replies.results[60].addr,
+            "2001:db8::ffff".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cancelled_scans_send_nothing() {
+        let options = EchoScanOptions::default();
+        options.cancel.store(true, Ordering::Relaxed);
+        // Returns before opening a raw socket, so this holds with or without privileges
+        let hosts: Vec<Ipv6Addr> = "2001:db8::/120"
+            .parse::<ipnet::Ipv6Net>()
+            .unwrap()
+            .hosts()
+            .collect();
+        let results = icmp6_scan_resumable(
+            &hosts,
+            &|progress| {
+                assert_eq!(
+                    progress,
+                    ScanProgress {
+                        sent: 0,
+                        total: 256,
+                        replies: 0
+                    }
+                )
+            },
+            None,
+            &options,
+        )
+        .unwrap();
+        assert!(results.is_empty());
+
+        let targets: Vec<u32> = (0..10).collect();
+        let cancel = AtomicBool::new(true);
+        let mut sent = Vec::new();
+        let outcome = send_probes(&targets, None, &cancel, |_| false, |t| sent.push(t), |_| {});
+        assert!(sent.is_empty());
+        assert_eq!(outcome.reached, 0);
+
+        // Cancelling mid-scan stops before the next probe
+        let cancel = AtomicBool::new(false);
+        let outcome = send_probes(
+            &targets,
+            None,
+            &cancel,
+            |t| t % 2 == 1,
+            |t| {
+                sent.push(t);
+                if t == 4 {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            },
+            |_| {},
+        );
+        assert_eq!(sent, [0, 2, 4]);
+        assert_eq!(
+            outcome,
+            SendOutcome {
+                reached: 5,
+                skipped: 2
+            }
+        );
+    }
+
+    /// An ICMPv6 echo reply to a probe sent with `identifier`, as a receiver would see it.
+    fn simulated_icmpv6_reply(identifier: u16) -> Vec<u8> {
+        let mut buffer = vec![0u8; 8 + DEFAULT_PAYLOAD_SIZE];
+        let mut reply = icmpv6::echo_reply::MutableEchoReplyPacket::new(&mut buffer).unwrap();
+        reply.set_icmpv6_type(Icmpv6Types::EchoReply);
+        reply.set_identifier(identifier);
+        buffer
+    }
+
+    fn simulated_icmp4_reply(identifier: u16) -> Vec<u8> {
+        let mut buffer = vec![0u8; 8 + DEFAULT_PAYLOAD_SIZE];
+        let mut reply = icmp::echo_reply::MutableEchoReplyPacket::new(&mut buffer).unwrap();
+        reply.set_icmp_type(IcmpTypes::EchoReply);
+        reply.set_identifier(identifier);
+        buffer
+    }
+
+    #[test]
+    fn test_concurrent_scans_ignore_each_others_replies() {
+        let (first, second) = (0x1111, 0x2222);
+        let replies = [
+            simulated_icmpv6_reply(first),
+            simulated_icmpv6_reply(second),
+        ];
+        let accepted = |identifier: u16| -> Vec<usize> {
+            replies
+                .iter()
+                .enumerate()
+                .filter(|(_, reply)| {
+                    let packet = Icmpv6Packet::new(reply).unwrap();
+                    icmpv6_reply_rtt(&packet, identifier).is_some()
+                })
+                .map(|(i, _)| i)
+                .collect()
+        };
+        assert_eq!(accepted(first), [0]);
+        assert_eq!(accepted(second), [1]);
+
+        let reply = simulated_icmp4_reply(first);
+        let packet = IcmpPacket::new(&reply).unwrap();
+        assert!(icmp4_reply_rtt(&packet, first).is_some());
+        assert!(icmp4_reply_rtt(&packet, second).is_none());
+
+        // Our own echo requests, looped back, are not replies
+        let mut request = simulated_icmpv6_reply(first);
+        MutableIcmpv6Packet::new(&mut request)
+            .unwrap()
+            .set_icmpv6_type(Icmpv6Types::EchoRequest);
+        let packet = Icmpv6Packet::new(&request).unwrap();
+        assert!(icmpv6_reply_rtt(&packet, first).is_none());
+    }
+
+    #[test]
+    fn test_scan_status_stays_off_stdout() {
+        if std::env::var_os(CHILD_ENV).is_some() {
+            run_mocked_scan();
+            return;
+        }
+
+        // Re-run this test in a child process so its real stdout can be inspected
+        let output = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "icmp6::tests::test_scan_status_stays_off_stdout"])
+            .args(["--nocapture", "--test-threads=1", "--quiet"])
+            .env(CHILD_ENV, "1")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        let printed: Vec<&str> = stdout
+            .lines()
+            .filter(|line| line.starts_with("2001:db8::"))
+            .collect();
+        assert_eq!(printed, ["2001:db8::1", "2001:db8::2"]);
+        assert!(!stdout.contains("Received"), "{}", stdout);
+        assert!(!stdout.contains("timed out"), "{}", stdout);
+        assert!(stderr.contains("Receiver timed out"), "{}", stderr);
     }
 }
@@ -13,23 +13,61 @@ use ipnet::{IpNet, Ipv4Net, Ipv6Net};
 
 pub mod icmp6;
 pub mod link_local;
+pub mod state;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Scanner2 {
     max_active_probes: usize,
     new_probe_delay: Option<Duration>,
+    buffer_size: usize,
 }
 
 impl Scanner2 {
+    /// A scanner keeping at most `max_active_probes` probes in flight, sending new
+    /// ones as fast as that allows.
+    pub fn new(max_active_probes: usize) -> Self {
+        Self {
+            max_active_probes,
+            new_probe_delay: None,
+            buffer_size: icmp6::MAX_PACKET_SIZE,
+        }
+    }
+
+    /// Waits `delay` between starting consecutive probes.
+    pub fn with_probe_delay(mut self, delay: Duration) -> Self {
+        self.new_probe_delay = Some(delay);
+        self
+    }
+
+    /// Sets the send and receive buffer size; see [`icmp6::validate_buffer_size`].
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    pub fn max_active_probes(&self) -> usize {
+        self.max_active_probes
+    }
+
+    pub fn probe_delay(&self) -> Option<Duration> {
+        self.new_probe_delay
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    // Not public yet: it opens the channel but does not send or receive probes
     fn scan<A, T, I>(&self, settings: T, addrs: I)
     where
         A: Copy + Into<IpAddr>,
         T: Probe<A>,
         I: Iterator<Item = A>,
     {
-        let mut buffer = [0u8; 1024];
+        let mut buffer = vec![0u8; self.buffer_size];
         let mut packet = T::init(&mut buffer);
 
-        let (mut tx, mut rx) = transport_channel(100, T::CHANNEL_TYPE).unwrap();
+        let (mut tx, mut rx) = transport_channel(self.buffer_size, T::CHANNEL_TYPE).unwrap();
 
         for addr in addrs {
             let source = addr.clone();
@@ -37,3 +75,25 @@ impl Scanner2 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scanner_configuration_round_trips() {
+        let scanner = Scanner2::new(64);
+        assert_eq!(scanner.max_active_probes(), 64);
+        assert_eq!(scanner.probe_delay(), None);
+        assert_eq!(scanner.buffer_size(), icmp6::MAX_PACKET_SIZE);
+
+        let tuned = scanner
+            .clone()
+            .with_probe_delay(Duration::from_millis(20))
+            .with_buffer_size(9000);
+        assert_eq!(tuned.max_active_probes(), 64);
+        assert_eq!(tuned.probe_delay(), Some(Duration::from_millis(20)));
+        assert_eq!(tuned.buffer_size(), 9000);
+        assert_ne!(tuned, scanner);
+    }
+}
@@ -1,72 +1,206 @@
 use pnet::datalink::{self, NetworkInterface};
 use pnet::packet::Packet;
-use pnet::packet::icmpv6::echo_request::{self, MutableEchoRequestPacket};
-use pnet::packet::icmpv6::{self as icmpv6, Icmpv6Types, MutableIcmpv6Packet};
+use pnet::packet::icmpv6::echo_request::{EchoRequestPacket, MutableEchoRequestPacket};
+use pnet::packet::icmpv6::{self as icmpv6, Icmpv6Packet, Icmpv6Types};
 use pnet::packet::ip::IpNextHeaderProtocols;
-use pnet::transport::{self, TransportChannelType, TransportProtocol, icmpv6_packet_iter};
+use pnet::transport::{TransportChannelType, TransportProtocol, icmpv6_packet_iter};
 
+use analyze::analysis::predicates::reserved::LinkLocalPredicate;
 use metrics::{counter, gauge};
+use plugin::contracts::Predicate;
+use std::collections::{HashMap, HashSet};
 use std::net::{IpAddr, Ipv6Addr};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
-pub fn discover_ipv6_link_local(interface: &NetworkInterface) -> Result<Vec<Ipv6Addr>, String> {
-    let source_ipv6 = interface
+/// `/proc/net/if_inet6` flags that make an address unsuitable as a probe source.
+const IFA_F_DADFAILED: u32 = 0x08;
+const IFA_F_DEPRECATED: u32 = 0x20;
+const IFA_F_TENTATIVE: u32 = 0x40;
+const UNUSABLE_FLAGS: u32 = IFA_F_DADFAILED | IFA_F_DEPRECATED | IFA_F_TENTATIVE;
+
+/// Parses `/proc/net/if_inet6` into each address's flag bits.
+fn parse_if_inet6(contents: &str) -> HashMap<Ipv6Addr, u32> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let addr = u128::from_str_radix(fields.first()?, 16).ok()?;
+            let flags = u32::from_str_radix(fields.get(4)?, 16).ok()?;
+            Some((Ipv6Addr::from(addr), flags))
+        })
+        .collect()
+}
+
+/// Address flags as reported by the kernel; empty where `/proc/net/if_inet6` is unavailable.
+fn read_address_flags() -> HashMap<Ipv6Addr, u32> {
+    std::fs::read_to_string("/proc/net/if_inet6")
+        .map(|contents| parse_if_inet6(&contents))
+        .unwrap_or_default()
+}
+
+/// Picks the link-local (fe80::/10) address to send from, skipping addresses that are
+/// deprecated, tentative or failed duplicate address detection.
+fn select_link_local_source(
+    interface: &NetworkInterface,
+    address_flags: &HashMap<Ipv6Addr, u32>,
+) -> Result<Ipv6Addr, String> {
+    let v6_addrs: Vec<Ipv6Addr> = interface
         .ips
         .iter()
-        .find(|ip| {
-            ip.is_ipv6()
-                && match ip.ip() {
-                    IpAddr::V6(addr) => {
-                        addr.octets()[0] == 0xfe && (addr.octets()[1] & 0xc0) == 0x80
-                    }
-                    _ => false,
-                }
+        .filter_map(|ip| match ip.ip() {
+            IpAddr::V6(addr) => Some(addr),
+            IpAddr::V4(_) => None,
         })
-        .map(|ip| match ip.ip() {
-            IpAddr::V6(addr) => addr,
-            _ => unreachable!(),
+        .collect();
+
+    v6_addrs
+        .iter()
+        .copied()
+        .filter(|addr| LinkLocalPredicate.predicate(*addr))
+        .find(|addr| {
+            address_flags
+                .get(addr)
+                .is_none_or(|flags| flags & UNUSABLE_FLAGS == 0)
         })
         .ok_or_else(|| {
+            let addrs: Vec<String> = interface.ips.iter().map(|ip| ip.to_string()).collect();
             format!(
-                "No suitable IPv6 link-local address found on interface {}",
-                interface.name
+                "No usable IPv6 link-local address found on interface {} (addresses: {})",
+                interface.name,
+                if addrs.is_empty() {
+                    "none".to_string()
+                } else {
+                    addrs.join(", ")
+                }
             )
-        })?;
+        })
+}
+
+/// How long discovery listens for replies unless told otherwise.
+pub const DEFAULT_LISTEN_WINDOW: Duration = Duration::from_secs(5);
+
+/// How often the receiver checks whether discovery is over.
+const RECEIVE_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
+/// Waits up to `window` for replies to land in `hosts`, returning early once at least
+/// one host has replied and no new one has for a fifth of the window.
+fn wait_for_replies(hosts: &Mutex<HashSet<IpAddr>>, window: Duration) {
+    let quiet_period = window / 5;
+    let started = Instant::now();
+    let mut last_new_host = started;
+    let mut seen = 0;
+    while started.elapsed() < window {
+        let count = hosts.lock().unwrap().len();
+        if count > seen {
+            seen = count;
+            last_new_host = Instant::now();
+        } else if seen > 0 && last_new_host.elapsed() >= quiet_period {
+            debug!(
+                "No new hosts for {:?}, ending discovery early",
+                quiet_period
+            );
+            return;
+        }
+        thread::sleep(RECEIVE_POLL_INTERVAL.min(window.saturating_sub(started.elapsed())));
+    }
+}
+
+/// The echo request discovery sends from `source` with `identifier`, and the
+/// all-nodes multicast address it goes to.
+fn discovery_probe(source: Ipv6Addr, identifier: u16) -> (Vec<u8>, Ipv6Addr) {
+    const PAYLOAD_SIZE: usize = 48;
     let target_addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+    let mut buffer = vec![0u8; 8 + PAYLOAD_SIZE];
+    let mut icmp_packet = MutableEchoRequestPacket::new(&mut buffer).unwrap();
 
-    let (mut ts, mut tr) = transport::transport_channel(
-        4096,
+    icmp_packet.set_icmpv6_type(Icmpv6Types::EchoRequest);
+    icmp_packet.set_identifier(identifier);
+    icmp_packet.set_sequence_number(0);
+    icmp_packet.set_payload(&[0; PAYLOAD_SIZE]);
+
+    let checksum = icmpv6::checksum(
+        &Icmpv6Packet::new(icmp_packet.packet()).unwrap(),
+        &source,
+        &target_addr,
+    );
+    icmp_packet.set_checksum(checksum);
+    (buffer, target_addr)
+}
+
+/// Discovers hosts on `interface` answering a multicast echo request sent with
+/// `identifier`, listening for up to `listen_window`.
+pub fn discover_ipv6_link_local(
+    interface: &NetworkInterface,
+    identifier: u16,
+    listen_window: Duration,
+) -> Result<Vec<Ipv6Addr>, String> {
+    let source_ipv6 = select_link_local_source(interface, &read_address_flags())?;
+
+    let (probe, target_addr) = discovery_probe(source_ipv6, identifier);
+
+    let (mut ts, mut tr) = crate::icmp6::open_transport_channel(
         TransportChannelType::Layer4(TransportProtocol::Ipv6(IpNextHeaderProtocols::Icmpv6)),
-    )
-    .map_err(|e| format!("Failed to create transport channel: {}", e))?;
+        crate::icmp6::MAX_PACKET_SIZE,
+    )?;
 
-    println!("Using source address: {}", source_ipv6);
-    println!(
+    info!("Using source address: {}", source_ipv6);
+    info!(
         "Sending discovery packet to multicast address: {}",
         target_addr
     );
 
-    let discovered_hosts = Arc::new(Mutex::new(std::collections::HashSet::new()));
+    let next_reply = move |timeout| {
+        icmpv6_packet_iter(&mut tr)
+            .next_with_timeout(timeout)
+            .map(|reply| {
+                reply.and_then(|(packet, addr)| {
+                    Some((Icmpv6Packet::owned(packet.packet().to_vec())?, addr))
+                })
+            })
+    };
+    listen_for_replies(next_reply, identifier, listen_window, || {
+        let probe = EchoRequestPacket::owned(probe).unwrap();
+        ts.send_to(probe, IpAddr::V6(target_addr))
+            .map(|_| {
+                info!(
+                    "Discovery packet sent. Listening for replies for up to {:?}...",
+                    listen_window
+                );
+            })
+            .map_err(|_| "Failed to send discovery packet".to_string())
+    })
+}
+
+/// Receives with `next_reply` on a background thread, calls `send` to send the probe
+/// and waits out `listen_window` (see [`wait_for_replies`]). Returns the sorted hosts
+/// whose echo replies carried `identifier`.
+fn listen_for_replies<R, S>(
+    mut next_reply: R,
+    identifier: u16,
+    listen_window: Duration,
+    send: S,
+) -> Result<Vec<Ipv6Addr>, String>
+where
+    R: FnMut(Duration) -> std::io::Result<Option<(Icmpv6Packet<'static>, IpAddr)>> + Send + 'static,
+    S: FnOnce() -> Result<(), String>,
+{
+    let discovered_hosts = Arc::new(Mutex::new(HashSet::new()));
     let discovered_hosts_clone = Arc::clone(&discovered_hosts);
+    let done = Arc::new(AtomicBool::new(false));
+    let receiver_done = Arc::clone(&done);
 
     let receiver_thread = thread::spawn(move || {
-        let mut iter = icmpv6_packet_iter(&mut tr);
-        loop {
-            match iter.next_with_timeout(Duration::from_secs(1)) {
+        while !receiver_done.load(Ordering::Relaxed) {
+            match next_reply(RECEIVE_POLL_INTERVAL) {
                 Ok(Some((packet, addr))) => {
-                    if packet.get_icmpv6_type() == Icmpv6Types::EchoReply {
-                        if let Some(echo_reply) =
-                            icmpv6::echo_reply::EchoReplyPacket::new(packet.packet())
-                        {
-                            if echo_reply.get_identifier() == 0x1337 {
-                                println!("> Received reply from: {}", addr);
-                                let mut hosts = discovered_hosts_clone.lock().unwrap();
-                                hosts.insert(addr);
-                            }
-                        }
+                    if crate::icmp6::icmpv6_echo_reply(&packet, identifier).is_some() {
+                        debug!("Received reply from: {}", addr);
+                        let mut hosts = discovered_hosts_clone.lock().unwrap();
+                        hosts.insert(addr);
                     }
                 }
                 Ok(None) => continue,
@@ -75,30 +209,14 @@ pub fn discover_ipv6_link_local(interface: &NetworkInterface) -> Result<Vec<Ipv6
         }
     });
 
-    const PAYLOAD_SIZE: usize = 48;
-    let mut buffer = [0u8; 8 + PAYLOAD_SIZE];
-    let mut icmp_packet = MutableEchoRequestPacket::new(&mut buffer).unwrap();
-
-    icmp_packet.set_icmpv6_type(Icmpv6Types::EchoRequest);
-    icmp_packet.set_identifier(0x1337);
-    icmp_packet.set_sequence_number(0);
-    icmp_packet.set_payload(&[0; PAYLOAD_SIZE]);
-
-    let mut csum_buffer = [0u8; 8 + PAYLOAD_SIZE];
-    csum_buffer.copy_from_slice(&icmp_packet.packet());
-    let csum_packet = MutableIcmpv6Packet::new(&mut csum_buffer).unwrap();
-    let checksum = icmpv6::checksum(&csum_packet.to_immutable(), &source_ipv6, &target_addr);
-    icmp_packet.set_checksum(checksum);
-
-    if ts.send_to(icmp_packet, IpAddr::V6(target_addr)).is_err() {
-        return Err("Failed to send discovery packet".to_string());
+    if let Err(e) = send() {
+        done.store(true, Ordering::Relaxed);
+        return Err(e);
     }
 
-    println!("Discovery packet sent. Listening for replies for 5 seconds...");
-
-    thread::sleep(Duration::from_secs(5));
-
-
+    wait_for_replies(&discovered_hosts, listen_window);
+    done.store(true, Ordering::Relaxed);
+    let _ = receiver_thread.join();
 
     let hosts = discovered_hosts.lock().unwrap();
     let mut results: Vec<Ipv6Addr> = hosts
@@ -124,7 +242,41 @@ pub fn get_usable_interfaces() -> Vec<NetworkInterface> {
         .collect()
 }
 
-pub fn discover_all_ipv6_link_local() -> Result<Vec<Ipv6Addr>, String> {
+/// Runs `discover` on each of `interfaces` in its own thread, so every listen window
+/// overlaps, and merges the hosts they find. Interfaces that fail are logged and skipped.
+fn discover_on_interfaces<F>(interfaces: &[NetworkInterface], discover: F) -> Vec<Ipv6Addr>
+where
+    F: Fn(&NetworkInterface) -> Result<Vec<Ipv6Addr>, String> + Sync,
+{
+    let all_hosts = Mutex::new(HashSet::new());
+    thread::scope(|scope| {
+        for interface in interfaces {
+            let all_hosts = &all_hosts;
+            let discover = &discover;
+            scope.spawn(move || {
+                info!("Scanning interface: {}", interface.name);
+                match discover(interface) {
+                    Ok(hosts) => all_hosts.lock().unwrap().extend(hosts),
+                    Err(e) => {
+                        warn!("Failed to scan interface {}: {}", interface.name, e);
+                        counter!("rmap_link_local_interface_errors_total", 1);
+                    }
+                }
+            });
+        }
+    });
+
+    let mut results: Vec<Ipv6Addr> = all_hosts.into_inner().unwrap().into_iter().collect();
+    results.sort();
+    results
+}
+
+/// Runs [`discover_ipv6_link_local`] on every usable interface in parallel, matching
+/// replies on `identifier` and listening for up to `listen_window`.
+pub fn discover_all_ipv6_link_local(
+    identifier: u16,
+    listen_window: Duration,
+) -> Result<Vec<Ipv6Addr>, String> {
     counter!("rmap_link_local_discoveries_total", 1);
     gauge!("rmap_active_link_local_discoveries", 1.0);
 
@@ -135,28 +287,9 @@ pub fn discover_all_ipv6_link_local() -> Result<Vec<Ipv6Addr>, String> {
         return Err("No active network interfaces with IPv6 found.".to_string());
     }
 
-    let mut all_hosts = std::collections::HashSet::new();
-
-    for interface in interfaces {
-        println!("Scanning interface: {}", interface.name);
-        match discover_ipv6_link_local(&interface) {
-            Ok(hosts) => {
-                for host in hosts {
-                    all_hosts.insert(host);
-                }
-            }
-            Err(e) => {
-                println!(
-                    "Warning: Failed to scan interface {}: {}",
-                    interface.name, e
-                );
-                counter!("rmap_link_local_interface_errors_total", 1);
-            }
-        }
-    }
-
-    let mut results: Vec<Ipv6Addr> = all_hosts.into_iter().collect();
-    results.sort();
+    let results = discover_on_interfaces(&interfaces, |interface| {
+        discover_ipv6_link_local(interface, identifier, listen_window)
+    });
 
     counter!(
         "rmap_link_local_hosts_discovered_total",
@@ -166,3 +299,151 @@ pub fn discover_all_ipv6_link_local() -> Result<Vec<Ipv6Addr>, String> {
 
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet::ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network};
+    use pnet::packet::icmpv6::echo_reply::MutableEchoReplyPacket;
+
+    fn interface(addrs: &[&str]) -> NetworkInterface {
+        let ips = addrs
+            .iter()
+            .map(|addr| match addr.parse::<IpAddr>().unwrap() {
+                IpAddr::V4(v4) => IpNetwork::V4(Ipv4Network::new(v4, 24).unwrap()),
+                IpAddr::V6(v6) => IpNetwork::V6(Ipv6Network::new(v6, 64).unwrap()),
+            })
+            .collect();
+        NetworkInterface {
+            name: "eth0".to_string(),
+            description: String::new(),
+            index: 2,
+            mac: None,
+            ips,
+            flags: 0,
+        }
+    }
+
+    #[test]
+    fn test_selects_usable_link_local_source() {
+        let iface = interface(&["192.0.2.1", "2001:db8::1", "fe80::1", "febf::2", "fe80::3"]);
+        let flags = parse_if_inet6(
+            "fe800000000000000000000000000001 02 40 20 20 eth0\n\
+             febf0000000000000000000000000002 02 40 20 80 eth0\n\
+             fe800000000000000000000000000003 02 40 20 80 eth0\n",
+        );
+        assert_eq!(
+            flags[&"fe80::1".parse::<Ipv6Addr>().unwrap()],
+            IFA_F_DEPRECATED
+        );
+
+        // fe80::1 is deprecated, so the next link-local address in fe80::/10 wins
+        let source = select_link_local_source(&iface, &flags).unwrap();
+        assert_eq!(source, "febf::2".parse::<Ipv6Addr>().unwrap());
+
+        // Without kernel flags the first link-local address is used
+        let source = select_link_local_source(&iface, &HashMap::new()).unwrap();
+        assert_eq!(source, "fe80::1".parse::<Ipv6Addr>().unwrap());
+    }
+
+    #[test]
+    fn test_hosts_from_all_interfaces_are_merged() {
+        let interfaces: Vec<NetworkInterface> = ["eth0", "eth1", "wlan0"]
+            .into_iter()
+            .map(|name| NetworkInterface {
+                name: name.to_string(),
+                ..interface(&["fe80::1"])
+            })
+            .collect();
+        let host = |s: &str| s.parse::<Ipv6Addr>().unwrap();
+
+        let probed = Mutex::new(Vec::new());
+        let results = discover_on_interfaces(&interfaces, |iface| {
+            probed.lock().unwrap().push(iface.name.clone());
+            match iface.name.as_str() {
+                "eth0" => Ok(vec![host("fe80::b"), host("fe80::a")]),
+                "eth1" => Ok(vec![host("fe80::a"), host("fe80::c")]),
+                _ => Err("send failed".to_string()),
+            }
+        });
+        assert_eq!(results, [host("fe80::a"), host("fe80::b"), host("fe80::c")]);
+
+        // Every interface gets its own probe, even when another one fails
+        let mut probed = probed.into_inner().unwrap();
+        probed.sort();
+        assert_eq!(probed, ["eth0", "eth1", "wlan0"]);
+    }
+
+    #[test]
+    fn test_discovery_probe_goes_to_all_nodes() {
+        let source: Ipv6Addr = "fe80::1".parse().unwrap();
+        let (packet, destination) = discovery_probe(source, 7);
+        assert_eq!(destination, "ff02::1".parse::<Ipv6Addr>().unwrap());
+
+        let icmp = Icmpv6Packet::new(&packet).unwrap();
+        assert_eq!(icmp.get_icmpv6_type(), Icmpv6Types::EchoRequest);
+        assert_eq!(
+            icmp.get_checksum(),
+            icmpv6::checksum(&icmp, &source, &destination)
+        );
+        assert_eq!(EchoRequestPacket::new(&packet).unwrap().get_identifier(), 7);
+    }
+
+    #[test]
+    fn test_short_listen_window_returns_promptly() {
+        let hosts = Mutex::new(HashSet::new());
+        let started = Instant::now();
+        wait_for_replies(&hosts, Duration::from_millis(200));
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(200), "{:?}", elapsed);
+        assert!(elapsed < DEFAULT_LISTEN_WINDOW, "{:?}", elapsed);
+
+        // Once a host has replied, a quiet spell ends the window early
+        hosts.lock().unwrap().insert("fe80::1".parse().unwrap());
+        let started = Instant::now();
+        wait_for_replies(&hosts, Duration::from_secs(10));
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "{:?}",
+            started.elapsed()
+        );
+    }
+
+    #[test]
+    fn test_replies_are_returned_before_the_listen_window_ends() {
+        let reply = |identifier: u16| {
+            let mut buffer = vec![0u8; 8];
+            let mut packet = MutableEchoReplyPacket::new(&mut buffer).unwrap();
+            packet.set_icmpv6_type(Icmpv6Types::EchoReply);
+            packet.set_identifier(identifier);
+            Icmpv6Packet::owned(buffer).unwrap()
+        };
+        let host = |s: &str| s.parse::<Ipv6Addr>().unwrap();
+        let mut replies = vec![
+            (reply(7), IpAddr::V6(host("fe80::1"))),
+            (reply(8), IpAddr::V6(host("fe80::2"))),
+        ]
+        .into_iter();
+        let next_reply = move |timeout: Duration| {
+            thread::sleep(timeout.min(Duration::from_millis(50)));
+            Ok(replies.next())
+        };
+
+        let started = Instant::now();
+        let hosts = listen_for_replies(next_reply, 7, Duration::from_secs(10), || Ok(())).unwrap();
+        let elapsed = started.elapsed();
+        assert_eq!(hosts, [host("fe80::1")]);
+        assert!(elapsed < DEFAULT_LISTEN_WINDOW, "{:?}", elapsed);
+    }
+
+    #[test]
+    fn test_no_usable_link_local_source_lists_addresses() {
+        let iface = interface(&["2001:db8::1", "fe80::1"]);
+        let flags = parse_if_inet6("fe800000000000000000000000000001 02 40 20 40 eth0\n");
+
+        let err = select_link_local_source(&iface, &flags).unwrap_err();
+        assert!(err.contains("eth0"), "{}", err);
+        assert!(err.contains("2001:db8::1/64"), "{}", err);
+        assert!(err.contains("fe80::1/64"), "{}", err);
+    }
+}
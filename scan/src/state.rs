@@ -0,0 +1,114 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Tracks how far a scan has progressed through its target list so that an
+/// interrupted scan can pick up where it left off.
+///
+/// The state file holds the target specification on the first line and the
+/// index of the next unprobed target on the second.
+#[derive(Debug)]
+pub struct ScanState {
+    path: PathBuf,
+    target: String,
+    next_index: u64,
+    last_checkpoint: Instant,
+}
+
+impl ScanState {
+    /// Loads the state for `target` from `path`, or starts fresh if the file doesn't exist.
+    pub fn load_or_new(path: &Path, target: &str) -> Result<Self, String> {
+        let next_index = match fs::read_to_string(path) {
+            Ok(contents) => {
+                let mut lines = contents.lines();
+                let saved_target = lines.next().unwrap_or_default();
+                if saved_target != target {
+                    return Err(format!(
+                        "State file {} belongs to a scan of '{}', not '{}'",
+                        path.display(),
+                        saved_target,
+                        target
+                    ));
+                }
+                lines
+                    .next()
+                    .and_then(|line| line.trim().parse::<u64>().ok())
+                    .ok_or_else(|| format!("State file {} is corrupt", path.display()))?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read state file {}: {}",
+                    path.display(),
+                    e
+                ));
+            }
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            target: target.to_string(),
+            next_index,
+            last_checkpoint: Instant::now(),
+        })
+    }
+
+    pub fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    /// Skips the targets that a previous run already probed.
+    pub fn remaining<I: Iterator>(&self, targets: I) -> std::iter::Skip<I> {
+        targets.skip(self.next_index as usize)
+    }
+
+    /// Records that every target before `next_index` has been probed, writing a
+    /// checkpoint at most once per second.
+    pub fn record(&mut self, next_index: u64) -> Result<(), String> {
+        self.next_index = next_index;
+        if self.last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&mut self) -> Result<(), String> {
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, format!("{}\n{}\n", self.target, self.next_index))
+            .and_then(|_| fs::rename(&tmp_path, &self.path))
+            .map_err(|e| format!("Failed to write state file {}: {}", self.path.display(), e))?;
+        self.last_checkpoint = Instant::now();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_continues_from_checkpoint() {
+        let path = std::env::temp_dir().join(format!("rmap-scan-state-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let network: ipnet::Ipv6Net = "2001:db8::/120".parse().unwrap();
+        let target = network.to_string();
+
+        let mut state = ScanState::load_or_new(&path, &target).unwrap();
+        assert_eq!(state.next_index(), 0);
+        for (i, _) in state.remaining(network.hosts()).enumerate().take(100) {
+            state.record(i as u64 + 1).unwrap();
+        }
+        state.save().unwrap();
+        drop(state);
+
+        let resumed = ScanState::load_or_new(&path, &target).unwrap();
+        assert_eq!(resumed.next_index(), 100);
+        let next = resumed.remaining(network.hosts()).next().unwrap();
+        assert_eq!(next, network.hosts().nth(100).unwrap());
+
+        assert!(ScanState::load_or_new(&path, "2001:db8:1::/120").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,7 +1,8 @@
 use inventory;
+use rand::Rng;
 use rand::distributions::{Distribution, WeightedIndex};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::TGA;
 use plugin::contracts::PluginInfo;
@@ -19,61 +20,212 @@ pub struct Segment {
     pub values: Vec<SegmentValue>,
 }
 
+impl<'a> IntoIterator for &'a Segment {
+    type Item = &'a SegmentValue;
+    type IntoIter = std::slice::Iter<'a, SegmentValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Segment {
+    type Item = &'a mut SegmentValue;
+    type IntoIter = std::slice::IterMut<'a, SegmentValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter_mut()
+    }
+}
+
+/// Controls where `EntropyIpTga` splits addresses into segments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentationConfig {
+    /// Normalized nybble entropies; a segment boundary is placed where adjacent
+    /// nybbles fall on different sides of one of these values
+    pub thresholds: Vec<f64>,
+    /// Minimum entropy change between adjacent nybbles for a threshold crossing to split
+    pub hysteresis: f64,
+    /// Nybble indices that always start a new segment, regardless of entropy
+    pub forced_splits: Vec<usize>,
+}
+
+impl Default for SegmentationConfig {
+    fn default() -> Self {
+        Self {
+            thresholds: vec![0.025, 0.1, 0.3, 0.5, 0.9],
+            hysteresis: 0.05,
+            forced_splits: vec![8, 16],
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EntropyIpTga {
     segments: Vec<Segment>,
 }
 
+impl<'a> IntoIterator for &'a EntropyIpTga {
+    type Item = &'a Segment;
+    type IntoIter = std::slice::Iter<'a, Segment>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.segments.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut EntropyIpTga {
+    type Item = &'a mut Segment;
+    type IntoIter = std::slice::IterMut<'a, Segment>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.segments.iter_mut()
+    }
+}
+
 impl PluginInfo for EntropyIpTga {
     const NAME: &'static str = "entropy_ip";
     const DESCRIPTION: &'static str = "Entropy/IP algorithm for IPv6 address generation based on entropy analysis and segment mining";
 }
 
-#[typetag::serde]
+#[typetag::serde(name = "entropy_ip")]
 impl TGA for EntropyIpTga {
     fn train<T: IntoIterator<Item = [u8; 16]>>(seeds: T) -> Result<Self, String> {
-        let addresses: Vec<u128> = seeds
-            .into_iter()
-            .map(|bytes| {
-                let mut padded = [0u8; 16];
-                padded.copy_from_slice(&bytes);
-                u128::from_be_bytes(padded)
+        Self::train_with_config(seeds, &SegmentationConfig::default())
+    }
+
+    fn generate(&self) -> [u8; 16] {
+        self.generate_with_rng(&mut rand::thread_rng())
+    }
+
+    /// The product of each segment's distinct generatable values, counting a segment
+    /// without a usable distribution as its whole range.
+    fn capacity(&self) -> Option<u128> {
+        self.segments.iter().try_fold(1u128, |capacity, segment| {
+            let values: HashSet<u128> = segment
+                .values
+                .iter()
+                .filter(|v| v.probability > 0.0)
+                .map(|v| v.value)
+                .collect();
+            let distinct = match values.len() {
+                0 => nybble_mask(segment.end_nybble - segment.start_nybble + 1).checked_add(1)?,
+                n => n as u128,
+            };
+            capacity.checked_mul(distinct)
+        })
+    }
+
+    /// The product of the same per-segment values [`TGA::capacity`] counts, the last
+    /// segment varying fastest.
+    fn enumerate(&self) -> Option<Box<dyn Iterator<Item = [u8; 16]> + '_>> {
+        // (shift, distinct values or empty for the whole range, largest index)
+        let digits: Vec<(usize, Vec<u128>, u128)> = self
+            .segments
+            .iter()
+            .map(|segment| {
+                let mask = nybble_mask(segment.end_nybble - segment.start_nybble + 1);
+                let mut values: Vec<u128> = segment
+                    .values
+                    .iter()
+                    .filter(|v| v.probability > 0.0)
+                    .map(|v| v.value & mask)
+                    .collect();
+                values.sort_unstable();
+                values.dedup();
+                let last = match values.len() {
+                    0 => mask,
+                    n => n as u128 - 1,
+                };
+                ((31 - segment.end_nybble) * 4, values, last)
             })
             .collect();
 
+        let mut next = Some(vec![0u128; digits.len()]);
+        Some(Box::new(std::iter::from_fn(move || {
+            let mut indices = next.take()?;
+            let address =
+                digits
+                    .iter()
+                    .zip(&indices)
+                    .fold(0u128, |address, ((shift, values, _), &index)| {
+                        let value = match values.is_empty() {
+                            true => index,
+                            false => values[index as usize],
+                        };
+                        address | (value << shift)
+                    });
+
+            for (position, (_, _, last)) in digits.iter().enumerate().rev() {
+                if indices[position] < *last {
+                    indices[position] += 1;
+                    next = Some(indices);
+                    break;
+                }
+                indices[position] = 0;
+            }
+            Some(address.to_be_bytes())
+        })))
+    }
+
+    fn name(&self) -> &'static str {
+        Self::name_static()
+    }
+
+    fn description(&self) -> &'static str {
+        Self::description_static()
+    }
+}
+
+impl EntropyIpTga {
+    pub fn name_static() -> &'static str {
+        "entropy_ip"
+    }
+    pub fn description_static() -> &'static str {
+        "Entropy/IP algorithm for IPv6 address generation based on entropy analysis and segment mining"
+    }
+
+    /// Trains on `seeds`, splitting segments according to `config`.
+    pub fn train_with_config<T: IntoIterator<Item = [u8; 16]>>(
+        seeds: T,
+        config: &SegmentationConfig,
+    ) -> Result<Self, String> {
+        let addresses: Vec<u128> = seeds.into_iter().map(u128::from_be_bytes).collect();
+
         if addresses.is_empty() {
-            return Ok(EntropyIpTga { segments: vec![] });
+            return Err(crate::NO_SEEDS_ERROR.to_string());
         }
 
         let entropies = Self::calculate_entropies(&addresses);
 
-        let mut segments = Self::segment_addresses(&entropies, 16);
+        let mut segments = Self::segment_addresses(&entropies, 16, config);
 
         Self::mine_segments(&mut segments, &addresses);
 
         Ok(EntropyIpTga { segments })
     }
 
-    fn generate(&self) -> [u8; 16] {
-        let mut rng = rand::thread_rng();
+    /// Samples an address like [`TGA::generate`], drawing from `rng` so a seeded
+    /// generator gives the same sequence every run.
+    pub fn generate_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> [u8; 16] {
         let mut new_address: u128 = 0;
 
         for segment in &self.segments {
-            let probabilities: Vec<f64> = segment.values.iter().map(|v| v.probability).collect();
-            let Ok(dist) = WeightedIndex::new(&probabilities) else {
-                continue;
-            };
-
-            let chosen_index = dist.sample(&mut rng);
-            let chosen_value = segment.values[chosen_index].value;
-
             let num_nybbles_in_segment = segment.end_nybble - segment.start_nybble + 1;
             let total_nybbles = 16 * 2; // Total number of nybbles
             let shift = (total_nybbles - segment.end_nybble - 1) * 4;
+            let mask = nybble_mask(num_nybbles_in_segment);
+
+            let probabilities: Vec<f64> = segment.values.iter().map(|v| v.probability).collect();
+            let chosen_value = match WeightedIndex::new(&probabilities) {
+                Ok(dist) => segment.values[dist.sample(rng)].value,
+                // No usable distribution (no values, or no probability mass left after
+                // editing): fall back to a uniform draw over the segment's whole range
+                Err(_) => rng.gen_range(0..=mask),
+            };
 
-            let mask = (1u128 << (num_nybbles_in_segment * 4)) - 1;
             new_address &= !(mask << shift);
-            new_address |= chosen_value << shift;
+            new_address |= (chosen_value & mask) << shift;
         }
 
         let bytes = new_address.to_be_bytes();
@@ -82,21 +234,48 @@ impl TGA for EntropyIpTga {
         result
     }
 
-    fn name(&self) -> &'static str {
-        Self::name_static()
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
     }
 
-    fn description(&self) -> &'static str {
-        Self::description_static()
+    /// Mutable access to the trained segments; call [`EntropyIpTga::normalize_probabilities`]
+    /// after editing their values.
+    pub fn segments_mut(&mut self) -> &mut [Segment] {
+        &mut self.segments
     }
-}
 
-impl EntropyIpTga {
-    pub fn name_static() -> &'static str {
-        "entropy_ip"
-    }
-    pub fn description_static() -> &'static str {
-        "Entropy/IP algorithm for IPv6 address generation based on entropy analysis and segment mining"
+    /// Rescales each segment's value probabilities to sum to 1.0. Fails without changing
+    /// anything if a probability is negative or not finite, or a segment with values
+    /// has no probability mass left.
+    pub fn normalize_probabilities(&mut self) -> Result<(), String> {
+        let mut totals = Vec::with_capacity(self.segments.len());
+        for segment in &self.segments {
+            if let Some(invalid) = segment
+                .values
+                .iter()
+                .find(|v| !v.probability.is_finite() || v.probability < 0.0)
+            {
+                return Err(format!(
+                    "Segment {}-{} value {:#x} has invalid probability {}",
+                    segment.start_nybble, segment.end_nybble, invalid.value, invalid.probability
+                ));
+            }
+            let total: f64 = segment.values.iter().map(|v| v.probability).sum();
+            if !segment.values.is_empty() && total <= 0.0 {
+                return Err(format!(
+                    "Segment {}-{} has no probability mass to normalize",
+                    segment.start_nybble, segment.end_nybble
+                ));
+            }
+            totals.push(total);
+        }
+
+        for (segment, total) in self.segments.iter_mut().zip(totals) {
+            for value in segment {
+                value.probability /= total;
+            }
+        }
+        Ok(())
     }
 
     fn calculate_entropies(addresses: &[u128]) -> Vec<f64> {
@@ -122,27 +301,24 @@ impl EntropyIpTga {
         entropies
     }
 
-    fn segment_addresses(entropies: &[f64], const_c: usize) -> Vec<Segment> {
+    fn segment_addresses(
+        entropies: &[f64],
+        const_c: usize,
+        config: &SegmentationConfig,
+    ) -> Vec<Segment> {
         let mut segments = Vec::new();
         let total_nybbles = const_c * 2;
         if total_nybbles == 0 {
             return segments;
         }
 
-        let thresholds = [0.025, 0.1, 0.3, 0.5, 0.9];
-        let hysteresis = 0.05;
-
         let mut current_segment_start = 0;
+        // Threshold crossings are only considered after the first forced split,
+        // which keeps the leading prefix (the /32 by default) in one segment
+        let first_forced = config.forced_splits.iter().copied().min().unwrap_or(0);
 
-        segments.push(Segment {
-            start_nybble: 0,
-            end_nybble: 7,
-            values: Vec::new(),
-        });
-        current_segment_start = 8;
-
-        for i in (current_segment_start + 1)..total_nybbles {
-            if i == 16 {
+        for i in 1..total_nybbles {
+            if config.forced_splits.contains(&i) {
                 segments.push(Segment {
                     start_nybble: current_segment_start,
                     end_nybble: i - 1,
@@ -151,15 +327,19 @@ impl EntropyIpTga {
                 current_segment_start = i;
                 continue;
             }
+            if i <= first_forced {
+                continue;
+            }
 
             let h_prev = entropies[i - 1];
             let h_curr = entropies[i];
 
-            let crosses_threshold = thresholds
+            let crosses_threshold = config
+                .thresholds
                 .iter()
                 .any(|&t| (h_prev < t && h_curr >= t) || (h_prev >= t && h_curr < t));
 
-            if crosses_threshold && (h_curr - h_prev).abs() > hysteresis {
+            if crosses_threshold && (h_curr - h_prev).abs() > config.hysteresis {
                 segments.push(Segment {
                     start_nybble: current_segment_start,
                     end_nybble: i - 1,
@@ -185,26 +365,47 @@ impl EntropyIpTga {
             let mut value_counts = HashMap::new();
             let num_nybbles_in_segment = segment.end_nybble - segment.start_nybble + 1;
             let shift = (32 - segment.end_nybble - 1) * 4;
-            let mask = (1u128 << (num_nybbles_in_segment * 4)) - 1;
+            let mask = nybble_mask(num_nybbles_in_segment);
 
+            // A single nybble has few enough values to give every one of them the
+            // pseudocount, so values unseen in training can still be generated
+            if num_nybbles_in_segment == 1 {
+                value_counts.extend((0..=mask).map(|value| (value, 0)));
+            }
             for &addr in addresses {
                 let value = (addr >> shift) & mask;
                 *value_counts.entry(value).or_insert(0) += 1;
             }
 
+            let smoothed_total =
+                total_addresses + SMOOTHING_PSEUDOCOUNT * value_counts.len() as f64;
             segment.values = value_counts
                 .into_iter()
                 .map(|(value, count)| SegmentValue {
                     value,
-                    probability: count as f64 / total_addresses,
+                    probability: (count as f64 + SMOOTHING_PSEUDOCOUNT) / smoothed_total,
                 })
                 .collect();
         }
     }
 }
 
-fn entropy_ip_train_fn(addresses: Vec<[u8; 16]>) -> Box<dyn crate::TGA> {
-    Box::new(<EntropyIpTga as crate::TGA>::train(addresses).expect("Training failed"))
+/// Pseudocount added to every observed segment value, and to every value of a
+/// single-nybble segment, when mining, so rare and unseen values keep a share of the
+/// probability mass (add-one smoothing).
+const SMOOTHING_PSEUDOCOUNT: f64 = 1.0;
+
+/// Mask covering the low `nybbles` nybbles; a segment may span the whole address.
+fn nybble_mask(nybbles: usize) -> u128 {
+    if nybbles >= 32 {
+        u128::MAX
+    } else {
+        (1u128 << (nybbles * 4)) - 1
+    }
+}
+
+fn entropy_ip_train_fn(addresses: Vec<[u8; 16]>) -> Result<Box<dyn crate::TGA>, String> {
+    Ok(Box::new(<EntropyIpTga as crate::TGA>::train(addresses)?))
 }
 
 inventory::submit! {
@@ -214,3 +415,209 @@ inventory::submit! {
         train_fn: entropy_ip_train_fn,
     }
 }
+
+inventory::submit! {
+    crate::TgaTypeTag("entropy_ip")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seeds sharing a /48 whose nybbles 12-13 take every value, so entropy jumps
+    /// from 0 to 1 and back around them.
+    fn varied_seeds() -> Vec<[u8; 16]> {
+        (0..256u128)
+            .map(|i| (0x2001_0db8_0000_0000_0000_0000_0000_0001u128 | (i << 72)).to_be_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_stricter_thresholds_yield_fewer_segments() {
+        let default =
+            EntropyIpTga::train_with_config(varied_seeds(), &SegmentationConfig::default())
+                .unwrap();
+        let strict = SegmentationConfig {
+            thresholds: vec![],
+            ..SegmentationConfig::default()
+        };
+        let strict = EntropyIpTga::train_with_config(varied_seeds(), &strict).unwrap();
+
+        assert!(
+            strict.segments().len() < default.segments().len(),
+            "strict: {}, default: {}",
+            strict.segments().len(),
+            default.segments().len()
+        );
+    }
+
+    #[test]
+    fn test_disabling_forced_64_split_changes_segmentation() {
+        // A fixed /48 followed by 80 bits that all vary together
+        let seeds: Vec<[u8; 16]> = (0..16u128)
+            .map(|i| {
+                (0x2001_0db8_0001_0000_0000_0000_0000_0000u128 | (i * 0x1111_1111_1111_1111_1111))
+                    .to_be_bytes()
+            })
+            .collect();
+
+        let default =
+            EntropyIpTga::train_with_config(seeds.clone(), &SegmentationConfig::default()).unwrap();
+        let no_64 = SegmentationConfig {
+            forced_splits: vec![8],
+            ..SegmentationConfig::default()
+        };
+        let no_64 = EntropyIpTga::train_with_config(seeds.clone(), &no_64).unwrap();
+
+        let bounds = |tga: &EntropyIpTga| -> Vec<(usize, usize)> {
+            tga.segments()
+                .iter()
+                .map(|s| (s.start_nybble, s.end_nybble))
+                .collect()
+        };
+        assert_eq!(bounds(&default), vec![(0, 7), (8, 11), (12, 15), (16, 31)]);
+        assert_eq!(bounds(&no_64), vec![(0, 7), (8, 11), (12, 31)]);
+
+        // A single segment spanning the whole address must not overflow
+        let none = SegmentationConfig {
+            thresholds: vec![],
+            forced_splits: vec![],
+            ..SegmentationConfig::default()
+        };
+        let whole = EntropyIpTga::train_with_config(seeds, &none).unwrap();
+        assert_eq!(bounds(&whole), vec![(0, 31)]);
+        let generated = u128::from_be_bytes(whole.generate());
+        assert_eq!(generated >> 80, 0x2001_0db8_0001);
+    }
+
+    #[test]
+    fn test_normalize_after_manual_edits() {
+        let mut tga = EntropyIpTga::train(varied_seeds()).unwrap();
+        let varied = tga
+            .segments()
+            .iter()
+            .position(|s| s.values.len() > 1)
+            .unwrap();
+
+        // Drop the rarest half of the varied segment's values and double one survivor
+        let segment = &mut tga.segments_mut()[varied];
+        segment.values.sort_by(|a, b| {
+            a.probability
+                .total_cmp(&b.probability)
+                .then(a.value.cmp(&b.value))
+        });
+        segment.values.drain(..segment.values.len() / 2);
+        segment.values[0].probability *= 2.0;
+        let kept: Vec<u128> = segment.into_iter().map(|v| v.value).collect();
+
+        tga.normalize_probabilities().unwrap();
+        for segment in &tga {
+            let total: f64 = segment.into_iter().map(|v| v.probability).sum();
+            assert!((total - 1.0).abs() < 1e-9, "{}", total);
+            assert!(segment.into_iter().all(|v| v.probability >= 0.0));
+        }
+
+        let segment = &tga.segments()[varied];
+        let shift = (31 - segment.end_nybble) * 4;
+        let mask = nybble_mask(segment.end_nybble - segment.start_nybble + 1);
+        for _ in 0..100 {
+            let value = (u128::from_be_bytes(tga.generate()) >> shift) & mask;
+            assert!(kept.contains(&value), "{:#x}", value);
+        }
+    }
+
+    #[test]
+    fn test_single_observed_value_is_always_generated() {
+        let tga = EntropyIpTga::train(varied_seeds()).unwrap();
+        let prefix = &tga.segments()[0];
+        assert_eq!((prefix.start_nybble, prefix.end_nybble), (0, 7));
+        assert_eq!(prefix.values.len(), 1);
+        assert_eq!(prefix.values[0].probability, 1.0);
+
+        for _ in 0..1000 {
+            let generated = u128::from_be_bytes(tga.generate());
+            assert_eq!(generated >> 96, 0x2001_0db8);
+        }
+    }
+
+    #[test]
+    fn test_unseen_nybble_values_can_be_generated() {
+        // Only 1 and 2 are seen in the last nybble, which is a segment of its own
+        let seeds = [
+            0x2001_0db8_0000_0000_0000_0000_0000_0001u128.to_be_bytes(),
+            0x2001_0db8_0000_0000_0000_0000_0000_0002u128.to_be_bytes(),
+        ];
+        let tga = EntropyIpTga::train(seeds).unwrap();
+        let last = tga.segments().last().unwrap();
+        assert_eq!((last.start_nybble, last.end_nybble), (31, 31));
+        assert_eq!(last.values.len(), 16);
+
+        let seen: HashSet<u128> = (0..2000)
+            .map(|_| u128::from_be_bytes(tga.generate()) & 0xf)
+            .collect();
+        assert!(seen.contains(&1) && seen.contains(&2));
+        assert!(seen.len() > 2, "{:?}", seen);
+    }
+
+    #[test]
+    fn test_seeded_generation_is_reproducible() {
+        use rand::SeedableRng;
+        let tga = EntropyIpTga::train(crate::demo_seed_addresses()).unwrap();
+        let sample = |seed| {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            (0..20)
+                .map(|_| tga.generate_with_rng(&mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sample(7), sample(7));
+        assert_ne!(sample(7), sample(8));
+    }
+
+    #[test]
+    fn test_empty_segment_falls_back_to_uniform() {
+        let mut tga = EntropyIpTga::train(varied_seeds()).unwrap();
+        let last = tga.segments().len() - 1;
+        tga.segments_mut()[last].values.clear();
+
+        // The emptied segment is drawn uniformly instead of being left as zeros, and
+        // the segments before it are unaffected
+        let generated: Vec<u128> = (0..100)
+            .map(|_| u128::from_be_bytes(tga.generate()))
+            .collect();
+        assert!(generated.iter().all(|addr| addr >> 96 == 0x2001_0db8));
+        assert!(generated.iter().any(|addr| addr & 0xffff != 0x0001));
+    }
+
+    #[test]
+    fn test_unique_generation_beyond_capacity_fails_fast() {
+        // Two seeds differing only in the last byte give exactly two addresses
+        let seeds = [
+            0x2001_0db8_0000_0000_0000_0000_0000_0011u128.to_be_bytes(),
+            0x2001_0db8_0000_0000_0000_0000_0000_0022u128.to_be_bytes(),
+        ];
+        let tga = EntropyIpTga::train(seeds).unwrap();
+        assert_eq!(tga.capacity(), Some(2));
+
+        let err = tga.generate_unique(3).unwrap_err();
+        assert!(err.contains("only produce 2"), "{}", err);
+        assert_eq!(tga.generate_unique(2).unwrap().len(), 2);
+        let enumerated: Vec<[u8; 16]> = tga.enumerate().unwrap().collect();
+        assert_eq!(enumerated, seeds);
+
+        // An emptied segment counts as its whole range
+        let mut tga = tga;
+        tga.segments_mut()[0].values.clear();
+        assert!(tga.capacity().unwrap() > 2);
+    }
+
+    #[test]
+    fn test_normalize_rejects_negative_probabilities() {
+        let mut tga = EntropyIpTga::train(varied_seeds()).unwrap();
+        for value in &mut tga.segments_mut()[0] {
+            value.probability = -0.5;
+        }
+        let err = tga.normalize_probabilities().unwrap_err();
+        assert!(err.contains("invalid probability"), "{}", err);
+        assert!(tga.segments()[0].values[0].probability < 0.0);
+    }
+}
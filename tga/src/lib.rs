@@ -1,4 +1,6 @@
 mod entropy_ip;
+mod low_byte;
+mod model_cache;
 pub mod python_tga;
 mod random_ip;
 
@@ -8,8 +10,10 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::net::Ipv6Addr;
 use std::sync::Once;
+use tracing::{debug, info, warn};
 
-pub use entropy_ip::EntropyIpTga;
+pub use entropy_ip::{EntropyIpTga, Segment, SegmentValue, SegmentationConfig};
+pub use low_byte::LowByteTga;
 use plugin::contracts::PluginInfo;
 pub use python_tga::PythonTGA;
 pub use python_tga::PythonTgaInfo;
@@ -22,13 +26,33 @@ pub trait TgaInfo {
     const DESCRIPTION: &'static str;
 }
 
+/// Error returned when training an algorithm that needs seeds on an empty seed set.
+pub const NO_SEEDS_ERROR: &str = "no seed addresses";
+
 #[typetag::serde]
 pub trait TGA: Send + Sync {
+    /// Trains a model on `seeds`. Algorithms that learn from seeds return
+    /// `Err(NO_SEEDS_ERROR)` when `seeds` is empty rather than a degenerate model.
     fn train<T: IntoIterator<Item = [u8; 16]>>(seeds: T) -> Result<Self, String>
     where
         Self: Sized;
     fn generate(&self) -> [u8; 16];
-    fn generate_unique(&self, count: usize) -> Vec<[u8; 16]> {
+    /// Number of distinct addresses the model can produce, or `None` when unknown or
+    /// too large to matter.
+    fn capacity(&self) -> Option<u128> {
+        None
+    }
+    /// Generates `count` distinct addresses, failing up front when the model's
+    /// [`TGA::capacity`] is smaller than `count`.
+    fn generate_unique(&self, count: usize) -> Result<Vec<[u8; 16]>, String> {
+        if let Some(capacity) = self.capacity() {
+            if count as u128 > capacity {
+                return Err(format!(
+                    "Requested {} unique addresses but the model can only produce {}",
+                    count, capacity
+                ));
+            }
+        }
         const MAX_ATTEMPTS: usize = 1_000_000;
         let mut set = HashSet::new();
         let mut attempts = 0;
@@ -36,7 +60,12 @@ pub trait TGA: Send + Sync {
             set.insert(self.generate());
             attempts += 1;
         }
-        set.into_iter().collect()
+        Ok(set.into_iter().collect())
+    }
+    /// Every address the model can generate, each once and in order, or `None` when the
+    /// model cannot list its address space. The iterator ends once the space is exhausted.
+    fn enumerate(&self) -> Option<Box<dyn Iterator<Item = [u8; 16]> + '_>> {
+        None
     }
     fn name(&self) -> &'static str;
     fn description(&self) -> &'static str;
@@ -46,11 +75,17 @@ pub trait TGA: Send + Sync {
 pub struct TgaRegistration {
     pub name: &'static str,
     pub description: &'static str,
-    pub train_fn: fn(Vec<[u8; 16]>) -> Box<dyn TGA>,
+    pub train_fn: fn(Vec<[u8; 16]>) -> Result<Box<dyn TGA>, String>,
 }
 
 inventory::collect!(TgaRegistration);
 
+/// The `#[typetag::serde(name = ...)]` tag of a concrete TGA type, registered so that
+/// the set of deserializable model types can be enumerated.
+pub struct TgaTypeTag(pub &'static str);
+
+inventory::collect!(TgaTypeTag);
+
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -59,15 +94,15 @@ static DYNAMIC_PYTHON_TGAS: Mutex<Vec<TgaRegistration>> = Mutex::new(Vec::new())
 
 fn get_dynamic_python_tgas() -> Vec<TgaRegistration> {
     DYNAMIC_PYTHON_TGAS_INIT.call_once(|| {
-        println!("[DEBUG] Querying Python TGA registry...");
+        debug!("Querying Python TGA registry...");
         let python_tga_infos = match python_tga::get_available_python_tga_infos() {
             Ok(list) => list,
             Err(e) => {
-                println!("[DEBUG] Error querying Python TGAs: {e}");
+                debug!("Error querying Python TGAs: {e}");
                 vec![]
             }
         };
-        println!("[DEBUG] Python TGAs found: {:?}", python_tga_infos);
+        debug!("Python TGAs found: {:?}", python_tga_infos);
         let mut regs = Vec::new();
         for info in python_tga_infos {
             let name = info.name;
@@ -84,34 +119,39 @@ fn get_dynamic_python_tgas() -> Vec<TgaRegistration> {
         *dynamic_tgas = regs;
     });
     let result = DYNAMIC_PYTHON_TGAS.lock().unwrap().clone();
-    println!(
-        "[DEBUG] Returning dynamic Python TGAs: {:?}",
+    debug!(
+        "Returning dynamic Python TGAs: {:?}",
         result.iter().map(|r| r.name).collect::<Vec<_>>()
     );
     result
 }
 
-fn create_python_tga_train_fn(tga_name: &'static str) -> fn(Vec<[u8; 16]>) -> Box<dyn TGA> {
+fn create_python_tga_train_fn(
+    tga_name: &'static str,
+) -> fn(Vec<[u8; 16]>) -> Result<Box<dyn TGA>, String> {
     match tga_name {
         "lstm_ipv6" => lstm_ipv6_train_fn,
         _ => generic_python_tga_train_fn,
     }
 }
 
-fn lstm_ipv6_train_fn(addresses: Vec<[u8; 16]>) -> Box<dyn TGA> {
+fn lstm_ipv6_train_fn(addresses: Vec<[u8; 16]>) -> Result<Box<dyn TGA>, String> {
     let kwargs = serde_json::json!({});
-    let python_tga = PythonTGA::train_with_python("lstm_ipv6", addresses, kwargs)
-        .expect("Failed to train Python TGA");
-    Box::new(python_tga)
+    let python_tga = PythonTGA::train_with_python("lstm_ipv6", addresses, kwargs)?;
+    Ok(Box::new(python_tga))
 }
 
-fn generic_python_tga_train_fn(addresses: Vec<[u8; 16]>) -> Box<dyn TGA> {
+fn generic_python_tga_train_fn(addresses: Vec<[u8; 16]>) -> Result<Box<dyn TGA>, String> {
     let kwargs = serde_json::json!({});
-    let python_tga = PythonTGA::train_with_python("lstm_ipv6", addresses, kwargs)
-        .expect("Failed to train Python TGA");
-    Box::new(python_tga)
+    let python_tga = PythonTGA::train_with_python("lstm_ipv6", addresses, kwargs)?;
+    Ok(Box::new(python_tga))
 }
 
+/// Prefix written before every serialized model so foreign files are rejected up front.
+pub const MODEL_MAGIC: &[u8; 4] = b"TGAS";
+/// Bumped whenever the serialized model layout changes incompatibly.
+pub const MODEL_FORMAT_VERSION: u8 = 2;
+
 pub struct TgaRegistry;
 
 impl TgaRegistry {
@@ -132,26 +172,72 @@ impl TgaRegistry {
         name: &str,
         addresses: Vec<[u8; 16]>,
     ) -> Result<Box<dyn TGA + Sync + Send + 'static>, String> {
+        // Registry training always builds a model from a seed file, so an empty one is
+        // rejected here before any train_fn can panic on it
+        if addresses.is_empty() {
+            return Err(NO_SEEDS_ERROR.to_string());
+        }
         if let Some(reg) = inventory::iter::<TgaRegistration>
             .into_iter()
             .find(|reg| reg.name == name)
         {
-            Ok((reg.train_fn)(addresses))
+            (reg.train_fn)(addresses).map(|tga| tga as Box<dyn TGA + Sync + Send + 'static>)
         } else {
             let python_tgas = get_dynamic_python_tgas();
             if let Some(reg) = python_tgas.iter().find(|reg| reg.name == name) {
-                Ok((reg.train_fn)(addresses))
+                (reg.train_fn)(addresses).map(|tga| tga as Box<dyn TGA + Sync + Send + 'static>)
             } else {
                 Err(format!("Unknown TGA type: {}", name))
             }
         }
     }
+    /// Type tags of every TGA type that can be deserialized from a model file.
+    pub fn registered_type_tags() -> Vec<&'static str> {
+        let mut tags: Vec<&'static str> = inventory::iter::<TgaTypeTag>
+            .into_iter()
+            .map(|tag| tag.0)
+            .collect();
+        tags.sort_unstable();
+        tags
+    }
+    /// Serializes a trained model behind the [`MODEL_MAGIC`] header and format version.
+    pub fn serialize_tga(model: &dyn TGA) -> Result<Vec<u8>, String> {
+        let body =
+            bincode::serialize(model).map_err(|e| format!("Failed to serialize model: {}", e))?;
+        let mut model_data = Vec::with_capacity(MODEL_MAGIC.len() + 1 + body.len());
+        model_data.extend_from_slice(MODEL_MAGIC);
+        model_data.push(MODEL_FORMAT_VERSION);
+        model_data.extend_from_slice(&body);
+        Ok(model_data)
+    }
     pub fn deserialize_tga(
         model_data: &[u8],
     ) -> Result<Box<dyn TGA + Sync + Send + 'static>, String> {
-        bincode::deserialize::<Box<dyn TGA>>(model_data)
+        let header_len = MODEL_MAGIC.len() + 1;
+        if model_data.len() < header_len || &model_data[..MODEL_MAGIC.len()] != MODEL_MAGIC {
+            return Err("Not a tgas model: missing model header".to_string());
+        }
+        let version = model_data[MODEL_MAGIC.len()];
+        if version != MODEL_FORMAT_VERSION {
+            return Err(format!(
+                "Model format version mismatch: file has version {}, this build reads version {}",
+                version, MODEL_FORMAT_VERSION
+            ));
+        }
+        let body = &model_data[header_len..];
+        if let Some(tag) = embedded_type_tag(body) {
+            let known = Self::registered_type_tags();
+            if !known.contains(&tag) {
+                return Err(format!(
+                    "Model has unknown TGA type '{}'; known types: {}",
+                    tag,
+                    known.join(", ")
+                ));
+            }
+        }
+        bincode::deserialize::<Box<dyn TGA>>(body)
             .map(|b| b as Box<dyn TGA + Sync + Send + 'static>)
-            .map_err(|e| format!("Failed to deserialize model: {}", e))
+            .map_err(|e| format!("Failed to deserialize model (file may be truncated): {}", e))
     }
     pub fn get_tga_help_text() -> String {
         let mut help = String::from("Type of TGA to train. Available types:\n");
@@ -162,6 +248,21 @@ impl TgaRegistry {
     }
 }
 
+/// Reads the type tag typetag writes at the start of a bincode-encoded model: a
+/// one-entry map whose key is the length-prefixed tag string.
+fn embedded_type_tag(body: &[u8]) -> Option<&str> {
+    let read_u64 = |offset: usize| {
+        body.get(offset..offset + 8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    if read_u64(0)? != 1 {
+        return None;
+    }
+    let len = usize::try_from(read_u64(8)?).ok()?;
+    let tag = body.get(16..16usize.checked_add(len)?)?;
+    std::str::from_utf8(tag).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -189,28 +290,167 @@ mod tests {
         println!("Registered TGAs: {:?}", tgas);
         println!("Help text:\n{}", help_text);
     }
+
+    fn serialized_model() -> Vec<u8> {
+        let model = RandomIpTga::train(demo_seed_addresses()).unwrap();
+        TgaRegistry::serialize_tga(&model).unwrap()
+    }
+
+    #[test]
+    fn test_model_round_trip() {
+        let model = TgaRegistry::deserialize_tga(&serialized_model()).unwrap();
+        assert_eq!(model.name(), "random_ip");
+    }
+
+    #[test]
+    fn test_truncated_model_is_rejected() {
+        let model_data = serialized_model();
+        let err = TgaRegistry::deserialize_tga(&model_data[..model_data.len() / 2])
+            .err()
+            .unwrap();
+        assert!(err.contains("truncated"), "{}", err);
+
+        let err = TgaRegistry::deserialize_tga(&model_data[..2])
+            .err()
+            .unwrap();
+        assert!(err.contains("Not a tgas model"), "{}", err);
+    }
+
+    #[test]
+    fn test_wrong_magic_is_rejected() {
+        let mut model_data = serialized_model();
+        model_data[..4].copy_from_slice(b"PK\x03\x04");
+        let err = TgaRegistry::deserialize_tga(&model_data).err().unwrap();
+        assert!(err.contains("Not a tgas model"), "{}", err);
+
+        let mut model_data = serialized_model();
+        model_data[4] = MODEL_FORMAT_VERSION + 1;
+        let err = TgaRegistry::deserialize_tga(&model_data).err().unwrap();
+        assert!(err.contains("version mismatch"), "{}", err);
+    }
+
+    #[test]
+    fn test_entropy_ip_model_tag_is_registered() {
+        let model = EntropyIpTga::train(demo_seed_addresses()).unwrap();
+        let model_data = TgaRegistry::serialize_tga(&model).unwrap();
+
+        let tag = embedded_type_tag(&model_data[MODEL_MAGIC.len() + 1..]).unwrap();
+        assert_eq!(tag, "entropy_ip");
+        assert!(TgaRegistry::registered_type_tags().contains(&tag));
+
+        let model = TgaRegistry::deserialize_tga(&model_data).unwrap();
+        assert_eq!(model.name(), "entropy_ip");
+    }
+
+    #[test]
+    fn test_unknown_type_tag_lists_known_tags() {
+        let mut model_data = MODEL_MAGIC.to_vec();
+        model_data.push(MODEL_FORMAT_VERSION);
+        model_data.extend_from_slice(&1u64.to_le_bytes());
+        model_data.extend_from_slice(&5u64.to_le_bytes());
+        model_data.extend_from_slice(b"bogus");
+
+        let err = TgaRegistry::deserialize_tga(&model_data).err().unwrap();
+        assert!(err.contains("unknown TGA type 'bogus'"), "{}", err);
+        assert!(err.contains("entropy_ip"), "{}", err);
+        assert!(err.contains("random_ip"), "{}", err);
+    }
+
+    #[test]
+    fn test_training_on_empty_seeds_is_an_error() {
+        for name in TgaRegistry::get_available_tgas() {
+            let err = TgaRegistry::train_tga(name, Vec::new()).err().unwrap();
+            assert_eq!(err, NO_SEEDS_ERROR, "{}", name);
+        }
+
+        let err = EntropyIpTga::train(Vec::new()).err().unwrap();
+        assert_eq!(err, NO_SEEDS_ERROR);
+        let err = PythonTGA::train(Vec::new()).err().unwrap();
+        assert_eq!(err, NO_SEEDS_ERROR);
+    }
+
+    fn failing_train_fn(_addresses: Vec<[u8; 16]>) -> Result<Box<dyn TGA>, String> {
+        Err("model did not converge".to_string())
+    }
+
+    inventory::submit! {
+        TgaRegistration {
+            name: "failing_test_tga",
+            description: "Always fails to train",
+            train_fn: failing_train_fn,
+        }
+    }
+
+    #[test]
+    fn test_training_failure_is_returned_as_error() {
+        let result = std::panic::catch_unwind(|| {
+            TgaRegistry::train_tga("failing_test_tga", demo_seed_addresses())
+        });
+        let err = result.expect("training panicked").err().unwrap();
+        assert_eq!(err, "model did not converge");
+    }
+
+    #[test]
+    fn test_demo_seed_addresses_are_stable() {
+        let seeds = demo_seed_addresses();
+        assert_eq!(seeds, demo_seed_addresses());
+        assert_eq!(seeds.len(), DEMO_SEED_COUNT);
+        assert_eq!(seeds.iter().collect::<HashSet<_>>().len(), seeds.len());
+        assert!(seeds.iter().all(|s| s[..4] == [0x20, 0x01, 0x0d, 0xb8]));
+        assert_eq!(demo_seeds(10), seeds[..10]);
+    }
+
+    #[test]
+    fn test_demo_seeds_span_several_prefixes() {
+        let seeds = demo_seed_addresses();
+        let slash48s: HashSet<&[u8]> = seeds.iter().map(|s| &s[..6]).collect();
+        let slash64s: HashSet<&[u8]> = seeds.iter().map(|s| &s[..8]).collect();
+        assert_eq!(slash48s.len(), 4);
+        assert_eq!(slash64s.len(), 16);
+    }
+}
+
+/// Number of seeds in [`demo_seed_addresses`].
+pub const DEMO_SEED_COUNT: usize = 48;
+
+/// A fixed set of documentation-prefix seeds for demos and tests.
+pub fn demo_seed_addresses() -> Vec<[u8; 16]> {
+    demo_seeds(DEMO_SEED_COUNT)
+}
+
+/// The first `count` addresses of a deterministic seed sequence under `2001:db8::/32`.
+/// Consecutive seeds rotate over four /48s, four /64s in each, and low-byte, EUI-64
+/// and pseudo-random interface identifiers, so models trained on them vary in every
+/// part of the address.
+pub fn demo_seeds(count: usize) -> Vec<[u8; 16]> {
+    (0..count as u64)
+        .map(|i| {
+            let site = 1 + i % 4;
+            let subnet = i / 4 % 4;
+            let iid = match i % 3 {
+                0 => i / 3 + 1,
+                1 => 0x0211_22ff_fe00_0000 | (i & 0xff_ffff),
+                _ => splitmix64(i),
+            };
+            let prefix =
+                (0x2001_0db8u128 << 96) | ((site as u128) << 80) | ((subnet as u128) << 64);
+            (prefix | iid as u128).to_be_bytes()
+        })
+        .collect()
+}
+
+/// A fixed bijective mix of `x`, for identifiers that look random but never change.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
 pub fn generate(count: usize, unique: bool) {
-    let seed_ips: Vec<[u8; 16]> = vec![
-        Ipv6Addr::new(0x2001, 0x0db8, 0x0001, 0x0001, 0, 0, 0, 0x0001).octets(),
-        Ipv6Addr::new(0x2001, 0x0db8, 0x0001, 0x0001, 0, 0, 0, 0x0002).octets(),
-        Ipv6Addr::new(0x2001, 0x0db8, 0x0001, 0x0002, 0, 0, 0, 0x0001).octets(),
-        Ipv6Addr::new(0x2001, 0x0db8, 0x0001, 0x0002, 0, 0, 0, 0x0002).octets(),
-        Ipv6Addr::new(0x2001, 0x0db8, 0x0002, 0x000a, 0, 0, 0, 0x000a).octets(),
-        Ipv6Addr::new(0x2001, 0x0db8, 0x0002, 0x000a, 0, 0, 0, 0x000b).octets(),
-        Ipv6Addr::new(0x2001, 0x0db8, 0x0002, 0x000b, 0, 0, 0, 0x000a).octets(),
-        Ipv6Addr::new(
-            0x2001, 0x0db8, 0x1111, 0x2222, 0x3333, 0x4444, 0x5555, 0x6666,
-        )
-        .octets(),
-        Ipv6Addr::new(
-            0x2001, 0x0db8, 0x1111, 0x2222, 0x3333, 0x4444, 0x5555, 0x6667,
-        )
-        .octets(),
-    ];
-
-    println!("Building model from {} seed addresses...", seed_ips.len());
+    let seed_ips = demo_seed_addresses();
+
+    info!("Building model from {} seed addresses...", seed_ips.len());
     let tga = EntropyIpTga::train(seed_ips).expect("Failed to train model");
 
     let mut generated = HashSet::new();
@@ -229,8 +469,8 @@ pub fn generate(count: usize, unique: bool) {
         } else {
             attempts += 1;
             if attempts >= MAX_ATTEMPTS {
-                eprintln!(
-                    "Warning: Could only generate {}/{} unique addresses after {} attempts",
+                warn!(
+                    "Could only generate {}/{} unique addresses after {} attempts",
                     i, count, MAX_ATTEMPTS
                 );
                 break;
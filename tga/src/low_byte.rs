@@ -0,0 +1,182 @@
+use crate::TGA;
+use plugin::contracts::PluginInfo;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+/// Mask of the interface identifier, the low 64 bits of an address.
+const IID_MASK: u128 = u64::MAX as u128;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LowByteTga {
+    /// Distinct /64 prefixes seen in the seeds, sorted
+    prefixes: Vec<u128>,
+    /// Largest interface identifier generated; hosts are drawn from `1..=max_host`
+    max_host: u16,
+}
+
+impl PluginInfo for LowByteTga {
+    const NAME: &'static str = "low_byte";
+    const DESCRIPTION: &'static str =
+        "Enumerates low interface identifiers (::1, ::2, ...) in the seeds' /64 prefixes";
+}
+
+impl LowByteTga {
+    pub const NAME: &'static str = "low_byte";
+    pub const DESCRIPTION: &'static str =
+        "Enumerates low interface identifiers (::1, ::2, ...) in the seeds' /64 prefixes";
+
+    /// Hosts generated by [`TGA::train`]: `::1` through `::ff`.
+    pub const DEFAULT_MAX_HOST: u16 = 0xff;
+
+    /// Learns the seeds' /64 prefixes; generation picks one uniformly and pairs it with a
+    /// host identifier between 1 and `max_host`. `max_host` must be below `0xffff`,
+    /// which is no longer a low-byte host.
+    pub fn train_with_max_host<T: IntoIterator<Item = [u8; 16]>>(
+        seeds: T,
+        max_host: u16,
+    ) -> Result<Self, String> {
+        if max_host == 0 || max_host == u16::MAX {
+            return Err(format!(
+                "Largest host must be between 1 and {:#x}, got {:#x}",
+                u16::MAX - 1,
+                max_host
+            ));
+        }
+
+        let mut prefixes: Vec<u128> = seeds
+            .into_iter()
+            .map(|seed| u128::from_be_bytes(seed) & !IID_MASK)
+            .collect();
+        if prefixes.is_empty() {
+            return Err(crate::NO_SEEDS_ERROR.to_string());
+        }
+        prefixes.sort_unstable();
+        prefixes.dedup();
+
+        Ok(LowByteTga { prefixes, max_host })
+    }
+
+    pub fn prefixes(&self) -> &[u128] {
+        &self.prefixes
+    }
+
+    pub fn max_host(&self) -> u16 {
+        self.max_host
+    }
+}
+
+#[typetag::serde(name = "low_byte")]
+impl TGA for LowByteTga {
+    fn train<T: IntoIterator<Item = [u8; 16]>>(seeds: T) -> Result<Self, String> {
+        Self::train_with_max_host(seeds, Self::DEFAULT_MAX_HOST)
+    }
+
+    fn generate(&self) -> [u8; 16] {
+        let mut rng = rand::thread_rng();
+        let prefix = self.prefixes.choose(&mut rng).copied().unwrap_or_default();
+        let host = rng.gen_range(1..=self.max_host);
+        (prefix | host as u128).to_be_bytes()
+    }
+
+    fn capacity(&self) -> Option<u128> {
+        Some(self.prefixes.len() as u128 * self.max_host as u128)
+    }
+
+    /// `::1` through `::max_host` in each prefix.
+    fn enumerate(&self) -> Option<Box<dyn Iterator<Item = [u8; 16]> + '_>> {
+        let max_host = self.max_host;
+        Some(Box::new(self.prefixes.iter().flat_map(move |&prefix| {
+            (1..=max_host).map(move |host| (prefix | host as u128).to_be_bytes())
+        })))
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn description(&self) -> &'static str {
+        Self::DESCRIPTION
+    }
+}
+
+fn low_byte_train_fn(addresses: Vec<[u8; 16]>) -> Result<Box<dyn crate::TGA>, String> {
+    Ok(Box::new(<LowByteTga as crate::TGA>::train(addresses)?))
+}
+
+inventory::submit! {
+    crate::TgaRegistration {
+        name: LowByteTga::NAME,
+        description: LowByteTga::DESCRIPTION,
+        train_fn: low_byte_train_fn,
+    }
+}
+
+inventory::submit! {
+    crate::TgaTypeTag("low_byte")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use analyze::analysis::predicates::eui64::IsLowByteHostPredicate;
+    use plugin::contracts::Predicate;
+    use std::collections::HashSet;
+    use std::net::Ipv6Addr;
+
+    #[test]
+    fn test_generated_addresses_are_low_byte_hosts_in_seed_prefixes() {
+        let tga = LowByteTga::train(crate::demo_seed_addresses()).unwrap();
+        let prefixes: HashSet<u128> = tga.prefixes().iter().copied().collect();
+        assert_eq!(prefixes.len(), 16);
+
+        for _ in 0..1000 {
+            let addr = Ipv6Addr::from(tga.generate());
+            assert!(IsLowByteHostPredicate.predicate(addr), "{}", addr);
+            assert!(
+                prefixes.contains(&(u128::from(addr) & !IID_MASK)),
+                "{}",
+                addr
+            );
+            assert_ne!(u128::from(addr) & IID_MASK, 0, "{}", addr);
+        }
+    }
+
+    #[test]
+    fn test_unique_generation_enumerates_every_host() {
+        let seeds = ["2001:db8::1234:5678", "2001:db8:0:1::1"]
+            .map(|s| s.parse::<Ipv6Addr>().unwrap().octets());
+        let tga = LowByteTga::train_with_max_host(seeds, 4).unwrap();
+        assert_eq!(tga.capacity(), Some(8));
+
+        let mut generated = tga.generate_unique(8).unwrap();
+        generated.sort();
+        let hosts: Vec<String> = generated
+            .iter()
+            .map(|bytes| Ipv6Addr::from(*bytes).to_string())
+            .collect();
+        assert_eq!(
+            hosts,
+            [
+                "2001:db8::1",
+                "2001:db8::2",
+                "2001:db8::3",
+                "2001:db8::4",
+                "2001:db8:0:1::1",
+                "2001:db8:0:1::2",
+                "2001:db8:0:1::3",
+                "2001:db8:0:1::4",
+            ]
+        );
+        assert!(LowByteTga::train_with_max_host(seeds, u16::MAX).is_err());
+
+        // Through the registry, as a boxed model
+        let boxed = crate::TgaRegistry::train_tga(LowByteTga::NAME, seeds.to_vec()).unwrap();
+        assert_eq!(boxed.enumerate().unwrap().count(), 255 * 2);
+
+        let mut enumerated: Vec<[u8; 16]> = tga.enumerate().unwrap().collect();
+        assert_eq!(enumerated.len(), 8);
+        enumerated.sort();
+        assert_eq!(enumerated, generated);
+    }
+}
@@ -0,0 +1,123 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// On-disk index from training inputs to the model file they produced, so that
+/// retraining on identical seeds reuses the existing model instead of running
+/// training again.
+pub struct ModelCache {
+    index_path: PathBuf,
+}
+
+impl ModelCache {
+    /// The cache index lives in the system temp directory, next to the model
+    /// files written by `tga_runner.py`.
+    pub fn default_location() -> Self {
+        Self::new(std::env::temp_dir().join("tgas_model_cache.json"))
+    }
+
+    pub fn new(index_path: PathBuf) -> Self {
+        Self { index_path }
+    }
+
+    /// Hashes the sorted seed set together with the TGA name and its kwargs.
+    pub fn key(tga_name: &str, addresses: &[[u8; 16]], kwargs: &Value) -> String {
+        let mut sorted = addresses.to_vec();
+        sorted.sort_unstable();
+
+        // FNV-1a, chosen because the key is persisted and must be stable across builds
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut feed = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        feed(tga_name.as_bytes());
+        feed(&[0]);
+        for addr in &sorted {
+            feed(addr);
+        }
+        feed(kwargs.to_string().as_bytes());
+
+        format!("{:016x}", hash)
+    }
+
+    /// Returns the cached model path for `key` if its file still exists, otherwise
+    /// runs `train` and records the model path it returns.
+    pub fn get_or_train<F>(&self, key: &str, train: F) -> Result<String, String>
+    where
+        F: FnOnce() -> Result<String, String>,
+    {
+        let mut index = self.load();
+        if let Some(model_path) = index.get(key) {
+            if Path::new(model_path).exists() {
+                return Ok(model_path.clone());
+            }
+        }
+
+        let model_path = train()?;
+        index.insert(key.to_string(), model_path.clone());
+        self.save(&index)?;
+        Ok(model_path)
+    }
+
+    fn load(&self) -> HashMap<String, String> {
+        // A missing or unreadable index is treated as empty; it is only a cache
+        std::fs::read_to_string(&self.index_path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, index: &HashMap<String, String>) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(index)
+            .map_err(|e| format!("Failed to serialize model cache: {}", e))?;
+        let tmp_path = self.index_path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, contents)
+            .map_err(|e| format!("Failed to write model cache {:?}: {}", tmp_path, e))?;
+        std::fs::rename(&tmp_path, &self.index_path)
+            .map_err(|e| format!("Failed to write model cache {:?}: {}", self.index_path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_identical_seeds_hit_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ModelCache::new(dir.path().join("index.json"));
+        let trained = Cell::new(0);
+        let train = || {
+            trained.set(trained.get() + 1);
+            let model_path = dir.path().join(format!("model_{}.pkl", trained.get()));
+            std::fs::write(&model_path, b"model").unwrap();
+            Ok(model_path.to_string_lossy().to_string())
+        };
+
+        let seeds = [[1u8; 16], [2u8; 16]];
+        let reordered = [[2u8; 16], [1u8; 16]];
+        let first = cache
+            .get_or_train(&ModelCache::key("lstm_ipv6", &seeds, &json!({})), train)
+            .unwrap();
+        let second = cache
+            .get_or_train(&ModelCache::key("lstm_ipv6", &reordered, &json!({})), train)
+            .unwrap();
+
+        assert_eq!(trained.get(), 1);
+        assert_eq!(first, second);
+
+        let other = cache
+            .get_or_train(
+                &ModelCache::key("lstm_ipv6", &seeds, &json!({"epochs": 5})),
+                train,
+            )
+            .unwrap();
+        assert_eq!(trained.get(), 2);
+        assert_ne!(first, other);
+    }
+}
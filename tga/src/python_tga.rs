@@ -1,13 +1,26 @@
 use crate::TGA;
+use crate::model_cache::ModelCache;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::Once;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Overrides the path to `tga_runner.py`, bypassing the search next to the executable.
+pub const PYTHON_SCRIPT_ENV: &str = "TGAS_PYTHON_SCRIPT";
+/// Overrides the Python interpreter used to run `tga_runner.py`.
+pub const PYTHON_EXECUTABLE_ENV: &str = "TGAS_PYTHON_EXECUTABLE";
+/// Seconds to wait for a response from `tga_runner.py` before killing it.
+pub const PYTHON_TIMEOUT_ENV: &str = "TGAS_PYTHON_TIMEOUT";
+/// How long to wait for a response when [`PYTHON_TIMEOUT_ENV`] is unset.
+pub const DEFAULT_PYTHON_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Clone)]
 pub struct PythonTgaInfo {
@@ -34,30 +47,39 @@ impl PythonTGA {
         addresses: Vec<[u8; 16]>,
         kwargs: Value,
     ) -> Result<Self, String> {
-        let hex_addresses: Vec<String> = addresses.iter().map(|addr| hex::encode(addr)).collect();
-
-        let command = json!({
-            "command": "train",
-            "tga_name": tga_name,
-            "addresses": hex_addresses,
-            "kwargs": kwargs
-        });
-
-        let result = Self::execute_python_command(&command)?;
-
-        if let Some(error) = result.get("error") {
-            return Err(format!("Python TGA training failed: {}", error));
+        if addresses.is_empty() {
+            return Err(crate::NO_SEEDS_ERROR.to_string());
         }
+        let cache = ModelCache::default_location();
+        let key = ModelCache::key(tga_name, &addresses, &kwargs);
+        let model_path = cache.get_or_train(&key, || {
+            let hex_addresses: Vec<String> =
+                addresses.iter().map(|addr| hex::encode(addr)).collect();
+
+            let command = json!({
+                "command": "train",
+                "tga_name": tga_name,
+                "addresses": hex_addresses,
+                "kwargs": kwargs
+            });
+
+            let result = Self::execute_python_command(&command)?;
+
+            if let Some(error) = result.get("error") {
+                return Err(format!("Python TGA training failed: {}", error));
+            }
 
-        let model_info = result
-            .get("model_path")
-            .and_then(|path| {
-                Some(json!({
-                    "model_path": path.as_str().unwrap(),
-                    "tga_name": tga_name
-                }))
-            })
-            .ok_or_else(|| "No model path in response".to_string())?;
+            result
+                .get("model_path")
+                .and_then(|path| path.as_str())
+                .map(str::to_string)
+                .ok_or_else(|| "No model path in response".to_string())
+        })?;
+
+        let model_info = json!({
+            "model_path": model_path,
+            "tga_name": tga_name
+        });
 
         Ok(Self {
             tga_name: tga_name.to_string(),
@@ -122,68 +144,155 @@ impl PythonTGA {
 
         let python_executable = Self::find_python_executable()?;
 
-        println!("[DEBUG] Using Python executable: {}", python_executable);
-        println!("[DEBUG] Using script path: {:?}", script_path);
+        debug!("Using Python executable: {}", python_executable);
+        debug!("Using script path: {:?}", script_path);
+
+        Self::run_python(
+            &python_executable,
+            &script_path,
+            command,
+            Self::python_timeout()?,
+        )
+    }
+
+    fn python_timeout() -> Result<Duration, String> {
+        match std::env::var(PYTHON_TIMEOUT_ENV) {
+            Ok(secs) => secs
+                .parse::<u64>()
+                .map(Duration::from_secs)
+                .map_err(|e| format!("Invalid {} '{}': {}", PYTHON_TIMEOUT_ENV, secs, e)),
+            Err(_) => Ok(DEFAULT_PYTHON_TIMEOUT),
+        }
+    }
 
-        let mut child = Command::new(&python_executable)
-            .arg(&script_path)
+    /// Sends `command` to the script and waits up to `timeout` for its response,
+    /// killing the subprocess if it does not answer and exit in time.
+    fn run_python(
+        python_executable: &str,
+        script_path: &Path,
+        command: &Value,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let deadline = Instant::now() + timeout;
+        let mut child = Command::new(python_executable)
+            .arg(script_path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
             .map_err(|e| format!("Failed to start Python subprocess: {}", e))?;
 
-        let stdin = child
+        // Drain stderr concurrently so a chatty subprocess cannot block on a full pipe
+        let stderr_reader = child.stderr.take().map(|mut stderr| {
+            std::thread::spawn(move || {
+                let mut error_output = String::new();
+                let _ = std::io::Read::read_to_string(&mut stderr, &mut error_output);
+                error_output
+            })
+        });
+
+        let mut stdin = child
             .stdin
-            .as_mut()
+            .take()
             .ok_or_else(|| "Failed to get stdin".to_string())?;
 
         let command_str = serde_json::to_string(command)
             .map_err(|e| format!("Failed to serialize command: {}", e))?;
 
-        println!("[DEBUG] Sending command: {}", command_str);
+        debug!("Sending command: {}", command_str);
 
         writeln!(stdin, "{}", command_str)
             .map_err(|e| format!("Failed to write to stdin: {}", e))?;
+        // Close stdin so the script sees EOF after the command
+        drop(stdin);
 
         let stdout = child
             .stdout
             .take()
             .ok_or_else(|| "Failed to get stdout".to_string())?;
 
-        let reader = BufReader::new(stdout);
-        let mut response = String::new();
-
-        for line in reader.lines() {
-            let line = line.map_err(|e| format!("Failed to read stdout: {}", e))?;
-            response = line;
-            break;
-        }
+        // Read on a separate thread so a hung subprocess cannot block us past the deadline
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let first_line = BufReader::new(stdout).lines().next().transpose();
+            let _ = tx.send(first_line);
+        });
 
-        println!("[DEBUG] Received response: '{}'", response);
+        let response = match rx.recv_timeout(timeout) {
+            Ok(line) => line
+                .map_err(|e| format!("Failed to read stdout: {}", e))?
+                .unwrap_or_default(),
+            Err(_) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "Python subprocess did not respond within {:?} and was killed",
+                    timeout
+                ));
+            }
+        };
+
+        debug!("Received response: '{}'", response);
+
+        let status = loop {
+            match child
+                .try_wait()
+                .map_err(|e| format!("Failed to wait for subprocess: {}", e))?
+            {
+                Some(status) => break status,
+                None if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!(
+                        "Python subprocess did not exit within {:?} and was killed",
+                        timeout
+                    ));
+                }
+                None => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
 
-        let status = child
-            .wait()
-            .map_err(|e| format!("Failed to wait for subprocess: {}", e))?;
+        let stderr = stderr_reader
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default();
 
         if !status.success() {
-            let stderr = child
-                .stderr
-                .map(|mut stderr| {
-                    let mut error_output = String::new();
-                    std::io::Read::read_to_string(&mut stderr, &mut error_output)
-                        .map(|_| error_output)
-                        .unwrap_or_else(|_| "Unknown error".to_string())
-                })
-                .unwrap_or_else(|| "No stderr available".to_string());
-
-            return Err(format!("Python subprocess failed: {}", stderr));
+            return Err(format!(
+                "Python subprocess failed ({}){}",
+                status,
+                stderr_context(&stderr)
+            ));
         }
 
-        serde_json::from_str(&response).map_err(|e| format!("Failed to parse JSON response: {}", e))
+        if response.trim().is_empty() {
+            return Err(format!(
+                "Python subprocess produced no response{}",
+                stderr_context(&stderr)
+            ));
+        }
+
+        serde_json::from_str(&response).map_err(|e| {
+            format!(
+                "Failed to parse JSON response '{}': {}{}",
+                response,
+                e,
+                stderr_context(&stderr)
+            )
+        })
     }
 
     fn find_python_script() -> Result<PathBuf, String> {
+        if let Some(path) = std::env::var_os(PYTHON_SCRIPT_ENV) {
+            let script_path = PathBuf::from(path);
+            if !script_path.exists() {
+                return Err(format!(
+                    "{} is set to {:?}, which does not exist",
+                    PYTHON_SCRIPT_ENV, script_path
+                ));
+            }
+            return Ok(script_path);
+        }
+
         let mut script_path = std::env::current_exe()
             .map_err(|e| format!("Failed to get current executable path: {}", e))?
             .parent()
@@ -218,6 +327,10 @@ impl PythonTGA {
     }
 
     fn find_python_executable() -> Result<String, String> {
+        if let Some(executable) = std::env::var_os(PYTHON_EXECUTABLE_ENV) {
+            return Ok(executable.to_string_lossy().to_string());
+        }
+
         let mut venv_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         venv_path.push("python");
         venv_path.push("venv");
@@ -232,7 +345,7 @@ impl PythonTGA {
     }
 }
 
-#[typetag::serde]
+#[typetag::serde(name = "python_tga")]
 impl TGA for PythonTGA {
     fn train<T: IntoIterator<Item = [u8; 16]>>(seeds: T) -> Result<Self, String>
     where
@@ -261,6 +374,20 @@ impl TGA for PythonTGA {
 }
 
 
+/// Formats captured stderr as a suffix for error messages, or nothing if it is empty.
+fn stderr_context(stderr: &str) -> String {
+    let stderr = stderr.trim();
+    if stderr.is_empty() {
+        String::new()
+    } else {
+        format!("\nPython stderr:\n{}", stderr)
+    }
+}
+
+inventory::submit! {
+    crate::TgaTypeTag("python_tga")
+}
+
 static PYTHON_TGAS_INIT: Once = Once::new();
 static PYTHON_TGAS: Mutex<Vec<PythonTgaInfo>> = Mutex::new(Vec::new());
 
@@ -271,7 +398,7 @@ pub fn get_available_python_tga_infos() -> Result<Vec<PythonTgaInfo>, String> {
             *python_tgas = tgas;
         }
         Err(e) => {
-            eprintln!("Warning: Failed to query Python TGAs: {}", e);
+            warn!("Failed to query Python TGAs: {}", e);
         }
     });
 
@@ -341,4 +468,82 @@ mod tests {
             }
         }
     }
+
+    /// Writes a shell script standing in for `tga_runner.py`; it consumes the command line first.
+    fn fake_script(body: &str) -> tempfile::NamedTempFile {
+        let mut script = tempfile::NamedTempFile::new().unwrap();
+        writeln!(script, "read -r _").unwrap();
+        script.write_all(body.as_bytes()).unwrap();
+        script
+    }
+
+    #[test]
+    fn test_stderr_traceback_surfaces_in_parse_error() {
+        let script = fake_script(
+            "echo 'Traceback (most recent call last):' >&2\n\
+             echo 'ValueError: model file is corrupt' >&2\n\
+             echo 'not json'\n",
+        );
+
+        let err = PythonTGA::run_python(
+            "sh",
+            script.path(),
+            &json!({"command": "list_tgas"}),
+            DEFAULT_PYTHON_TIMEOUT,
+        )
+        .unwrap_err();
+        assert!(err.contains("Failed to parse JSON response"), "{}", err);
+        assert!(err.contains("ValueError: model file is corrupt"), "{}", err);
+    }
+
+    #[test]
+    fn test_empty_response_is_distinguished_from_invalid_json() {
+        let script = fake_script("echo 'Traceback (most recent call last):' >&2\n");
+
+        let err = PythonTGA::run_python(
+            "sh",
+            script.path(),
+            &json!({"command": "list_tgas"}),
+            DEFAULT_PYTHON_TIMEOUT,
+        )
+        .unwrap_err();
+        assert!(err.contains("produced no response"), "{}", err);
+        assert!(err.contains("Traceback"), "{}", err);
+    }
+
+    #[test]
+    fn test_hung_subprocess_times_out() {
+        let script = fake_script("exec sleep 30\n");
+
+        let started = Instant::now();
+        let err = PythonTGA::run_python(
+            "sh",
+            script.path(),
+            &json!({"command": "list_tgas"}),
+            Duration::from_millis(200),
+        )
+        .unwrap_err();
+        assert!(err.contains("did not respond"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_env_vars_override_python_search() {
+        let script = tempfile::NamedTempFile::new().unwrap();
+
+        // SAFETY: the only concurrent reader is the discovery test, which tolerates failure
+        unsafe {
+            std::env::set_var(PYTHON_SCRIPT_ENV, script.path());
+            std::env::set_var(PYTHON_EXECUTABLE_ENV, "/opt/python/bin/python3");
+        }
+        let found_script = PythonTGA::find_python_script();
+        let found_executable = PythonTGA::find_python_executable();
+        unsafe {
+            std::env::remove_var(PYTHON_SCRIPT_ENV);
+            std::env::remove_var(PYTHON_EXECUTABLE_ENV);
+        }
+
+        assert_eq!(found_script.unwrap(), script.path());
+        assert_eq!(found_executable.unwrap(), "/opt/python/bin/python3");
+    }
 }
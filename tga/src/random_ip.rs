@@ -1,10 +1,27 @@
 use crate::TGA;
 use plugin::contracts::PluginInfo;
 use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeedPrefix {
+    pub prefix: u128,
+    pub count: u64,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RandomIpTga {
+    /// Length of the seed prefixes sampled from; host bits below it are random
+    #[serde(default)]
+    prefix_length: u8,
+    /// Seed prefixes and how often each occurred. Empty means fully random.
+    #[serde(default)]
+    prefixes: Vec<SeedPrefix>,
+    #[serde(skip)]
+    distribution: OnceLock<Option<WeightedIndex<u64>>>,
 }
 
 impl PluginInfo for RandomIpTga {
@@ -17,32 +34,131 @@ impl RandomIpTga {
     pub const NAME: &'static str = "random_ip";
     pub const DESCRIPTION: &'static str =
         "Random IPv6 address generation using cryptographically secure random bytes";
+    pub const PREFIX_NAME: &'static str = "random_prefix";
+    pub const PREFIX_DESCRIPTION: &'static str =
+        "Random host bits under seed /48 prefixes, weighted by how often each prefix occurs";
+    /// Prefix length used when `random_prefix` is trained through the registry.
+    pub const DEFAULT_PREFIX_LENGTH: u8 = 48;
+
+    /// Learns how often each `/prefix_length` prefix occurs in the seeds. Generation
+    /// then picks a prefix weighted by that frequency and randomizes the host bits.
+    pub fn train_with_prefix_length<T: IntoIterator<Item = [u8; 16]>>(
+        seeds: T,
+        prefix_length: u8,
+    ) -> Result<Self, String> {
+        if prefix_length > 128 {
+            return Err(format!(
+                "Prefix length must be between 0 and 128, got {}",
+                prefix_length
+            ));
+        }
+
+        let network_mask = !Self::host_mask(prefix_length);
+        let mut counts: HashMap<u128, u64> = HashMap::new();
+        for seed in seeds {
+            let prefix = u128::from_be_bytes(seed) & network_mask;
+            *counts.entry(prefix).or_insert(0) += 1;
+        }
+        if counts.is_empty() {
+            return Err(crate::NO_SEEDS_ERROR.to_string());
+        }
+
+        let mut prefixes: Vec<SeedPrefix> = counts
+            .into_iter()
+            .map(|(prefix, count)| SeedPrefix { prefix, count })
+            .collect();
+        prefixes.sort_by_key(|p| p.prefix);
+
+        Ok(RandomIpTga {
+            prefix_length,
+            prefixes,
+            distribution: OnceLock::new(),
+        })
+    }
+
+    pub fn prefix_length(&self) -> u8 {
+        self.prefix_length
+    }
+
+    pub fn prefixes(&self) -> &[SeedPrefix] {
+        &self.prefixes
+    }
+
+    fn host_mask(prefix_length: u8) -> u128 {
+        match prefix_length {
+            0 => u128::MAX,
+            128.. => 0,
+            len => u128::MAX >> len,
+        }
+    }
+
+    fn distribution(&self) -> Option<&WeightedIndex<u64>> {
+        self.distribution
+            .get_or_init(|| WeightedIndex::new(self.prefixes.iter().map(|p| p.count)).ok())
+            .as_ref()
+    }
 }
 
-#[typetag::serde]
+#[typetag::serde(name = "random_ip")]
 impl TGA for RandomIpTga {
-    fn train<T: IntoIterator<Item = [u8; 16]>>(_seeds: T) -> Result<Self, String> {
-        Ok(RandomIpTga {})
+    /// Produces a fully random model; the seeds are only checked to be non-empty.
+    fn train<T: IntoIterator<Item = [u8; 16]>>(seeds: T) -> Result<Self, String> {
+        if seeds.into_iter().next().is_none() {
+            return Err(crate::NO_SEEDS_ERROR.to_string());
+        }
+        Ok(RandomIpTga {
+            prefix_length: 0,
+            prefixes: Vec::new(),
+            distribution: OnceLock::new(),
+        })
     }
 
     fn generate(&self) -> [u8; 16] {
         let mut rng = rand::thread_rng();
         let mut bytes = [0u8; 16];
         rng.fill(&mut bytes);
-        bytes
+
+        let Some(dist) = self.distribution() else {
+            return bytes;
+        };
+        let prefix = self.prefixes[dist.sample(&mut rng)].prefix;
+        let host = u128::from_be_bytes(bytes) & Self::host_mask(self.prefix_length);
+        (prefix | host).to_be_bytes()
+    }
+
+    /// Each seed prefix (or, untrained, the whole address space) with its host bits
+    /// counting up from zero.
+    fn enumerate(&self) -> Option<Box<dyn Iterator<Item = [u8; 16]> + '_>> {
+        let host_mask = Self::host_mask(self.prefix_length);
+        let prefixes: Vec<u128> = if self.prefixes.is_empty() {
+            vec![0]
+        } else {
+            self.prefixes.iter().map(|p| p.prefix).collect()
+        };
+        Some(Box::new(prefixes.into_iter().flat_map(move |prefix| {
+            (0..=host_mask).map(move |host| (prefix | host).to_be_bytes())
+        })))
     }
 
     fn name(&self) -> &'static str {
-        Self::NAME
+        if self.prefixes.is_empty() {
+            Self::NAME
+        } else {
+            Self::PREFIX_NAME
+        }
     }
 
     fn description(&self) -> &'static str {
-        Self::DESCRIPTION
+        if self.prefixes.is_empty() {
+            Self::DESCRIPTION
+        } else {
+            Self::PREFIX_DESCRIPTION
+        }
     }
 }
 
-fn random_ip_train_fn(addresses: Vec<[u8; 16]>) -> Box<dyn crate::TGA> {
-    Box::new(<RandomIpTga as crate::TGA>::train(addresses).expect("Training failed"))
+fn random_ip_train_fn(addresses: Vec<[u8; 16]>) -> Result<Box<dyn crate::TGA>, String> {
+    Ok(Box::new(<RandomIpTga as crate::TGA>::train(addresses)?))
 }
 
 inventory::submit! {
@@ -52,3 +168,106 @@ inventory::submit! {
         train_fn: random_ip_train_fn,
     }
 }
+
+fn random_prefix_train_fn(addresses: Vec<[u8; 16]>) -> Result<Box<dyn crate::TGA>, String> {
+    Ok(Box::new(RandomIpTga::train_with_prefix_length(
+        addresses,
+        RandomIpTga::DEFAULT_PREFIX_LENGTH,
+    )?))
+}
+
+inventory::submit! {
+    crate::TgaRegistration {
+        name: RandomIpTga::PREFIX_NAME,
+        description: RandomIpTga::PREFIX_DESCRIPTION,
+        train_fn: random_prefix_train_fn,
+    }
+}
+
+inventory::submit! {
+    crate::TgaTypeTag("random_ip")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    #[test]
+    fn test_prefix_weighted_generation_follows_seed_frequencies() {
+        let a: Ipv6Addr = "2001:db8:1::".parse().unwrap();
+        let b: Ipv6Addr = "2001:db8:2::".parse().unwrap();
+        let seeds: Vec<[u8; 16]> = (0..100u128)
+            .map(|i| {
+                let base = if i < 75 { a } else { b };
+                (u128::from(base) | i).to_be_bytes()
+            })
+            .collect();
+
+        let tga = RandomIpTga::train_with_prefix_length(seeds, 48).unwrap();
+        assert_eq!(tga.prefixes().len(), 2);
+
+        let samples = 10_000;
+        let mut in_a = 0;
+        for _ in 0..samples {
+            let addr = u128::from_be_bytes(tga.generate());
+            let prefix = addr & !RandomIpTga::host_mask(48);
+            assert!(prefix == u128::from(a) || prefix == u128::from(b));
+            if prefix == u128::from(a) {
+                in_a += 1;
+            }
+        }
+
+        let fraction = in_a as f64 / samples as f64;
+        assert!((fraction - 0.75).abs() < 0.03, "fraction was {}", fraction);
+    }
+
+    #[test]
+    fn test_registry_random_prefix_samples_under_seed_prefixes() {
+        let seeds: Vec<[u8; 16]> = ["2001:db8:1::1", "2001:db8:1::2", "2001:db8:2::1"]
+            .iter()
+            .map(|s| s.parse::<Ipv6Addr>().unwrap().octets())
+            .collect();
+
+        let tga = crate::TgaRegistry::train_tga(RandomIpTga::PREFIX_NAME, seeds.clone()).unwrap();
+        assert_eq!(tga.name(), RandomIpTga::PREFIX_NAME);
+
+        let network_mask = !RandomIpTga::host_mask(RandomIpTga::DEFAULT_PREFIX_LENGTH);
+        let seed_prefixes: Vec<u128> = seeds
+            .iter()
+            .map(|seed| u128::from_be_bytes(*seed) & network_mask)
+            .collect();
+        for _ in 0..1000 {
+            let prefix = u128::from_be_bytes(tga.generate()) & network_mask;
+            assert!(seed_prefixes.contains(&prefix), "{:x}", prefix);
+        }
+    }
+
+    #[test]
+    fn test_enumerate_covers_a_prefix_exactly_once() {
+        let seed: Ipv6Addr = "2001:db8::7".parse().unwrap();
+        let tga = RandomIpTga::train_with_prefix_length([seed.octets()], 124).unwrap();
+
+        let addresses: Vec<Ipv6Addr> = tga.enumerate().unwrap().map(Ipv6Addr::from).collect();
+        let expected: Vec<Ipv6Addr> = (0..16u128)
+            .map(|host| Ipv6Addr::from((u128::from(seed) & !0xf) | host))
+            .collect();
+        assert_eq!(addresses, expected);
+    }
+
+    #[test]
+    fn test_untrained_prefixes_are_fully_random() {
+        let seed: Ipv6Addr = "2001:db8::7".parse().unwrap();
+        let tga = <RandomIpTga as TGA>::train([seed.octets()]).unwrap();
+        assert!(tga.prefixes().is_empty());
+        assert_ne!(tga.generate(), tga.generate());
+    }
+
+    #[test]
+    fn test_empty_seeds_are_rejected() {
+        let err = <RandomIpTga as TGA>::train(Vec::new()).unwrap_err();
+        assert_eq!(err, crate::NO_SEEDS_ERROR);
+        let err = RandomIpTga::train_with_prefix_length(Vec::new(), 48).unwrap_err();
+        assert_eq!(err, crate::NO_SEEDS_ERROR);
+    }
+}
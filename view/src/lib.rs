@@ -9,19 +9,28 @@ use ratatui::{
     backend::{Backend, CrosstermBackend},
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Wrap},
 };
+use std::collections::BTreeSet;
 use std::io;
 
 struct App {
     state: TableState,
     df: DataFrame,
+    /// Indices into `df`'s columns that are hidden from the table
+    hidden: BTreeSet<usize>,
+    /// Position of the leftmost shown column among the visible ones
     scroll_x: usize,
     viewport_height: usize,
+    color: bool,
+    /// Whether moving past the last row selects the first one, and vice versa
+    wrap: bool,
+    /// Full text of the cell shown in the detail popup, while it is open
+    detail: Option<String>,
 }
 
 impl App {
-    fn new(lf: LazyFrame) -> Self {
+    fn new(lf: LazyFrame, color: bool) -> Self {
         let df = lf.collect().unwrap_or_else(|_| DataFrame::default());
         let mut state = TableState::default();
         if !df.is_empty() {
@@ -31,66 +40,173 @@ impl App {
         Self {
             state,
             df,
+            hidden: BTreeSet::new(),
             scroll_x: 0,
             viewport_height: 0,
+            color,
+            wrap: false,
+            detail: None,
         }
     }
 
-    pub fn next(&mut self) {
+    /// Number of data rows that fit in the table, excluding its borders and header.
+    fn page_size(&self) -> usize {
+        self.viewport_height.saturating_sub(3).max(1)
+    }
+
+    fn last_row(&self) -> usize {
+        self.df.height().saturating_sub(1)
+    }
+
+    /// Selects row `i` and scrolls just far enough to keep it visible.
+    fn select_row(&mut self, i: usize) {
         if self.df.is_empty() {
             return;
         }
-        let i = match self.state.selected() {
-            Some(i) => (i + 1).min(self.df.height().saturating_sub(1)),
-            None => 0,
-        };
+        let i = i.min(self.last_row());
         self.state.select(Some(i));
 
-        let row_count = self.viewport_height.saturating_sub(3);
-        if row_count > 0 {
-            let offset = self.state.offset();
-            if i >= offset + row_count {
-                *self.state.offset_mut() = offset + 1;
-            }
+        let offset = self.state.offset();
+        if i < offset {
+            *self.state.offset_mut() = i;
+        } else if self.viewport_height > 3 && i >= offset + self.page_size() {
+            *self.state.offset_mut() = i + 1 - self.page_size();
         }
     }
 
+    pub fn next(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) if i == self.last_row() && self.wrap => 0,
+            Some(i) => i + 1,
+            None => 0,
+        };
+        self.select_row(i);
+    }
+
     pub fn previous(&mut self) {
-        if self.df.is_empty() {
-            return;
-        }
         let i = match self.state.selected() {
+            Some(0) if self.wrap => self.last_row(),
             Some(i) => i.saturating_sub(1),
             None => 0,
         };
-        self.state.select(Some(i));
+        self.select_row(i);
+    }
 
-        let offset = self.state.offset();
-        if i < offset {
-            *self.state.offset_mut() = offset - 1;
+    pub fn page_down(&mut self) {
+        let i = self.state.selected().unwrap_or(0) + self.page_size();
+        self.select_row(i);
+    }
+
+    pub fn page_up(&mut self) {
+        let i = self
+            .state
+            .selected()
+            .unwrap_or(0)
+            .saturating_sub(self.page_size());
+        self.select_row(i);
+    }
+
+    pub fn first(&mut self) {
+        self.select_row(0);
+    }
+
+    pub fn last(&mut self) {
+        self.select_row(self.last_row());
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+    }
+
+    /// Opens the detail popup on the selected row's leftmost visible cell, or closes it.
+    pub fn toggle_detail(&mut self) {
+        if self.detail.take().is_some() {
+            return;
         }
+        let Some(row) = self.state.selected() else {
+            return;
+        };
+        let Some(column) = self.focused_column().map(|i| &self.df.get_columns()[i]) else {
+            return;
+        };
+        self.detail = column.get(row).ok().map(|val| match val {
+            AnyValue::String(s) => s.to_string(),
+            val => val.to_string(),
+        });
+    }
+
+    pub fn close_detail(&mut self) {
+        self.detail = None;
     }
 
     pub fn next_col(&mut self) {
         self.scroll_x = self
             .scroll_x
             .saturating_add(1)
-            .min(self.df.width().saturating_sub(1));
+            .min(self.visible_columns().len().saturating_sub(1));
     }
 
     pub fn previous_col(&mut self) {
         self.scroll_x = self.scroll_x.saturating_sub(1);
     }
+
+    /// Indices of the columns that are not hidden, in frame order.
+    fn visible_columns(&self) -> Vec<usize> {
+        (0..self.df.width())
+            .filter(|i| !self.hidden.contains(i))
+            .collect()
+    }
+
+    /// The leftmost shown column, which hiding and the detail popup act on.
+    fn focused_column(&self) -> Option<usize> {
+        self.visible_columns().get(self.scroll_x).copied()
+    }
+
+    /// Columns drawn when `max_cols` fit on screen.
+    fn shown_columns(&self, max_cols: usize) -> Vec<usize> {
+        self.visible_columns()
+            .into_iter()
+            .skip(self.scroll_x)
+            .take(max_cols)
+            .collect()
+    }
+
+    /// Hides the focused column; the last visible column is always kept.
+    pub fn hide_column(&mut self) {
+        let visible = self.visible_columns();
+        if visible.len() <= 1 {
+            return;
+        }
+        if let Some(i) = self.focused_column() {
+            self.hidden.insert(i);
+        }
+        self.scroll_x = self.scroll_x.min(visible.len() - 2);
+    }
+
+    /// Names of the columns drawn when `max_cols` fit on screen.
+    fn header_names(&self, max_cols: usize) -> Vec<String> {
+        let names = self.df.get_column_names();
+        self.shown_columns(max_cols)
+            .into_iter()
+            .map(|i| names[i].to_string())
+            .collect()
+    }
+
+    pub fn show_all_columns(&mut self) {
+        self.hidden.clear();
+    }
 }
 
-pub fn run_tui(lf: LazyFrame) -> io::Result<()> {
+/// Opens the table explorer on `lf`; with `color` false no colors or text styles are
+/// used, leaving the `>>` marker to show the selected row.
+pub fn run_tui(lf: LazyFrame, color: bool) -> io::Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(lf);
+    let mut app = App::new(lf, color);
     let res = run_app(&mut terminal, &mut app);
 
     disable_raw_mode()?;
@@ -102,7 +218,7 @@ pub fn run_tui(lf: LazyFrame) -> io::Result<()> {
     terminal.show_cursor()?;
 
     if let Err(err) = res {
-        println!("Error: {:?}", err)
+        eprintln!("Error: {:?}", err)
     }
 
     Ok(())
@@ -113,12 +229,28 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
         terminal.draw(|f| ui(f, app))?;
 
         if let Event::Key(key) = event::read()? {
+            if app.detail.is_some() {
+                match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Esc | KeyCode::Enter => app.close_detail(),
+                    _ => {}
+                }
+                continue;
+            }
             match key.code {
                 KeyCode::Char('q') => return Ok(()),
                 KeyCode::Left => app.previous_col(),
                 KeyCode::Right => app.next_col(),
-                KeyCode::Down => app.next(),
-                KeyCode::Up => app.previous(),
+                KeyCode::Down | KeyCode::Char('j') => app.next(),
+                KeyCode::Up | KeyCode::Char('k') => app.previous(),
+                KeyCode::PageDown => app.page_down(),
+                KeyCode::PageUp => app.page_up(),
+                KeyCode::Home | KeyCode::Char('g') => app.first(),
+                KeyCode::End | KeyCode::Char('G') => app.last(),
+                KeyCode::Char('w') => app.toggle_wrap(),
+                KeyCode::Enter => app.toggle_detail(),
+                KeyCode::Char('x') => app.hide_column(),
+                KeyCode::Char('r') => app.show_all_columns(),
                 _ => {}
             }
         }
@@ -134,29 +266,72 @@ fn ui(f: &mut Frame, app: &mut App) {
 
     draw_table(f, app, chunks[0]);
 
-    let help_text = "Use arrow keys to navigate rows/cols, 'q' to quit.";
+    let help_text = format!(
+        "Arrows/j/k: move, PgUp/PgDn: page, Home/End or g/G: first/last row, \
+         'w': wrap around ({}), Enter: show full cell, \
+         'x': hide column, 'r': show all columns, 'q': quit.",
+        if app.wrap { "on" } else { "off" }
+    );
     let help_message =
         Paragraph::new(help_text).block(Block::default().borders(Borders::ALL).title("Help"));
     f.render_widget(help_message, chunks[1]);
+
+    if let Some(detail) = &app.detail {
+        let area = centered_rect(60, 40, f.size());
+        let popup = Paragraph::new(detail.as_str())
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Cell (Esc to close)"),
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+}
+
+/// A `Rect` of `percent_x` by `percent_y` of `area`, centered in it.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
 }
 
 fn draw_table(f: &mut Frame, app: &mut App, area: Rect) {
     app.viewport_height = area.height as usize;
 
-    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
-    let header_style = Style::default()
-        .fg(Color::Blue)
-        .add_modifier(Modifier::BOLD);
+    let (selected_style, header_style) = if app.color {
+        (
+            Style::default().add_modifier(Modifier::REVERSED),
+            Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        (Style::default(), Style::default())
+    };
 
     let max_cols = (area.width / 20).max(1) as usize;
 
+    let shown = app.shown_columns(max_cols);
+
     let header_cells: Vec<Cell> = app
-        .df
-        .get_column_names()
-        .iter()
-        .skip(app.scroll_x)
-        .take(max_cols)
-        .map(|h| Cell::from(h.to_string()).style(header_style))
+        .header_names(max_cols)
+        .into_iter()
+        .map(|h| Cell::from(h).style(header_style))
         .collect();
 
     let header = Row::new(header_cells).height(1);
@@ -168,12 +343,9 @@ fn draw_table(f: &mut Frame, app: &mut App, area: Rect) {
     let rows: Vec<ratatui::widgets::Row> = (start_row..end_row)
         .map(|i| {
             let polars_row = app.df.get_row(i).unwrap();
-            let cells: Vec<Cell> = polars_row
-                .0
+            let cells: Vec<Cell> = shown
                 .iter()
-                .skip(app.scroll_x)
-                .take(max_cols)
-                .map(|val| Cell::from(val.to_string()))
+                .map(|&col| Cell::from(polars_row.0[col].to_string()))
                 .collect();
             ratatui::widgets::Row::new(cells).height(1)
         })
@@ -212,3 +384,94 @@ fn draw_table(f: &mut Frame, app: &mut App, area: Rect) {
     app.state.select(abs_sel);
     *app.state.offset_mut() = abs_offset;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_with_rows(rows: u32) -> App {
+        let df =
+            DataFrame::new(vec![Column::new("n".into(), (0..rows).collect::<Vec<_>>())]).unwrap();
+        let mut app = App::new(df.lazy(), true);
+        // Room for 10 data rows once the borders and header are drawn
+        app.viewport_height = 13;
+        app
+    }
+
+    #[test]
+    fn test_page_down_advances_by_a_viewport() {
+        let mut app = app_with_rows(100);
+        app.page_down();
+        assert_eq!(app.state.selected(), Some(10));
+        assert_eq!(app.state.offset(), 1);
+
+        app.page_up();
+        assert_eq!(app.state.selected(), Some(0));
+        assert_eq!(app.state.offset(), 0);
+    }
+
+    #[test]
+    fn test_end_selects_the_last_row() {
+        let mut app = app_with_rows(100);
+        app.last();
+        assert_eq!(app.state.selected(), Some(99));
+        assert_eq!(app.state.offset(), 90);
+
+        app.next();
+        assert_eq!(app.state.selected(), Some(99));
+        app.first();
+        assert_eq!(app.state.selected(), Some(0));
+    }
+
+    #[test]
+    fn test_detail_captures_the_full_selected_cell() {
+        let raw = "2001:db8:1234:5678:9abc:def0:1234:5678,icmp,echo-reply,64";
+        let df = DataFrame::new(vec![
+            Column::new("n".into(), &[0u32, 1]),
+            Column::new("raw".into(), &["short", raw]),
+        ])
+        .unwrap();
+        let mut app = App::new(df.lazy(), true);
+        app.next();
+        app.next_col();
+
+        app.toggle_detail();
+        assert_eq!(app.detail.as_deref(), Some(raw));
+        app.toggle_detail();
+        assert_eq!(app.detail, None);
+    }
+
+    #[test]
+    fn test_hidden_columns_are_left_out_of_the_header() {
+        let df = DataFrame::new(vec![
+            Column::new("saddr".into(), &["2001:db8::1"]),
+            Column::new("raw".into(), &["..."]),
+            Column::new("ttl".into(), &[64u32]),
+        ])
+        .unwrap();
+        let mut app = App::new(df.lazy(), true);
+
+        app.next_col();
+        app.hide_column();
+        assert_eq!(app.header_names(10), ["ttl"]);
+        app.previous_col();
+        assert_eq!(app.header_names(10), ["saddr", "ttl"]);
+        assert_eq!(app.df.width(), 3);
+
+        app.show_all_columns();
+        assert_eq!(app.header_names(10), ["saddr", "raw", "ttl"]);
+    }
+
+    #[test]
+    fn test_wrap_around_at_the_boundaries() {
+        let mut app = app_with_rows(5);
+        app.previous();
+        assert_eq!(app.state.selected(), Some(0));
+
+        app.toggle_wrap();
+        app.previous();
+        assert_eq!(app.state.selected(), Some(4));
+        app.next();
+        assert_eq!(app.state.selected(), Some(0));
+    }
+}